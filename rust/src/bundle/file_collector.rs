@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs::read_to_string;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use pyo3::prelude::*;
 use pyo3::pyclass;
@@ -14,8 +15,11 @@ use ruff_python_ast::visitor::walk_stmt;
 use crate::common::ast::checkers::is_dynamic_import;
 use crate::common::ast::checkers::is_importlib_import;
 use crate::common::ast::{get_import_from_absolute_module_spec, parse_python_source};
+use crate::common::cache::{FileCache, hash_content};
 use crate::common::module_spec::remove_last_segment;
-use crate::common::module_spec::{get_file_for_module_spec, get_parent_package, is_in_std_lib};
+use crate::common::module_spec::{
+    get_file_for_module_spec, get_parent_package, is_builtin, is_in_std_lib, parse_dotted_reference,
+};
 
 #[pyclass]
 pub struct FileCollector {
@@ -26,41 +30,174 @@ pub struct FileCollector {
     importlib_package_alias: Option<String>,
     import_aliases: HashMap<String, String>,
     module_aliases: HashMap<String, HashSet<String>>,
+    vendor_stdlib: bool,
+    cache: Option<Arc<FileCache>>,
+    // module specs discovered while visiting a single file
+    discovered_modules: Vec<String>,
+    // every module spec an import could refer to, import aliases added later are resolved against these
+    requested_modules: HashSet<String>,
+    // follow string literals which look like import paths, e.g. `"pkg.module:attr"`
+    string_references: bool,
+    // string literals treated as references, mapped to the module spec they resolved to
+    #[pyo3(get)]
+    found_string_references: HashMap<String, String>,
+    // string literals of a single file which look like import paths, they are cached instead
+    // of the modules they resolve to, as that depends on which modules exist
+    string_literals: Vec<String>,
+    // module specs string literals of a single file resolved to
+    referenced_modules: Vec<String>,
+    // module specs requested from python, everything else is collected because they import it
+    roots: HashSet<String>,
+    // modules imported or referenced by every collected python file
+    imported_modules: HashMap<(String, PathBuf), Vec<String>>,
+    // module specs of invalidated files, until they were processed again
+    invalidated_modules: HashSet<String>,
 }
 #[pymethods]
 impl FileCollector {
     #[new]
-    fn new(package: String, import_aliases: HashMap<String, String>) -> Self {
-        let mut module_aliases: HashMap<String, HashSet<String>> = HashMap::new();
-        for (search, replacement) in &import_aliases {
-            let (search_module, replacement_module) = (
-                remove_last_segment(search),
-                remove_last_segment(replacement),
-            );
-            match module_aliases.get_mut(search_module) {
-                Some(replacements) => {
-                    replacements.insert(replacement_module.to_owned());
-                }
-                None => {
-                    module_aliases.insert(
-                        search_module.to_owned(),
-                        HashSet::from([replacement_module.to_owned()]),
-                    );
-                }
-            };
-        }
-
-        FileCollector {
+    #[pyo3(signature = (
+        package,
+        import_aliases,
+        vendor_stdlib=false,
+        cache_dir=None,
+        cache_namespace=String::new(),
+        string_references=false,
+    ))]
+    fn new(
+        package: String,
+        import_aliases: HashMap<String, String>,
+        vendor_stdlib: bool,
+        cache_dir: Option<PathBuf>,
+        cache_namespace: String,
+        string_references: bool,
+    ) -> Self {
+        let mut collector = FileCollector {
             package,
             collected_files: HashMap::new(),
             importlib_package_alias: None,
-            import_aliases: import_aliases,
-            module_aliases: module_aliases,
-        }
+            import_aliases: HashMap::new(),
+            module_aliases: HashMap::new(),
+            vendor_stdlib,
+            cache: cache_dir.map(|cache_dir| Arc::new(FileCache::new(cache_dir, &cache_namespace))),
+            discovered_modules: Vec::new(),
+            requested_modules: HashSet::new(),
+            string_references,
+            found_string_references: HashMap::new(),
+            string_literals: Vec::new(),
+            referenced_modules: Vec::new(),
+            roots: HashSet::new(),
+            imported_modules: HashMap::new(),
+            invalidated_modules: HashSet::new(),
+        };
+        collector.insert_import_aliases(import_aliases);
+        collector
     }
 
     fn _process_module(&mut self, module_spec: &str) {
-        if is_in_std_lib(module_spec) {
+        self.roots.insert(module_spec.to_owned());
+        self.process_module(module_spec);
+    }
+
+    /// Forgets collected files with the given paths, so they are read again
+    /// by the next `refresh` call.
+    /// Returns the module specs of the forgotten files.
+    fn invalidate(&mut self, paths: HashSet<PathBuf>) -> Vec<String> {
+        let invalidated_keys: Vec<(String, PathBuf)> = self
+            .collected_files
+            .keys()
+            .filter(|(_, path)| paths.contains(path))
+            .cloned()
+            .collect();
+        for key in &invalidated_keys {
+            self.collected_files.remove(key);
+            self.imported_modules.remove(key);
+        }
+        let invalidated_modules: Vec<String> = invalidated_keys
+            .into_iter()
+            .map(|(module_spec, _)| module_spec)
+            .collect();
+        self.invalidated_modules
+            .extend(invalidated_modules.iter().cloned());
+        invalidated_modules
+    }
+
+    /// Processes the invalidated modules again and forgets the collected files which are
+    /// no longer imported, directly or transitively, by a module passed to `_process_module`.
+    /// Returns the module specs of the forgotten files.
+    fn refresh(&mut self) -> Vec<String> {
+        let mut invalidated_modules: Vec<String> =
+            self.invalidated_modules.iter().cloned().collect();
+        invalidated_modules.sort();
+        for module_spec in invalidated_modules {
+            // a module stays invalidated if processing it fails, e.g. on a syntax error
+            self.process_module(&module_spec);
+            self.invalidated_modules.remove(&module_spec);
+        }
+
+        let collected_keys: HashMap<String, (String, PathBuf)> = self
+            .collected_files
+            .keys()
+            .map(|key| (key.0.clone(), key.clone()))
+            .collect();
+        let mut reachable_keys: HashSet<(String, PathBuf)> = HashSet::new();
+        let mut pending_modules: Vec<String> = self.roots.iter().cloned().collect();
+        while let Some(module_spec) = pending_modules.pop() {
+            let Some(key) = collected_keys.get(&module_spec) else {
+                continue;
+            };
+            if !reachable_keys.insert(key.clone()) {
+                continue;
+            }
+            if let Some(imported_modules) = self.imported_modules.get(key) {
+                pending_modules.extend(imported_modules.iter().cloned());
+            }
+        }
+
+        let unreachable_keys: Vec<(String, PathBuf)> = self
+            .collected_files
+            .keys()
+            .filter(|key| !reachable_keys.contains(*key))
+            .cloned()
+            .collect();
+        for key in &unreachable_keys {
+            self.collected_files.remove(key);
+            self.imported_modules.remove(key);
+        }
+        let mut removed_modules: Vec<String> = unreachable_keys
+            .into_iter()
+            .map(|(module_spec, _)| module_spec)
+            .collect();
+        removed_modules.sort();
+        removed_modules
+    }
+
+    /// Adds import aliases after modules were collected, e.g. from ecosystem plugins
+    /// of distributions found in the bundle. Aliases of already imported modules are
+    /// collected right away. Existing aliases are never replaced.
+    fn add_import_aliases(&mut self, import_aliases: HashMap<String, String>) {
+        let new_aliases: HashMap<String, String> = import_aliases
+            .into_iter()
+            .filter(|(search, _)| !self.import_aliases.contains_key(search))
+            .collect();
+        self.insert_import_aliases(new_aliases);
+        let aliased_modules: Vec<String> = self
+            .requested_modules
+            .iter()
+            .flat_map(|requested_module| self.get_aliased_modules(requested_module))
+            .collect();
+        for aliased_module in aliased_modules {
+            // not imported by a single file, so the aliased modules are kept like requested ones
+            self.roots.insert(aliased_module.clone());
+            self.discover_module(&aliased_module);
+        }
+    }
+}
+
+impl FileCollector {
+    fn process_module(&mut self, module_spec: &str) {
+        // builtin modules are compiled into the interpreter and never have a file
+        if is_builtin(module_spec) || (is_in_std_lib(module_spec) && !self.vendor_stdlib) {
             return;
         }
 
@@ -79,7 +216,8 @@ impl FileCollector {
                     .is_some_and(|extension| extension == "py")
                 {
                     if let Ok(file_content) = read_to_string(&file_origin) {
-                        self.collected_files.insert(key, Some(file_content.clone()));
+                        self.collected_files
+                            .insert(key.clone(), Some(file_content.clone()));
 
                         let mut next_parent_package = get_parent_package(&module_name).to_string();
                         if file_origin.file_name().is_some_and(|file_name| {
@@ -93,12 +231,75 @@ impl FileCollector {
                             importlib_package_alias: None,
                             import_aliases: self.import_aliases.to_owned(),
                             module_aliases: self.module_aliases.to_owned(),
+                            vendor_stdlib: self.vendor_stdlib,
+                            cache: self.cache.clone(),
+                            discovered_modules: Vec::new(),
+                            requested_modules: HashSet::new(),
+                            string_references: self.string_references,
+                            found_string_references: HashMap::new(),
+                            string_literals: Vec::new(),
+                            referenced_modules: Vec::new(),
+                            roots: HashSet::new(),
+                            imported_modules: HashMap::new(),
+                            invalidated_modules: HashSet::new(),
                         };
-                        let module = parse_python_source(&file_content).unwrap().expect_module();
-                        for stmt in &module.body {
-                            sub_collector.visit_stmt(stmt);
+                        let cache_key = hash_content(&[&sub_collector.package, &file_content]);
+                        match self
+                            .cache
+                            .as_ref()
+                            .and_then(|cache| cache.get("imports", &cache_key))
+                        {
+                            Some(discovered_modules) => {
+                                for discovered_module in discovered_modules.lines() {
+                                    sub_collector.discover_module(discovered_module);
+                                    // cached imports might predate import aliases added later on
+                                    for aliased_module in
+                                        sub_collector.get_aliased_modules(discovered_module)
+                                    {
+                                        sub_collector.discover_module(&aliased_module);
+                                    }
+                                }
+                                if let Some(string_literals) = self
+                                    .cache
+                                    .as_ref()
+                                    .and_then(|cache| cache.get("string_literals", &cache_key))
+                                {
+                                    for string_literal in string_literals.lines() {
+                                        sub_collector.discover_string_reference(string_literal);
+                                    }
+                                }
+                            }
+                            None => {
+                                let module =
+                                    parse_python_source(&file_content).unwrap().expect_module();
+                                for stmt in &module.body {
+                                    sub_collector.visit_stmt(stmt);
+                                }
+                                if let Some(cache) = &self.cache {
+                                    cache.set(
+                                        "imports",
+                                        &cache_key,
+                                        &sub_collector.discovered_modules.join("\n"),
+                                    );
+                                    if self.string_references {
+                                        cache.set(
+                                            "string_literals",
+                                            &cache_key,
+                                            &sub_collector.string_literals.join("\n"),
+                                        );
+                                    }
+                                }
+                            }
                         }
+                        let mut imported_modules = sub_collector.discovered_modules;
+                        imported_modules.extend(sub_collector.referenced_modules);
+                        self.imported_modules.extend(sub_collector.imported_modules);
+                        self.imported_modules.insert(key, imported_modules);
                         self.collected_files.extend(sub_collector.collected_files);
+                        self.requested_modules
+                            .extend(sub_collector.requested_modules);
+                        self.found_string_references
+                            .extend(sub_collector.found_string_references);
                     }
                 } else {
                     self.collected_files.insert(key, None);
@@ -106,6 +307,69 @@ impl FileCollector {
             }
         };
     }
+
+    fn insert_import_aliases(&mut self, import_aliases: HashMap<String, String>) {
+        for (search, replacement) in &import_aliases {
+            let (search_module, replacement_module) = (
+                remove_last_segment(search),
+                remove_last_segment(replacement),
+            );
+            match self.module_aliases.get_mut(search_module) {
+                Some(replacements) => {
+                    replacements.insert(replacement_module.to_owned());
+                }
+                None => {
+                    self.module_aliases.insert(
+                        search_module.to_owned(),
+                        HashSet::from([replacement_module.to_owned()]),
+                    );
+                }
+            };
+        }
+        self.import_aliases.extend(import_aliases);
+    }
+
+    fn get_aliased_modules(&self, module_spec: &str) -> Vec<String> {
+        let mut aliased_modules: Vec<String> = Vec::new();
+        if let Some(alias) = self.import_aliases.get(module_spec) {
+            aliased_modules.push(alias.to_owned());
+        }
+        if let Some(aliases) = self.module_aliases.get(module_spec) {
+            aliased_modules.extend(aliases.iter().cloned());
+        }
+        aliased_modules
+    }
+
+    fn discover_module(&mut self, module_spec: &str) {
+        self.discovered_modules.push(module_spec.to_owned());
+        self.requested_modules.insert(module_spec.to_owned());
+        self.process_module(module_spec);
+    }
+
+    /// Strings are only references if they resolve to a module in the environment,
+    /// which is either the module itself or the module containing the attribute
+    fn discover_string_reference(&mut self, value: &str) {
+        let Some(reference) = parse_dotted_reference(value) else {
+            return;
+        };
+        self.string_literals.push(value.to_owned());
+        if is_builtin(&reference.fqn) || (is_in_std_lib(&reference.fqn) && !self.vendor_stdlib) {
+            return;
+        }
+        let Some(module_spec) = reference
+            .module_specs
+            .into_iter()
+            .find(|module_spec| get_file_for_module_spec(module_spec).is_some())
+        else {
+            return;
+        };
+        self.found_string_references
+            .insert(value.to_owned(), module_spec.clone());
+        // not a discovered import, the cached imports must not depend on which modules exist
+        self.requested_modules.insert(module_spec.clone());
+        self.referenced_modules.push(module_spec.clone());
+        self.process_module(&module_spec);
+    }
 }
 
 impl Visitor<'_> for FileCollector {
@@ -115,11 +379,16 @@ impl Visitor<'_> for FileCollector {
         {
             match dynamic_import_expr {
                 Expr::StringLiteral(literal) => {
-                    self._process_module(literal.value.to_str());
+                    self.discover_module(literal.value.to_str());
                 }
                 _ => {}
             }
         }
+        if let Expr::StringLiteral(literal) = expr {
+            if self.string_references {
+                self.discover_string_reference(literal.value.to_str());
+            }
+        }
         walk_expr(self, expr);
     }
 
@@ -133,14 +402,14 @@ impl Visitor<'_> for FileCollector {
 
                 for name in &import.names {
                     modules.insert(name.name.to_string());
-                    self._process_module(&name.name);
+                    self.discover_module(&name.name);
                     if let Some(aliases) = self.module_aliases.get(name.name.as_str()) {
                         modules.extend(aliases.iter().cloned());
                     }
                 }
 
                 for module in modules {
-                    self._process_module(&module);
+                    self.discover_module(&module);
                 }
             }
             Stmt::ImportFrom(import_from) => {
@@ -174,7 +443,7 @@ impl Visitor<'_> for FileCollector {
                     }
 
                     for potential_module in potential_modules {
-                        self._process_module(&potential_module);
+                        self.discover_module(&potential_module);
                     }
                 }
             }
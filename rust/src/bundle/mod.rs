@@ -1 +1,7 @@
+#[cfg(feature = "python")]
 pub mod file_collector;
+pub mod shared_libraries;
+#[cfg(feature = "python")]
+pub mod watcher;
+#[cfg(feature = "python")]
+pub mod zipapp;
@@ -0,0 +1,238 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use goblin::elf::Elf;
+#[cfg(feature = "python")]
+use pyo3::{pyclass, pymethods};
+
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+
+// libraries of the manylinux policy which are part of glibc, they have to match the
+// dynamic loader of the host and must neither be bundled nor reported as missing
+const SYSTEM_LIBRARY_PREFIXES: [&str; 8] = [
+    "libc.so",
+    "libm.so",
+    "libdl.so",
+    "libpthread.so",
+    "librt.so",
+    "libutil.so",
+    "ld-linux",
+    "ld64.so",
+];
+
+fn is_system_library(name: &str) -> bool {
+    SYSTEM_LIBRARY_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+/// Absolute paths of objects inside a sysroot refer to the sysroot, not to the host
+fn in_sysroot(path: PathBuf, sysroot: Option<&Path>) -> PathBuf {
+    match sysroot {
+        Some(sysroot) if path.is_absolute() && !path.starts_with(sysroot) => {
+            sysroot.join(path.strip_prefix("/").unwrap_or(&path))
+        }
+        _ => path,
+    }
+}
+
+struct DynamicSection {
+    needed: Vec<String>,
+    search_paths: Vec<PathBuf>,
+}
+
+fn expand_origin(search_path: &str, origin: &Path) -> PathBuf {
+    let origin = origin.to_string_lossy();
+    PathBuf::from(
+        search_path
+            .replace("${ORIGIN}", &origin)
+            .replace("$ORIGIN", &origin),
+    )
+}
+
+fn read_dynamic_section(
+    path: &Path,
+    sysroot: Option<&Path>,
+) -> Result<Option<DynamicSection>, std::io::Error> {
+    let bytes = fs::read(path)?;
+    if !bytes.starts_with(ELF_MAGIC) {
+        return Ok(None);
+    }
+    let Ok(elf) = Elf::parse(&bytes) else {
+        return Ok(None);
+    };
+    let origin = path.parent().unwrap_or(Path::new(""));
+    // DT_RPATH is ignored by the dynamic loader if DT_RUNPATH is present
+    let search_paths = if elf.runpaths.is_empty() {
+        &elf.rpaths
+    } else {
+        &elf.runpaths
+    };
+
+    Ok(Some(DynamicSection {
+        needed: elf.libraries.iter().map(|lib| lib.to_string()).collect(),
+        search_paths: search_paths
+            .iter()
+            .flat_map(|paths| paths.split(':'))
+            .filter(|search_path| !search_path.is_empty())
+            .map(|search_path| in_sysroot(expand_origin(search_path, origin), sysroot))
+            .collect(),
+    }))
+}
+
+/// Follows `DT_NEEDED` entries of ELF objects inside a bundle.
+///
+/// Libraries are looked up in the `RPATH`/`RUNPATH` of the requiring object first
+/// and in `library_dirs` afterwards, search paths of objects inside the `sysroot` are
+/// resolved inside of it. Libraries which were found outside of the bundle need to be
+/// copied, libraries which were not found at all are expected to be provided by the
+/// base image. glibc itself is always provided by the base image.
+#[cfg_attr(feature = "python", pyclass)]
+pub struct SharedLibrariesCollector {
+    bundle_dir: PathBuf,
+    library_dirs: Vec<PathBuf>,
+    sysroot: Option<PathBuf>,
+    processed_files: HashSet<PathBuf>,
+    bundled_libraries: HashSet<String>,
+    found_libraries: HashMap<String, PathBuf>,
+    missing_libraries: HashMap<String, HashSet<PathBuf>>,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl SharedLibrariesCollector {
+    #[new]
+    #[pyo3(signature = (bundle_dir, library_dirs, sysroot=None))]
+    fn py_new(bundle_dir: PathBuf, library_dirs: Vec<PathBuf>, sysroot: Option<PathBuf>) -> Self {
+        Self::new(bundle_dir, library_dirs, sysroot)
+    }
+
+    #[pyo3(name = "process_file")]
+    fn py_process_file(&mut self, path: PathBuf) -> Result<(), std::io::Error> {
+        self.process_file(path)
+    }
+
+    #[getter(bundled_libraries)]
+    fn get_bundled_libraries(&self) -> HashSet<String> {
+        self.bundled_libraries.clone()
+    }
+
+    #[getter(found_libraries)]
+    fn get_found_libraries(&self) -> HashMap<String, PathBuf> {
+        self.found_libraries.clone()
+    }
+
+    #[getter(missing_libraries)]
+    fn get_missing_libraries(&self) -> HashMap<String, HashSet<PathBuf>> {
+        self.missing_libraries.clone()
+    }
+}
+
+impl SharedLibrariesCollector {
+    pub fn new(bundle_dir: PathBuf, library_dirs: Vec<PathBuf>, sysroot: Option<PathBuf>) -> Self {
+        SharedLibrariesCollector {
+            bundle_dir,
+            library_dirs,
+            sysroot,
+            processed_files: HashSet::new(),
+            bundled_libraries: HashSet::new(),
+            found_libraries: HashMap::new(),
+            missing_libraries: HashMap::new(),
+        }
+    }
+
+    pub fn process_file(&mut self, path: PathBuf) -> Result<(), std::io::Error> {
+        if !self.processed_files.insert(path.clone()) {
+            return Ok(());
+        }
+        let sysroot = self
+            .sysroot
+            .clone()
+            .filter(|sysroot| path.starts_with(sysroot));
+        let Some(dynamic_section) = read_dynamic_section(&path, sysroot.as_deref())? else {
+            return Ok(());
+        };
+
+        for needed in dynamic_section.needed {
+            if is_system_library(&needed)
+                || self.bundled_libraries.contains(&needed)
+                || self.found_libraries.contains_key(&needed)
+            {
+                continue;
+            }
+
+            match self.resolve_library(&needed, &dynamic_section.search_paths, sysroot.as_deref()) {
+                Some(library_path) => {
+                    self.missing_libraries.remove(&needed);
+                    if library_path.starts_with(&self.bundle_dir) {
+                        self.bundled_libraries.insert(needed);
+                    } else {
+                        self.found_libraries.insert(needed, library_path.clone());
+                    }
+                    self.process_file(library_path)?;
+                }
+                None => {
+                    self.missing_libraries
+                        .entry(needed)
+                        .or_default()
+                        .insert(path.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_library(
+        &self,
+        name: &str,
+        search_paths: &[PathBuf],
+        sysroot: Option<&Path>,
+    ) -> Option<PathBuf> {
+        if name.contains('/') {
+            let path = in_sysroot(PathBuf::from(name), sysroot);
+            return path.is_file().then_some(path);
+        }
+        search_paths
+            .iter()
+            .chain(self.library_dirs.iter())
+            .map(|dir| dir.join(name))
+            .find(|candidate| candidate.is_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{in_sysroot, is_system_library};
+
+    #[test]
+    fn glibc_is_a_system_library() {
+        assert!(is_system_library("libc.so.6"));
+        assert!(is_system_library("libpthread.so.0"));
+        assert!(is_system_library("ld-linux-x86-64.so.2"));
+        assert!(!is_system_library("libcrypto.so.3"));
+        assert!(!is_system_library("libmagic.so.1"));
+    }
+
+    #[test]
+    fn resolves_absolute_paths_inside_the_sysroot() {
+        let sysroot = Path::new("/opt/sysroot");
+        assert_eq!(
+            in_sysroot(PathBuf::from("/usr/lib/libfoo"), Some(sysroot)),
+            PathBuf::from("/opt/sysroot/usr/lib/libfoo")
+        );
+        // $ORIGIN of an object in the sysroot is already inside of it
+        assert_eq!(
+            in_sysroot(PathBuf::from("/opt/sysroot/usr/lib"), Some(sysroot)),
+            PathBuf::from("/opt/sysroot/usr/lib")
+        );
+        assert_eq!(
+            in_sysroot(PathBuf::from("/usr/lib"), None),
+            PathBuf::from("/usr/lib")
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Mutex,
+        mpsc::{Receiver, channel},
+    },
+    time::Duration,
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use pyo3::{exceptions::PyOSError, prelude::*};
+
+// editors tend to write a file in multiple steps, wait for these to settle down
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(100);
+
+/// Watches files and directories for changes, using inotify on linux.
+///
+/// Waiting for changes releases the GIL, so other python threads keep running.
+#[pyclass]
+pub struct FileWatcher {
+    watcher: Mutex<RecommendedWatcher>,
+    receiver: Mutex<Receiver<notify::Result<Event>>>,
+}
+
+#[pymethods]
+impl FileWatcher {
+    #[new]
+    fn new() -> PyResult<Self> {
+        let (sender, receiver) = channel();
+        let watcher =
+            notify::recommended_watcher(sender).map_err(|e| PyOSError::new_err(e.to_string()))?;
+        Ok(FileWatcher {
+            watcher: Mutex::new(watcher),
+            receiver: Mutex::new(receiver),
+        })
+    }
+
+    #[pyo3(signature = (path, recursive=true))]
+    fn watch(&self, path: PathBuf, recursive: bool) -> PyResult<()> {
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        self.watcher
+            .lock()
+            .unwrap()
+            .watch(&path, mode)
+            .map_err(|e| PyOSError::new_err(e.to_string()))
+    }
+
+    /// Blocks until a change happened or `timeout_ms` passed.
+    /// Returns the changed paths, which is empty if the timeout was hit.
+    fn wait_for_changes(&self, py: Python<'_>, timeout_ms: u64) -> Vec<PathBuf> {
+        py.detach(|| {
+            let receiver = self.receiver.lock().unwrap();
+            let mut changed_paths: Vec<PathBuf> = Vec::new();
+            let mut timeout = Duration::from_millis(timeout_ms);
+            while let Ok(event) = receiver.recv_timeout(timeout) {
+                let Ok(event) = event else {
+                    continue;
+                };
+                if matches!(event.kind, EventKind::Access(_)) {
+                    continue;
+                }
+                for path in event.paths {
+                    if !changed_paths.contains(&path) {
+                        changed_paths.push(path);
+                    }
+                }
+                if !changed_paths.is_empty() {
+                    timeout = DEBOUNCE_DURATION;
+                }
+            }
+            changed_paths
+        })
+    }
+}
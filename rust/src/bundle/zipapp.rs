@@ -0,0 +1,118 @@
+use std::{
+    fs,
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+};
+
+use pyo3::pyfunction;
+use zip::{CompressionMethod, DateTime, ZipWriter, write::SimpleFileOptions};
+
+const NATIVE_EXTENSIONS: [&str; 2] = ["so", "pyd"];
+const SKIPPED_FILE_NAMES: [&str; 1] = [".gitignore"];
+
+fn is_native_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| NATIVE_EXTENSIONS.iter().any(|e| extension == *e))
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if !path
+            .file_name()
+            .is_some_and(|name| SKIPPED_FILE_NAMES.iter().any(|n| name == *n))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn archive_name(source_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(source_dir)
+        .unwrap()
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// top-level entries which cannot be imported by zipimport:
+/// packages containing extension modules, extension modules themselves and `.libs` directories
+fn find_native_top_levels(names: &[(String, PathBuf)]) -> Vec<String> {
+    let mut native_top_levels: Vec<String> = Vec::new();
+    for (name, path) in names {
+        let top_level = name.split('/').next().unwrap();
+        if (is_native_file(path) || top_level.ends_with(".libs"))
+            && !native_top_levels.iter().any(|n| n == top_level)
+        {
+            native_top_levels.push(top_level.to_owned());
+        }
+    }
+    native_top_levels
+}
+
+/// Writes a PEP 441 zipapp from `source_dir` to `target_path`.
+///
+/// Entries are sorted and written with a fixed timestamp and fixed permissions,
+/// so the same input always produces the same archive.
+/// Top-level entries that need native code are stored below `native_prefix`,
+/// the generated `__main__.py` is responsible for extracting them at runtime.
+#[pyfunction]
+#[pyo3(signature = (source_dir, target_path, main_source, native_prefix, interpreter=None))]
+pub fn write_zipapp(
+    source_dir: PathBuf,
+    target_path: PathBuf,
+    main_source: String,
+    native_prefix: String,
+    interpreter: Option<String>,
+) -> Result<(), std::io::Error> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    collect_files(&source_dir, &mut files)?;
+    let mut names: Vec<(String, PathBuf)> = files
+        .into_iter()
+        .map(|path| (archive_name(&source_dir, &path), path))
+        .filter(|(name, _)| name != "__main__.py")
+        .collect();
+    names.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let native_top_levels = find_native_top_levels(&names);
+
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(0o644);
+    let native_options = options.unix_permissions(0o755);
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer.start_file("__main__.py", options)?;
+    writer.write_all(main_source.as_bytes())?;
+
+    for (name, path) in &names {
+        let top_level = name.split('/').next().unwrap();
+        if native_top_levels.iter().any(|n| n == top_level) {
+            writer.start_file(format!("{}{}", native_prefix, name), native_options)?;
+        } else {
+            writer.start_file(name.as_str(), options)?;
+        }
+        writer.write_all(&fs::read(path)?)?;
+    }
+    let archive = writer.finish()?.into_inner();
+
+    if let Some(parent) = target_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut target_file = fs::File::create(&target_path)?;
+    if let Some(interpreter) = interpreter {
+        target_file.write_all(format!("#!{}\n", interpreter).as_bytes())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            target_file.set_permissions(fs::Permissions::from_mode(0o755))?;
+        }
+    }
+    target_file.write_all(&archive)?;
+
+    Ok(())
+}
@@ -1,6 +1,8 @@
 use ruff_python_ast::{Expr, Stmt};
 
-use crate::common::ast::{checkers::is_dynamic_import_mut, full_name::get_full_name_for_expr};
+use crate::common::ast::{
+    checkers::is_dynamic_import_mut, full_name::get_full_name_for_expr, resolve_name,
+};
 
 pub fn find_dynamic_import(
     stmt: &Stmt,
@@ -52,3 +54,48 @@ pub fn find_dynamic_import(
 
     found_import
 }
+
+/// Names a pep562 `__getattr__` imports lazily, declared like pydantic's `_dynamic_imports`,
+/// e.g. `{"BaseModel": (__spec__.parent, ".main")}`.
+/// Returns the names with the module specs they are imported from.
+pub fn find_lazy_imports(stmt: &Stmt, parent_package: &str) -> Vec<(String, String)> {
+    let value = match stmt {
+        Stmt::Assign(assign) => Some(assign.value.as_ref()),
+        Stmt::AnnAssign(ann_assign) => ann_assign.value.as_deref(),
+        _ => None,
+    };
+    let Some(Expr::Dict(dict)) = value else {
+        return Vec::new();
+    };
+
+    let mut found_imports: Vec<(String, String)> = Vec::new();
+    for item in &dict.items {
+        let (Some(Expr::StringLiteral(name)), Expr::Tuple(tuple)) = (&item.key, &item.value) else {
+            continue;
+        };
+        let [package, Expr::StringLiteral(module)] = &tuple.elts[..] else {
+            continue;
+        };
+        let package = match package {
+            Expr::StringLiteral(package) => package.value.to_str().to_string(),
+            _ if get_full_name_for_expr(package)
+                .iter()
+                .any(|full_name| full_name == "__spec__.parent" || full_name == "__package__") =>
+            {
+                parent_package.to_string()
+            }
+            _ => continue,
+        };
+        let module = module.value.to_str();
+        // the attribute is the module itself
+        if module == "__module__" {
+            continue;
+        }
+        let level = module.len() - module.trim_start_matches('.').len();
+        if let Ok(module_spec) = resolve_name(module.trim_start_matches('.'), &package, &level) {
+            found_imports.push((name.value.to_str().to_string(), module_spec));
+        }
+    }
+
+    found_imports
+}
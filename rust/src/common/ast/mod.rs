@@ -2,14 +2,11 @@ pub mod checkers;
 pub mod finders;
 pub mod full_name;
 pub mod providers;
+pub mod scopes;
 pub mod transformer;
-use pyo3::{
-    PyResult,
-    exceptions::{PyImportError, PyValueError},
-};
-use ruff_python_ast::{AtomicNodeIndex, Mod, ModModule, Stmt, StmtImportFrom, StmtPass};
+use ruff_python_ast::{AtomicNodeIndex, Expr, Mod, ModModule, Stmt, StmtImportFrom, StmtPass};
 use ruff_python_codegen::{Generator, Stylist};
-use ruff_python_parser::{Mode, ParseError, ParseOptions, Parsed, parse};
+use ruff_python_parser::{Mode, ParseError, ParseOptions, Parsed, parse, parse_expression};
 use ruff_text_size::TextRange;
 
 use crate::common::module_spec::get_parent_package;
@@ -20,6 +17,13 @@ pub fn parse_python_source(python_source: &str) -> Result<Mod, ParseError> {
         .to_owned())
 }
 
+/// Parses the content of a string annotation like `"ClassName"` or `"list[Item]"`.
+pub fn parse_string_annotation(annotation: &str) -> Option<Expr> {
+    parse_expression(annotation.trim())
+        .ok()
+        .map(|parsed| parsed.into_expr())
+}
+
 pub fn generate_source(
     body: &Vec<Stmt>,
     parsed: Parsed<ModModule>,
@@ -39,7 +43,7 @@ pub fn generate_source(
 }
 
 // does the same as libcst's resolve_name
-fn resolve_name(name: &str, package: &str, level: &usize) -> PyResult<String> {
+fn resolve_name(name: &str, package: &str, level: &usize) -> Result<String, String> {
     if *level == 0 {
         return Ok(name.to_string());
     }
@@ -48,10 +52,10 @@ fn resolve_name(name: &str, package: &str, level: &usize) -> PyResult<String> {
     bits.reverse();
 
     if bits.len() < *level {
-        return Err(PyImportError::new_err(format!(
+        return Err(format!(
             "attempted relative import beyond top-level package {} {}",
             &package, &level
-        )));
+        ));
     }
     let base = bits[0].to_string();
     if name.len() > 0 {
@@ -65,15 +69,13 @@ pub fn get_import_from_absolute_module_spec(
     node: &StmtImportFrom,
     parent_package: &str,
     greedy: bool,
-) -> PyResult<Vec<String>> {
+) -> Result<Vec<String>, String> {
     let mut fixed_parent_package = parent_package.to_string();
     if fixed_parent_package.ends_with(".__main__") {
         fixed_parent_package = fixed_parent_package.replace(".__main__", "");
     }
     if node.module.is_none() && node.level == 0 {
-        return Err(PyValueError::new_err(
-            "No absolute module spec could be found for node",
-        ));
+        return Err("No absolute module spec could be found for node".to_owned());
     }
     if let Some(module) = &node.module {
         return Ok(vec![resolve_name(
@@ -100,5 +102,5 @@ pub fn get_import_from_absolute_module_spec(
         return Ok(result);
     }
 
-    Err(PyValueError::new_err("Don't know how to handle node"))
+    Err("Don't know how to handle node".to_owned())
 }
@@ -1,10 +1,16 @@
-use std::path::PathBuf;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
 use ruff_python_ast::{Expr, Stmt};
 use ruff_python_stdlib::builtins::is_python_builtin;
 
 use crate::{
-    common::ast::full_name::{get_full_name_for_expr, get_full_name_for_stmt},
+    common::ast::{
+        full_name::{get_full_name_for_expr, get_full_name_for_stmt},
+        scopes::{ScopeId, ScopeTree},
+    },
     constants::PYTHON_MINOR_VERSION,
 };
 
@@ -12,8 +18,18 @@ use super::imports_provider::{ImportTrackingProviderScope, ImportsTrackingProvid
 
 type TNameContext = String;
 
+/// Whether a star import binds `name`, only the names in `__all__` if the module defines it,
+/// otherwise all names not starting with an underscore
+pub fn is_star_imported(exports: Option<&HashSet<String>>, name: &str) -> bool {
+    let root = name.split('.').next().unwrap_or(name);
+    match exports {
+        Some(names) => names.contains(root),
+        None => !root.starts_with('_'),
+    }
+}
+
 pub struct FullyQualifiedNameProviderScope {
-    pub name_context: Option<String>,
+    pub scope: Option<ScopeId>,
     pub import_scope: ImportTrackingProviderScope,
 }
 
@@ -21,6 +37,10 @@ pub struct FullyQualifiedNameProvider {
     pub name_context: TNameContext,
     module_spec: String,
     imports_provider: ImportsTrackingProvider,
+    scopes: ScopeTree,
+    current_scope: ScopeId,
+    // names bound by star imports of modules with a static `__all__`
+    star_exports: HashMap<String, HashSet<String>>,
 }
 
 impl FullyQualifiedNameProvider {
@@ -29,16 +49,44 @@ impl FullyQualifiedNameProvider {
             name_context: String::new(),
             imports_provider: ImportsTrackingProvider::new(module_spec, source_path),
             module_spec: module_spec.to_string(),
+            scopes: ScopeTree::new(),
+            current_scope: ScopeTree::MODULE_SCOPE,
+            star_exports: HashMap::new(),
         }
     }
 
+    pub fn set_star_exports(&mut self, star_exports: HashMap<String, HashSet<String>>) {
+        self.star_exports = star_exports;
+    }
+
+    /// Collects the scopes and bindings of a module, needs to be called before visiting it.
+    pub fn build_scopes(&mut self, body: &[Stmt]) {
+        self.scopes = ScopeTree::from_body(body);
+        self.set_current_scope(ScopeTree::MODULE_SCOPE);
+    }
+
+    fn set_current_scope(&mut self, scope_id: ScopeId) {
+        self.current_scope = scope_id;
+        self.name_context = self.scopes.get(scope_id).qualified_name.clone();
+    }
+
+    pub fn is_global_scope(&self) -> bool {
+        self.scopes.is_global(self.current_scope)
+    }
+
     pub fn get_imports_provider(&self) -> &ImportsTrackingProvider {
         return &self.imports_provider;
     }
 
+    /// Prefixes `name` with the scope its first segment is bound in,
+    /// looked up from the current scope
     pub fn resolve_qualified_name(&self, name: &str) -> String {
-        if self.name_context.len() > 0 {
-            format!("{}.{}", self.name_context, name)
+        let local_name = name.split('.').next().unwrap_or(name);
+        let scope = self
+            .scopes
+            .get(self.scopes.lookup(self.current_scope, local_name));
+        if scope.qualified_name.len() > 0 {
+            format!("{}.{}", scope.qualified_name, name)
         } else {
             name.to_string()
         }
@@ -47,12 +95,11 @@ impl FullyQualifiedNameProvider {
     fn get_expr_qualified_name(&self, expr: &Expr) -> Vec<String> {
         get_full_name_for_expr(expr)
             .iter()
-            .flat_map(|name| match expr {
-                Expr::Named(_) => vec![self.resolve_qualified_name(&name)],
-                Expr::Name(_) | Expr::Attribute(_) => {
-                    vec![self.resolve_qualified_name(&name), name.to_owned()]
+            .map(|name| match expr {
+                Expr::Named(_) | Expr::Name(_) | Expr::Attribute(_) => {
+                    self.resolve_qualified_name(&name)
                 }
-                _ => vec![name.to_owned()],
+                _ => name.to_owned(),
             })
             .collect()
     }
@@ -67,7 +114,10 @@ impl FullyQualifiedNameProvider {
                 | Stmt::AnnAssign(_)
                 | Stmt::AugAssign(_)
                 | Stmt::ClassDef(_)
-                | Stmt::FunctionDef(_) => {
+                | Stmt::FunctionDef(_)
+                | Stmt::TypeAlias(_)
+                | Stmt::Global(_)
+                | Stmt::Nonlocal(_) => {
                     self.resolve_qualified_name(name)
                 }
                 _ => name.to_string(),
@@ -94,7 +144,9 @@ impl FullyQualifiedNameProvider {
         }
 
         for star_import in &self.imports_provider.active_star_imports {
-            result.push(format!("{}.{}", star_import, qualified_name));
+            if is_star_imported(self.star_exports.get(star_import), qualified_name) {
+                result.push(format!("{}.{}", star_import, qualified_name));
+            }
         }
 
         return result;
@@ -128,35 +180,182 @@ impl FullyQualifiedNameProvider {
     }
 
     pub fn enter_scope(&mut self, stmt: &Stmt) -> FullyQualifiedNameProviderScope {
-        let old_name_context: Option<TNameContext> = match stmt {
-            Stmt::ClassDef(_) | Stmt::FunctionDef(_) => {
-                let full_names =
-                    get_full_name_for_stmt(stmt, &self.imports_provider.get_parent_package());
-                let mut ret_value: Option<TNameContext> = None;
-                if full_names.len() == 1 {
-                    ret_value = Some(self.name_context.clone());
-                    if self.name_context.len() > 0 {
-                        self.name_context = format!("{}.{}", self.name_context, full_names[0]);
-                    } else {
-                        self.name_context = full_names[0].clone();
-                    }
-                }
-
-                ret_value
-            }
-            _ => None,
-        };
+        let previous_scope = self.scopes.get_stmt_scope(stmt).map(|scope_id| {
+            let previous_scope = self.current_scope;
+            self.set_current_scope(scope_id);
+            previous_scope
+        });
 
         FullyQualifiedNameProviderScope {
-            name_context: old_name_context,
+            scope: previous_scope,
             import_scope: self.imports_provider.enter_scope(stmt),
         }
     }
 
     pub fn exit_scope(&mut self, scope: FullyQualifiedNameProviderScope) {
-        if let Some(name_context) = scope.name_context {
-            self.name_context = name_context;
+        if let Some(scope_id) = scope.scope {
+            self.set_current_scope(scope_id);
         }
         self.imports_provider.exit_scope(scope.import_scope);
     }
+
+    /// Lambdas and comprehensions, which can't contain imports
+    pub fn enter_expr_scope(&mut self, expr: &Expr) -> Option<ScopeId> {
+        self.scopes.get_expr_scope(expr).map(|scope_id| {
+            let previous_scope = self.current_scope;
+            self.set_current_scope(scope_id);
+            previous_scope
+        })
+    }
+
+    pub fn exit_expr_scope(&mut self, scope: Option<ScopeId>) {
+        if let Some(scope_id) = scope {
+            self.set_current_scope(scope_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        path::PathBuf,
+    };
+
+    use ruff_python_ast::{Expr, Stmt};
+    use ruff_python_parser::parse_expression;
+
+    use super::FullyQualifiedNameProvider;
+    use crate::common::ast::parse_python_source;
+
+    fn parse_body(source: &str) -> Vec<Stmt> {
+        parse_python_source(source).unwrap().expect_module().body
+    }
+
+    fn parse_expr(source: &str) -> Expr {
+        parse_expression(source).unwrap().into_expr()
+    }
+
+    fn visit_module(module_spec: &str, path: &str, body: &[Stmt]) -> FullyQualifiedNameProvider {
+        let mut provider = FullyQualifiedNameProvider::new(module_spec, &PathBuf::from(path));
+        provider.build_scopes(body);
+        for stmt in body {
+            provider.visit_stmt(stmt);
+        }
+        provider
+    }
+
+    fn resolve(provider: &FullyQualifiedNameProvider, expr: &str) -> Vec<String> {
+        provider.get_expr_fully_qualified_name(&parse_expr(expr))
+    }
+
+    #[test]
+    fn resolves_imported_names() {
+        let body = parse_body(
+            "import json\nfrom collections import OrderedDict as OD\nfrom .helpers import helper\nfrom . import sibling\n",
+        );
+        let provider = visit_module("pkg.module", "pkg/module.py", &body);
+
+        // attributes resolve to the value and the attribute
+        assert_eq!(resolve(&provider, "json.loads"), vec!["json", "json.loads"]);
+        assert_eq!(resolve(&provider, "OD"), vec!["collections.OrderedDict"]);
+        assert_eq!(resolve(&provider, "helper"), vec!["pkg.helpers.helper"]);
+        assert_eq!(
+            resolve(&provider, "sibling.run"),
+            vec!["pkg.sibling", "pkg.sibling.run"]
+        );
+    }
+
+    #[test]
+    fn resolves_relative_imports_of_packages() {
+        let body = parse_body("from .core import run\n");
+        let provider = visit_module("pkg", "pkg/__init__.py", &body);
+
+        assert_eq!(provider.get_imports_provider().get_parent_package(), "pkg");
+        assert_eq!(resolve(&provider, "run"), vec!["pkg.core.run"]);
+    }
+
+    #[test]
+    fn resolves_module_names_and_builtins() {
+        let body = parse_body("def load():\n    pass\n");
+        let provider = visit_module("pkg.module", "pkg/module.py", &body);
+
+        assert_eq!(resolve(&provider, "load"), vec!["pkg.module.load"]);
+        assert_eq!(
+            resolve(&provider, "len"),
+            vec!["pkg.module.len", "__builtin__.len"]
+        );
+    }
+
+    #[test]
+    fn resolves_star_import_candidates() {
+        let body = parse_body("from pkg.base import *\n");
+        let provider = visit_module("pkg.module", "pkg/module.py", &body);
+
+        assert_eq!(
+            resolve(&provider, "Thing"),
+            vec!["pkg.module.Thing", "pkg.base.Thing"]
+        );
+        assert_eq!(resolve(&provider, "_thing"), vec!["pkg.module._thing"]);
+    }
+
+    #[test]
+    fn resolves_star_import_candidates_from_dunder_all() {
+        let body = parse_body("from pkg.base import *\n");
+        let mut provider = visit_module("pkg.module", "pkg/module.py", &body);
+        provider.set_star_exports(HashMap::from([(
+            "pkg.base".to_owned(),
+            HashSet::from(["Thing".to_owned()]),
+        )]));
+
+        assert_eq!(
+            resolve(&provider, "Thing"),
+            vec!["pkg.module.Thing", "pkg.base.Thing"]
+        );
+        assert_eq!(resolve(&provider, "helper"), vec!["pkg.module.helper"]);
+    }
+
+    #[test]
+    fn resolves_names_in_their_scope() {
+        let body =
+            parse_body("counter = 0\n\ndef count():\n    step = 1\n    return counter + step\n");
+        let mut provider = visit_module("pkg.module", "pkg/module.py", &body);
+        assert!(provider.is_global_scope());
+
+        let scope = provider.enter_scope(&body[1]);
+        assert!(!provider.is_global_scope());
+        assert_eq!(provider.resolve_qualified_name("step"), "count.step");
+        assert_eq!(provider.resolve_qualified_name("counter"), "counter");
+        assert_eq!(resolve(&provider, "step"), vec!["pkg.module.count.step"]);
+
+        provider.exit_scope(scope);
+        assert!(provider.is_global_scope());
+        assert_eq!(provider.resolve_qualified_name("step"), "step");
+    }
+
+    #[test]
+    fn resolves_nonlocal_names_in_methods_to_the_enclosing_function() {
+        let body = parse_body(
+            "def outer():\n    class Counter:\n        count = 0\n        def increase(self):\n            nonlocal count\n            count += 1\n    count = 0\n    return Counter\n",
+        );
+        let mut provider = visit_module("pkg.module", "pkg/module.py", &body);
+        let Stmt::FunctionDef(outer) = &body[0] else {
+            unreachable!()
+        };
+        let Stmt::ClassDef(counter) = &outer.body[0] else {
+            unreachable!()
+        };
+
+        let outer_scope = provider.enter_scope(&body[0]);
+        let class_scope = provider.enter_scope(&outer.body[0]);
+        assert_eq!(
+            provider.resolve_qualified_name("count"),
+            "outer.Counter.count"
+        );
+        let method_scope = provider.enter_scope(&counter.body[1]);
+        assert_eq!(provider.resolve_qualified_name("count"), "outer.count");
+        provider.exit_scope(method_scope);
+        provider.exit_scope(class_scope);
+        provider.exit_scope(outer_scope);
+    }
 }
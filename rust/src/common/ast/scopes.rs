@@ -0,0 +1,418 @@
+use std::collections::{HashMap, HashSet};
+
+use ruff_python_ast::{
+    Comprehension, ExceptHandler, Expr, ExprContext, Pattern, Stmt,
+    visitor::{Visitor, walk_except_handler, walk_expr, walk_pattern, walk_stmt},
+};
+use ruff_text_size::{Ranged, TextRange};
+
+pub type ScopeId = usize;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ScopeKind {
+    Module,
+    Class,
+    Function,
+    Lambda,
+    Comprehension,
+}
+
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    // dotted path of the scope inside of its module, empty for the module scope
+    pub qualified_name: String,
+    bindings: HashSet<String>,
+    // names declared `global` or `nonlocal` are bound in the module or an enclosing scope
+    global_names: HashSet<String>,
+    nonlocal_names: HashSet<String>,
+}
+
+/// Lexical scopes of a module and the names bound in each of them.
+///
+/// Follows the rules of Python: classes, functions, lambdas and comprehensions
+/// create scopes, names bound in a class body are not visible in nested scopes
+/// and walrus targets in comprehensions are bound in the enclosing scope.
+pub struct ScopeTree {
+    scopes: Vec<Scope>,
+    node_scopes: HashMap<(ScopeKind, TextRange), ScopeId>,
+}
+
+impl ScopeTree {
+    pub const MODULE_SCOPE: ScopeId = 0;
+
+    pub fn new() -> Self {
+        ScopeTree {
+            scopes: vec![Scope {
+                kind: ScopeKind::Module,
+                parent: None,
+                qualified_name: String::new(),
+                bindings: HashSet::new(),
+                global_names: HashSet::new(),
+                nonlocal_names: HashSet::new(),
+            }],
+            node_scopes: HashMap::new(),
+        }
+    }
+
+    pub fn from_body(body: &[Stmt]) -> Self {
+        let mut builder = ScopeTreeBuilder {
+            tree: ScopeTree::new(),
+            current_scope: ScopeTree::MODULE_SCOPE,
+            nonlocal_bindings: Vec::new(),
+        };
+        for stmt in body {
+            builder.visit_stmt(stmt);
+        }
+        builder.bind_nonlocal_names();
+        builder.tree
+    }
+
+    pub fn get(&self, scope_id: ScopeId) -> &Scope {
+        &self.scopes[scope_id]
+    }
+
+    pub fn get_stmt_scope(&self, stmt: &Stmt) -> Option<ScopeId> {
+        let kind = match stmt {
+            Stmt::ClassDef(_) => ScopeKind::Class,
+            Stmt::FunctionDef(_) => ScopeKind::Function,
+            _ => return None,
+        };
+        self.node_scopes.get(&(kind, stmt.range())).copied()
+    }
+
+    pub fn get_expr_scope(&self, expr: &Expr) -> Option<ScopeId> {
+        let kind = match expr {
+            Expr::Lambda(_) => ScopeKind::Lambda,
+            Expr::ListComp(_) | Expr::SetComp(_) | Expr::DictComp(_) | Expr::Generator(_) => {
+                ScopeKind::Comprehension
+            }
+            _ => return None,
+        };
+        self.node_scopes.get(&(kind, expr.range())).copied()
+    }
+
+    /// Returns the scope `name` refers to when it is used in `scope_id`.
+    /// Names which are not bound anywhere are globals or builtins.
+    pub fn lookup(&self, scope_id: ScopeId, name: &str) -> ScopeId {
+        let mut current_id = scope_id;
+        loop {
+            let scope = &self.scopes[current_id];
+            if scope.global_names.contains(name) {
+                return ScopeTree::MODULE_SCOPE;
+            }
+            // class bodies are skipped when looking up names from nested scopes
+            let is_visible = current_id == scope_id || scope.kind != ScopeKind::Class;
+            if is_visible && scope.bindings.contains(name) && !scope.nonlocal_names.contains(name) {
+                return current_id;
+            }
+            match scope.parent {
+                Some(parent) => current_id = parent,
+                None => return ScopeTree::MODULE_SCOPE,
+            }
+        }
+    }
+
+    /// Code in the global scope is executed on import, including module level comprehensions
+    pub fn is_global(&self, scope_id: ScopeId) -> bool {
+        let mut current_id = Some(scope_id);
+        while let Some(scope_id) = current_id {
+            let scope = &self.scopes[scope_id];
+            if !matches!(scope.kind, ScopeKind::Module | ScopeKind::Comprehension) {
+                return false;
+            }
+            current_id = scope.parent;
+        }
+        true
+    }
+}
+
+struct ScopeTreeBuilder {
+    tree: ScopeTree,
+    current_scope: ScopeId,
+    // bindings of names declared `nonlocal`, the enclosing function might bind them later on
+    nonlocal_bindings: Vec<(ScopeId, String)>,
+}
+
+impl ScopeTreeBuilder {
+    fn push_scope(&mut self, kind: ScopeKind, range: TextRange, name: &str) -> ScopeId {
+        let parent = self.current_scope;
+        let parent_name = &self.tree.scopes[parent].qualified_name;
+        let qualified_name = if parent_name.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}.{}", parent_name, name)
+        };
+        let scope_id = self.tree.scopes.len();
+        self.tree.scopes.push(Scope {
+            kind,
+            parent: Some(parent),
+            qualified_name,
+            bindings: HashSet::new(),
+            global_names: HashSet::new(),
+            nonlocal_names: HashSet::new(),
+        });
+        self.tree.node_scopes.insert((kind, range), scope_id);
+        self.current_scope = scope_id;
+        parent
+    }
+
+    fn bind_in(&mut self, scope_id: ScopeId, name: &str) {
+        let scope = &self.tree.scopes[scope_id];
+        let binding_scope_id = if scope.global_names.contains(name) {
+            ScopeTree::MODULE_SCOPE
+        } else if scope.nonlocal_names.contains(name) {
+            self.nonlocal_bindings.push((scope_id, name.to_owned()));
+            return;
+        } else {
+            scope_id
+        };
+        self.tree.scopes[binding_scope_id]
+            .bindings
+            .insert(name.to_owned());
+    }
+
+    /// Nonlocal names are bound in the closest enclosing function binding the name,
+    /// which is only known once every scope was visited
+    fn bind_nonlocal_names(&mut self) {
+        for (scope_id, name) in std::mem::take(&mut self.nonlocal_bindings) {
+            // class bodies are skipped, `nonlocal` in a method refers to the enclosing function
+            let mut parent = self.tree.scopes[scope_id].parent;
+            while let Some(parent_id) = parent {
+                let parent_scope = &self.tree.scopes[parent_id];
+                if parent_scope.kind != ScopeKind::Class {
+                    break;
+                }
+                parent = parent_scope.parent;
+            }
+            let binding_scope_id =
+                parent.map_or(scope_id, |parent_id| self.tree.lookup(parent_id, &name));
+            self.tree.scopes[binding_scope_id].bindings.insert(name);
+        }
+    }
+
+    fn bind(&mut self, name: &str) {
+        self.bind_in(self.current_scope, name);
+    }
+
+    fn get_enclosing_non_comprehension_scope(&self) -> ScopeId {
+        let mut scope_id = self.current_scope;
+        while let Some(scope) = self.tree.scopes.get(scope_id) {
+            match (scope.kind, scope.parent) {
+                (ScopeKind::Comprehension, Some(parent)) => scope_id = parent,
+                _ => break,
+            }
+        }
+        scope_id
+    }
+
+    fn visit_comprehension_scope<'a>(
+        &mut self,
+        expr: &'a Expr,
+        name: &str,
+        generators: &'a [Comprehension],
+        elements: &[&'a Expr],
+    ) {
+        // the first iterable is evaluated in the enclosing scope
+        if let Some(first_generator) = generators.first() {
+            self.visit_expr(&first_generator.iter);
+        }
+        let previous_scope = self.push_scope(ScopeKind::Comprehension, expr.range(), name);
+        for (index, generator) in generators.iter().enumerate() {
+            self.visit_expr(&generator.target);
+            if index > 0 {
+                self.visit_expr(&generator.iter);
+            }
+            for condition in &generator.ifs {
+                self.visit_expr(condition);
+            }
+        }
+        for element in elements {
+            self.visit_expr(element);
+        }
+        self.current_scope = previous_scope;
+    }
+}
+
+impl<'a> Visitor<'a> for ScopeTreeBuilder {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::FunctionDef(func_def) => {
+                self.bind(func_def.name.as_str());
+                // decorators, defaults and annotations are evaluated in the enclosing scope
+                for decorator in &func_def.decorator_list {
+                    self.visit_decorator(decorator);
+                }
+                self.visit_parameters(&func_def.parameters);
+                if let Some(returns) = &func_def.returns {
+                    self.visit_annotation(returns);
+                }
+                let previous_scope =
+                    self.push_scope(ScopeKind::Function, stmt.range(), func_def.name.as_str());
+                for parameter in func_def.parameters.iter() {
+                    self.bind(parameter.name().as_str());
+                }
+                self.visit_body(&func_def.body);
+                self.current_scope = previous_scope;
+            }
+            Stmt::ClassDef(class_def) => {
+                self.bind(class_def.name.as_str());
+                for decorator in &class_def.decorator_list {
+                    self.visit_decorator(decorator);
+                }
+                if let Some(arguments) = &class_def.arguments {
+                    self.visit_arguments(arguments);
+                }
+                let previous_scope =
+                    self.push_scope(ScopeKind::Class, stmt.range(), class_def.name.as_str());
+                self.visit_body(&class_def.body);
+                self.current_scope = previous_scope;
+            }
+            Stmt::Import(import) => {
+                for alias in &import.names {
+                    match &alias.asname {
+                        Some(asname) => self.bind(asname.as_str()),
+                        // `import a.b` binds `a`
+                        None => {
+                            self.bind(alias.name.as_str().split('.').next().unwrap_or_default())
+                        }
+                    }
+                }
+            }
+            Stmt::ImportFrom(import_from) => {
+                for alias in &import_from.names {
+                    if alias.name.as_str() != "*" {
+                        self.bind(alias.asname.as_ref().unwrap_or(&alias.name).as_str());
+                    }
+                }
+            }
+            Stmt::Global(global) => {
+                for name in &global.names {
+                    self.tree.scopes[self.current_scope]
+                        .global_names
+                        .insert(name.to_string());
+                }
+            }
+            Stmt::Nonlocal(nonlocal) => {
+                for name in &nonlocal.names {
+                    self.tree.scopes[self.current_scope]
+                        .nonlocal_names
+                        .insert(name.to_string());
+                }
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Name(name) => {
+                if matches!(name.ctx, ExprContext::Store | ExprContext::Del) {
+                    self.bind(name.id.as_str());
+                }
+            }
+            Expr::Named(named) => {
+                // walrus targets in comprehensions are bound in the enclosing scope
+                if let Expr::Name(target) = named.target.as_ref() {
+                    let scope_id = self.get_enclosing_non_comprehension_scope();
+                    self.bind_in(scope_id, target.id.as_str());
+                }
+                self.visit_expr(&named.value);
+            }
+            Expr::Lambda(lambda) => {
+                if let Some(parameters) = &lambda.parameters {
+                    self.visit_parameters(parameters);
+                }
+                let previous_scope = self.push_scope(ScopeKind::Lambda, expr.range(), "<lambda>");
+                if let Some(parameters) = &lambda.parameters {
+                    for parameter in parameters.iter() {
+                        self.bind(parameter.name().as_str());
+                    }
+                }
+                self.visit_expr(&lambda.body);
+                self.current_scope = previous_scope;
+            }
+            Expr::ListComp(list_comp) => self.visit_comprehension_scope(
+                expr,
+                "<listcomp>",
+                &list_comp.generators,
+                &[&*list_comp.elt],
+            ),
+            Expr::SetComp(set_comp) => self.visit_comprehension_scope(
+                expr,
+                "<setcomp>",
+                &set_comp.generators,
+                &[&*set_comp.elt],
+            ),
+            Expr::Generator(generator) => self.visit_comprehension_scope(
+                expr,
+                "<genexpr>",
+                &generator.generators,
+                &[&*generator.elt],
+            ),
+            Expr::DictComp(dict_comp) => self.visit_comprehension_scope(
+                expr,
+                "<dictcomp>",
+                &dict_comp.generators,
+                &[&*dict_comp.key, &*dict_comp.value],
+            ),
+            _ => walk_expr(self, expr),
+        }
+    }
+
+    fn visit_except_handler(&mut self, except_handler: &'a ExceptHandler) {
+        let ExceptHandler::ExceptHandler(handler) = except_handler;
+        if let Some(name) = &handler.name {
+            self.bind(name.as_str());
+        }
+        walk_except_handler(self, except_handler);
+    }
+
+    fn visit_pattern(&mut self, pattern: &'a Pattern) {
+        let captured_name = match pattern {
+            Pattern::MatchAs(match_as) => match_as.name.as_ref(),
+            Pattern::MatchStar(match_star) => match_star.name.as_ref(),
+            Pattern::MatchMapping(match_mapping) => match_mapping.rest.as_ref(),
+            _ => None,
+        };
+        if let Some(name) = captured_name {
+            self.bind(name.as_str());
+        }
+        walk_pattern(self, pattern);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ruff_python_ast::Stmt;
+
+    use super::ScopeTree;
+    use crate::common::ast::parse_python_source;
+
+    fn parse_body(source: &str) -> Vec<Stmt> {
+        parse_python_source(source).unwrap().expect_module().body
+    }
+
+    #[test]
+    fn binds_nonlocal_names_in_the_enclosing_function() {
+        // the enclosing function binds `counter` after the nested function is defined
+        let body = parse_body(
+            "def outer():\n    def inner():\n        nonlocal counter\n        counter += 1\n    counter = 0\n    inner()\n    return counter\n",
+        );
+        let tree = ScopeTree::from_body(&body);
+        let outer_scope = tree.get_stmt_scope(&body[0]).unwrap();
+        let Stmt::FunctionDef(outer) = &body[0] else {
+            unreachable!()
+        };
+        let inner_scope = tree.get_stmt_scope(&outer.body[0]).unwrap();
+
+        assert_eq!(tree.lookup(inner_scope, "counter"), outer_scope);
+        assert_eq!(tree.lookup(outer_scope, "counter"), outer_scope);
+        assert!(
+            !tree
+                .get(ScopeTree::MODULE_SCOPE)
+                .bindings
+                .contains("counter")
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::{fs, path::PathBuf};
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use sha2::{Digest, Sha256};
+
+pub fn hash_content(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        // separator, so ["ab", "c"] and ["a", "bc"] differ
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Analysis results kept in memory between runs within the same process, e.g. the
+/// reference state of the treeshaking in watch mode.
+///
+/// Every `FileCache` created with it shares its entries.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Default)]
+pub struct MemoryCache {
+    entries: Arc<Mutex<HashMap<PathBuf, String>>>,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl MemoryCache {
+    #[new]
+    fn new() -> Self {
+        MemoryCache::default()
+    }
+
+    fn __len__(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+}
+
+/// Persistent storage for per-file analysis results.
+///
+/// Entries are stored below `{cache_dir}/{namespace}/{kind}`,
+/// the namespace is expected to change whenever the flay version,
+/// the target interpreter or the configuration changes.
+/// Failing to read or write an entry is never an error, the entry is treated as missing instead.
+///
+/// With a `MemoryCache`, entries are kept in memory as well and the directory is optional.
+pub struct FileCache {
+    dir: PathBuf,
+    persistent: bool,
+    memory: Option<Arc<Mutex<HashMap<PathBuf, String>>>>,
+}
+
+impl FileCache {
+    pub fn new(cache_dir: PathBuf, namespace: &str) -> Self {
+        FileCache {
+            dir: cache_dir.join(namespace),
+            persistent: true,
+            memory: None,
+        }
+    }
+
+    /// Cache in the cache directory and in memory, or in either of them
+    pub fn from_options(
+        cache_dir: Option<PathBuf>,
+        memory: Option<&MemoryCache>,
+        namespace: &str,
+    ) -> Option<Self> {
+        let mut cache = match (cache_dir, memory) {
+            (Some(cache_dir), _) => FileCache::new(cache_dir, namespace),
+            (None, Some(_)) => FileCache {
+                dir: PathBuf::from(namespace),
+                persistent: false,
+                memory: None,
+            },
+            (None, None) => return None,
+        };
+        cache.memory = memory.map(|memory| memory.entries.clone());
+        Some(cache)
+    }
+
+    fn entry_path(&self, kind: &str, key: &str) -> PathBuf {
+        self.dir.join(kind).join(&key[..2]).join(key)
+    }
+
+    pub fn get(&self, kind: &str, key: &str) -> Option<String> {
+        let entry_path = self.entry_path(kind, key);
+        if let Some(memory) = &self.memory {
+            if let Some(value) = memory.lock().unwrap().get(&entry_path) {
+                return Some(value.clone());
+            }
+        }
+        if !self.persistent {
+            return None;
+        }
+        let value = fs::read_to_string(&entry_path).ok()?;
+        if let Some(memory) = &self.memory {
+            memory.lock().unwrap().insert(entry_path, value.clone());
+        }
+        Some(value)
+    }
+
+    pub fn set(&self, kind: &str, key: &str, value: &str) {
+        let entry_path = self.entry_path(kind, key);
+        if let Some(memory) = &self.memory {
+            memory
+                .lock()
+                .unwrap()
+                .insert(entry_path.clone(), value.to_owned());
+        }
+        if !self.persistent {
+            return;
+        }
+        let Some(entry_dir) = entry_path.parent() else {
+            return;
+        };
+        if fs::create_dir_all(entry_dir).is_err() {
+            return;
+        }
+        // write to a temporary file first, so concurrent runs never read partial entries
+        let tmp_path = entry_path.with_extension(format!("{}.tmp", std::process::id()));
+        if fs::write(&tmp_path, value).is_ok() && fs::rename(&tmp_path, &entry_path).is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileCache, MemoryCache};
+
+    #[test]
+    fn keeps_entries_in_memory_between_caches() {
+        let memory = MemoryCache::default();
+        let key = "ab".repeat(32);
+        FileCache::from_options(None, Some(&memory), "first")
+            .unwrap()
+            .set("references", &key, "i pkg.name");
+
+        let cache = FileCache::from_options(None, Some(&memory), "first").unwrap();
+        assert_eq!(cache.get("references", &key).as_deref(), Some("i pkg.name"));
+        let other_namespace = FileCache::from_options(None, Some(&memory), "second").unwrap();
+        assert_eq!(other_namespace.get("references", &key), None);
+        assert!(FileCache::from_options(None, None, "first").is_none());
+    }
+}
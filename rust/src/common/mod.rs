@@ -1,2 +1,4 @@
 pub mod ast;
+pub mod cache;
 pub mod module_spec;
+pub mod patterns;
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 
+#[cfg(feature = "python")]
 use pyo3::{
     PyResult, Python,
     types::{PyAnyMethods, PyModule},
@@ -32,6 +33,47 @@ pub fn get_top_level_package(module_spec: &str) -> &str {
     return module_spec.split(".").next().unwrap();
 }
 
+fn is_identifier(segment: &str) -> bool {
+    let mut chars = segment.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|char| char.is_alphanumeric() || char == '_')
+}
+
+fn is_dotted_name(name: &str) -> bool {
+    name.split('.').all(is_identifier)
+}
+
+/// A string literal which looks like an import path, e.g. `pkg.module.attr` or the
+/// entry point notation `pkg.module:attr`
+pub struct DottedReference {
+    // the absolute name the string refers to
+    pub fqn: String,
+    // module specs the string could be imported from, most specific first
+    pub module_specs: Vec<String>,
+}
+
+pub fn parse_dotted_reference(value: &str) -> Option<DottedReference> {
+    if let Some((module_spec, attribute)) = value.split_once(':') {
+        if !is_dotted_name(module_spec) || !is_dotted_name(attribute) {
+            return None;
+        }
+        return Some(DottedReference {
+            fqn: format!("{}.{}", module_spec, attribute),
+            module_specs: vec![module_spec.to_owned()],
+        });
+    }
+    if !value.contains('.') || !is_dotted_name(value) {
+        return None;
+    }
+    Some(DottedReference {
+        fqn: value.to_owned(),
+        // the last segment is either a submodule or an attribute of the module
+        module_specs: vec![value.to_owned(), remove_last_segment(value).to_owned()],
+    })
+}
+
 pub fn is_in_std_lib(module_spec: &str) -> bool {
     let top_level = get_top_level_package(module_spec);
     return is_known_standard_library(PYTHON_MINOR_VERSION, top_level)
@@ -39,6 +81,11 @@ pub fn is_in_std_lib(module_spec: &str) -> bool {
         || top_level == "__future__";
 }
 
+pub fn is_builtin(module_spec: &str) -> bool {
+    is_builtin_module(PYTHON_MINOR_VERSION, get_top_level_package(module_spec))
+}
+
+#[cfg(feature = "python")]
 pub fn get_file_for_module_spec(module_spec: &str) -> Option<(String, PathBuf)> {
     let key_result = Python::attach(|py| -> PyResult<Option<(String, PathBuf)>> {
         let flay_common = PyModule::import(py, "flay.common.module_spec")?;
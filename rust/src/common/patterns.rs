@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyValueError, prelude::*};
+use regex::Regex;
+
+const REGEX_PREFIX: &str = "re:";
+const EXCLUDE_PREFIX: char = '!';
+
+/// Globs, regular expressions and exclusions are matched against fully qualified names
+/// during the analysis instead of being looked up directly
+#[cfg_attr(feature = "python", pyfunction)]
+pub fn is_symbol_pattern(pattern: &str) -> bool {
+    pattern.starts_with(REGEX_PREFIX)
+        || pattern.starts_with(EXCLUDE_PREFIX)
+        || pattern.contains(['*', '?'])
+}
+
+/// Translates a glob over dotted names into a regular expression.
+/// `*` and `?` never match across a `.`, `**` matches any number of segments.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str(r"[^.]*"),
+            '?' => regex.push_str(r"[^.]"),
+            _ => regex.push_str(&regex::escape(&char.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+fn compile_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    match pattern.strip_prefix(REGEX_PREFIX) {
+        Some(regex) => Regex::new(&format!("^(?:{})$", regex)),
+        None => Regex::new(&glob_to_regex(pattern)),
+    }
+}
+
+/// Exact names, globs (`pkg.**`, `pkg.*.handler`) and regular expressions (`re:...`)
+/// matched against fully qualified names.
+/// Patterns starting with `!` exclude names matched by other patterns.
+pub struct SymbolPatterns {
+    exact: HashSet<String>,
+    patterns: Vec<(String, Regex)>,
+    excluded: Vec<(String, Regex)>,
+}
+
+impl SymbolPatterns {
+    pub fn new<I>(patterns: I) -> Result<Self, regex::Error>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut symbol_patterns = SymbolPatterns {
+            exact: HashSet::new(),
+            patterns: Vec::new(),
+            excluded: Vec::new(),
+        };
+        for pattern in patterns {
+            if let Some(excluded) = pattern.strip_prefix(EXCLUDE_PREFIX) {
+                let regex = compile_pattern(excluded)?;
+                symbol_patterns.excluded.push((pattern, regex));
+            } else if is_symbol_pattern(&pattern) {
+                let regex = compile_pattern(&pattern)?;
+                symbol_patterns.patterns.push((pattern, regex));
+            } else {
+                symbol_patterns.exact.insert(pattern);
+            }
+        }
+        Ok(symbol_patterns)
+    }
+
+    /// Returns the pattern matching `fqn`, if any
+    pub fn find_match(&self, fqn: &str) -> Option<&str> {
+        if self.excluded.iter().any(|(_, regex)| regex.is_match(fqn)) {
+            return None;
+        }
+        if let Some(exact) = self.exact.get(fqn) {
+            return Some(exact);
+        }
+        self.patterns
+            .iter()
+            .find(|(_, regex)| regex.is_match(fqn))
+            .map(|(pattern, _)| pattern.as_str())
+    }
+}
+
+/// Returns the `symbols` matched by `patterns`, see [`SymbolPatterns`]
+#[cfg(feature = "python")]
+#[pyfunction]
+pub fn match_symbols(patterns: Vec<String>, symbols: Vec<String>) -> PyResult<HashSet<String>> {
+    let patterns =
+        SymbolPatterns::new(patterns).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(symbols
+        .into_iter()
+        .filter(|symbol| patterns.find_match(symbol).is_some())
+        .collect())
+}
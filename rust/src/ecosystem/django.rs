@@ -0,0 +1,160 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use pyo3::{pyclass, pymethods};
+use ruff_python_ast::{
+    ExceptHandler, Expr, Stmt,
+    visitor::{Visitor, walk_expr},
+};
+
+use crate::common::ast::{
+    parse_python_source, providers::imports_provider::ImportsTrackingProvider,
+};
+
+// Django only reads upper case names of a settings module
+fn is_setting_name(name: &str) -> bool {
+    name.starts_with(|char: char| char.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|char| char.is_ascii_uppercase() || char.is_ascii_digit() || char == '_')
+}
+
+/// Name of the setting an assignment target refers to, e.g. `DATABASES["default"]["ENGINE"]`
+fn get_setting_name(target: &Expr) -> Option<&str> {
+    match target {
+        Expr::Name(name) if is_setting_name(name.id.as_str()) => Some(name.id.as_str()),
+        Expr::Subscript(subscript) => get_setting_name(&subscript.value),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct StringLiteralsCollector {
+    strings: Vec<String>,
+}
+
+impl<'a> Visitor<'a> for StringLiteralsCollector {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if let Expr::StringLiteral(string_literal) = expr {
+            self.strings.push(string_literal.value.to_str().to_owned());
+        }
+        walk_expr(self, expr);
+    }
+}
+
+struct SettingsEvaluator<'a> {
+    imports_provider: ImportsTrackingProvider,
+    settings: &'a mut HashMap<String, Vec<String>>,
+}
+
+impl SettingsEvaluator<'_> {
+    fn add_strings(&mut self, setting_name: &str, expr: &Expr) {
+        let mut collector = StringLiteralsCollector::default();
+        collector.visit_expr(expr);
+        self.settings
+            .entry(setting_name.to_owned())
+            .or_default()
+            .extend(collector.strings);
+    }
+
+    fn visit_call(&mut self, expr: &Expr) {
+        let Expr::Call(call) = expr else {
+            return;
+        };
+        let Expr::Attribute(attribute) = call.func.as_ref() else {
+            return;
+        };
+        if let Some(setting_name) = get_setting_name(&attribute.value) {
+            for argument in &call.arguments.args {
+                self.add_strings(setting_name, argument);
+            }
+        }
+    }
+
+    fn visit_body(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            self.imports_provider.visit_stmt(stmt);
+            match stmt {
+                Stmt::Assign(assign) => {
+                    for target in &assign.targets {
+                        if let Some(setting_name) = get_setting_name(target) {
+                            self.add_strings(setting_name, &assign.value);
+                        }
+                    }
+                }
+                Stmt::AnnAssign(ann_assign) => {
+                    if let (Some(setting_name), Some(value)) =
+                        (get_setting_name(&ann_assign.target), &ann_assign.value)
+                    {
+                        self.add_strings(setting_name, value);
+                    }
+                }
+                Stmt::AugAssign(aug_assign) => {
+                    if let Some(setting_name) = get_setting_name(&aug_assign.target) {
+                        self.add_strings(setting_name, &aug_assign.value);
+                    }
+                }
+                // e.g. `INSTALLED_APPS.append("debug_toolbar")`
+                Stmt::Expr(stmt_expr) => self.visit_call(&stmt_expr.value),
+                // settings are often changed conditionally, all branches are included
+                Stmt::If(stmt_if) => {
+                    self.visit_body(&stmt_if.body);
+                    for clause in &stmt_if.elif_else_clauses {
+                        self.visit_body(&clause.body);
+                    }
+                }
+                Stmt::Try(stmt_try) => {
+                    self.visit_body(&stmt_try.body);
+                    for handler in &stmt_try.handlers {
+                        let ExceptHandler::ExceptHandler(handler) = handler;
+                        self.visit_body(&handler.body);
+                    }
+                    self.visit_body(&stmt_try.orelse);
+                    self.visit_body(&stmt_try.finalbody);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Evaluates Django settings modules statically.
+/// Collects the string literals of every setting, which is where Django takes
+/// the dotted import paths of apps, middlewares and backends from.
+#[pyclass]
+pub struct DjangoSettingsCollector {
+    #[pyo3(get)]
+    settings: HashMap<String, Vec<String>>,
+}
+
+#[pymethods]
+impl DjangoSettingsCollector {
+    #[new]
+    fn new() -> Self {
+        DjangoSettingsCollector {
+            settings: HashMap::new(),
+        }
+    }
+
+    /// Returns the modules the settings module star imports, e.g. `from .base import *`,
+    /// as their settings are part of it as well.
+    fn process_module(
+        &mut self,
+        module_spec: String,
+        source_path: PathBuf,
+    ) -> Result<Vec<String>, std::io::Error> {
+        let file_content = fs::read_to_string(&source_path)?;
+        let Ok(module) = parse_python_source(&file_content) else {
+            return Ok(Vec::new());
+        };
+        let mut evaluator = SettingsEvaluator {
+            imports_provider: ImportsTrackingProvider::new(&module_spec, &source_path),
+            settings: &mut self.settings,
+        };
+        evaluator.visit_body(&module.expect_module().body);
+        Ok(evaluator
+            .imports_provider
+            .active_star_imports
+            .into_iter()
+            .collect())
+    }
+}
@@ -1,21 +1,61 @@
+// without the python feature only the analysis is built, e.g. for `cargo test`
+#![cfg_attr(not(feature = "python"), allow(dead_code))]
+
 mod bundle;
 mod common;
 mod constants;
+mod ecosystem;
 mod treeshake;
-use bundle::file_collector::FileCollector;
 
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
-use treeshake::nodes_remover::NodesRemover;
-use treeshake::references_counter::ReferencesCounter;
+
+#[cfg(feature = "python")]
 #[pymodule]
 #[pyo3(name = "_flay_rs")]
 mod flay {
     #[pymodule_export]
-    use super::FileCollector;
+    use crate::bundle::file_collector::FileCollector;
+
+    #[pymodule_export]
+    use crate::treeshake::references_counter::ReferencesCounter;
+
+    #[pymodule_export]
+    use crate::common::cache::MemoryCache;
+
+    #[pymodule_export]
+    use crate::treeshake::nodes_remover::NodesRemover;
+
+    #[pymodule_export]
+    use crate::bundle::shared_libraries::SharedLibrariesCollector;
+
+    #[pymodule_export]
+    use crate::bundle::zipapp::write_zipapp;
+
+    #[pymodule_export]
+    use crate::bundle::watcher::FileWatcher;
+
+    #[pymodule_export]
+    use crate::treeshake::exports::ExportsCollector;
+
+    #[pymodule_export]
+    use crate::ecosystem::django::DjangoSettingsCollector;
+
+    #[pymodule_export]
+    use crate::treeshake::class_hierarchy::ClassHierarchyCollector;
+
+    #[pymodule_export]
+    use crate::treeshake::purity::PurityCollector;
+
+    #[pymodule_export]
+    use crate::treeshake::dead_code::DeadCodeRemover;
+
+    #[pymodule_export]
+    use crate::treeshake::defaults::UnusedDefaultsRemover;
 
     #[pymodule_export]
-    use super::ReferencesCounter;
+    use crate::common::patterns::is_symbol_pattern;
 
     #[pymodule_export]
-    use super::NodesRemover;
+    use crate::common::patterns::match_symbols;
 }
@@ -0,0 +1,192 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use ruff_python_ast::{ExceptHandler, Stmt, StmtClassDef};
+
+use crate::common::{
+    ast::{
+        parse_python_source, providers::fully_qualified_name_provider::FullyQualifiedNameProvider,
+    },
+    patterns::SymbolPatterns,
+};
+
+// metaclasses which never register the classes they create
+const PLAIN_METACLASSES: [&str; 2] = ["__builtin__.type", "abc.ABCMeta"];
+
+struct ClassInfo {
+    // candidates of the fully qualified names of each base
+    bases: Vec<Vec<String>>,
+    defines_init_subclass: bool,
+    has_custom_metaclass: bool,
+}
+
+impl ClassInfo {
+    fn is_registry(&self) -> bool {
+        self.defines_init_subclass || self.has_custom_metaclass
+    }
+}
+
+/// Collects the classes of all modules with their bases, to find classes which are
+/// registered somewhere as a side-effect of their definition,
+/// i.e. by `__init_subclass__` or the metaclass of one of their bases.
+#[pyclass]
+pub struct ClassHierarchyCollector {
+    classes: HashMap<String, ClassInfo>,
+}
+
+#[pymethods]
+impl ClassHierarchyCollector {
+    #[new]
+    fn new() -> Self {
+        ClassHierarchyCollector {
+            classes: HashMap::new(),
+        }
+    }
+
+    fn process_module(
+        &mut self,
+        module_spec: String,
+        source_path: PathBuf,
+    ) -> Result<(), std::io::Error> {
+        let file_content = fs::read_to_string(&source_path)?;
+        let Ok(module) = parse_python_source(&file_content) else {
+            return Ok(());
+        };
+        let body = &module.expect_module().body;
+        let mut names_provider = FullyQualifiedNameProvider::new(&module_spec, &source_path);
+        names_provider.build_scopes(body);
+        self.visit_body(&module_spec, &mut names_provider, body);
+        Ok(())
+    }
+
+    /// Classes registered by one of their bases when they are defined, mapped to that base.
+    /// Bases matching a pattern of `non_registering_bases` are ignored.
+    fn get_registered_classes(
+        &self,
+        non_registering_bases: HashSet<String>,
+    ) -> PyResult<HashMap<String, String>> {
+        let non_registering_bases = SymbolPatterns::new(non_registering_bases)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let mut registered_classes: HashMap<String, String> = HashMap::new();
+        for (fqn, class_info) in &self.classes {
+            // a metaclass creates the class itself, `__init_subclass__` only runs for subclasses
+            if class_info.has_custom_metaclass && non_registering_bases.find_match(fqn).is_none() {
+                registered_classes.insert(fqn.to_owned(), fqn.to_owned());
+            } else if let Some(base) =
+                self.find_registry_base(fqn, &non_registering_bases, &mut HashSet::new())
+            {
+                registered_classes.insert(fqn.to_owned(), base);
+            }
+        }
+        Ok(registered_classes)
+    }
+}
+
+impl ClassHierarchyCollector {
+    fn visit_body(
+        &mut self,
+        module_spec: &str,
+        names_provider: &mut FullyQualifiedNameProvider,
+        body: &[Stmt],
+    ) {
+        for stmt in body {
+            names_provider.visit_stmt(stmt);
+            match stmt {
+                Stmt::ClassDef(class_def) => {
+                    self.visit_class_def(module_spec, names_provider, class_def)
+                }
+                // classes might be defined conditionally, e.g. depending on the python version
+                Stmt::If(stmt_if) => {
+                    self.visit_body(module_spec, names_provider, &stmt_if.body);
+                    for clause in &stmt_if.elif_else_clauses {
+                        self.visit_body(module_spec, names_provider, &clause.body);
+                    }
+                }
+                Stmt::Try(stmt_try) => {
+                    self.visit_body(module_spec, names_provider, &stmt_try.body);
+                    for handler in &stmt_try.handlers {
+                        let ExceptHandler::ExceptHandler(handler) = handler;
+                        self.visit_body(module_spec, names_provider, &handler.body);
+                    }
+                    self.visit_body(module_spec, names_provider, &stmt_try.orelse);
+                    self.visit_body(module_spec, names_provider, &stmt_try.finalbody);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn visit_class_def(
+        &mut self,
+        module_spec: &str,
+        names_provider: &FullyQualifiedNameProvider,
+        class_def: &StmtClassDef,
+    ) {
+        let bases = class_def
+            .bases()
+            .iter()
+            .map(|base| names_provider.get_expr_fully_qualified_name(base))
+            .collect();
+        let has_custom_metaclass = class_def
+            .keywords()
+            .iter()
+            .filter(|keyword| {
+                keyword
+                    .arg
+                    .as_ref()
+                    .is_some_and(|arg| arg.as_str() == "metaclass")
+            })
+            .any(|keyword| {
+                !names_provider
+                    .get_expr_fully_qualified_name(&keyword.value)
+                    .iter()
+                    .any(|fqn| PLAIN_METACLASSES.contains(&fqn.as_str()))
+            });
+        let defines_init_subclass = class_def.body.iter().any(|stmt| {
+            stmt.as_function_def_stmt()
+                .is_some_and(|func_def| func_def.name.as_str() == "__init_subclass__")
+        });
+        self.classes.insert(
+            format!("{}.{}", module_spec, class_def.name),
+            ClassInfo {
+                bases,
+                defines_init_subclass,
+                has_custom_metaclass,
+            },
+        );
+    }
+
+    fn find_registry_base(
+        &self,
+        fqn: &str,
+        non_registering_bases: &SymbolPatterns,
+        visited: &mut HashSet<String>,
+    ) -> Option<String> {
+        if !visited.insert(fqn.to_owned()) {
+            return None;
+        }
+        let class_info = self.classes.get(fqn)?;
+        for base in class_info.bases.iter().flatten() {
+            let Some(base_info) = self.classes.get(base) else {
+                continue;
+            };
+            // subclasses of an ignored base are not registered through it
+            if non_registering_bases.find_match(base).is_some() {
+                continue;
+            }
+            if base_info.is_registry() {
+                return Some(base.to_owned());
+            }
+            if let Some(registry_base) =
+                self.find_registry_base(base, non_registering_bases, visited)
+            {
+                return Some(registry_base);
+            }
+        }
+        None
+    }
+}
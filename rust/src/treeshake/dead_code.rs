@@ -0,0 +1,350 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use pyo3::{pyclass, pymethods};
+use ruff_python_ast::{
+    AtomicNodeIndex, ExceptHandler, Expr, ExprContext, Number, Pattern, Stmt, StmtIf, StmtPass,
+    UnaryOp,
+    visitor::{Visitor, walk_except_handler, walk_expr, walk_pattern, walk_stmt},
+};
+use ruff_python_parser::parse_module;
+use ruff_text_size::TextRange;
+
+use crate::common::ast::{
+    generate_source,
+    transformer::{self, Transformer},
+};
+
+/// Statements after these statements are never executed
+fn is_terminal(stmt: &Stmt) -> bool {
+    matches!(
+        stmt,
+        Stmt::Return(_) | Stmt::Raise(_) | Stmt::Continue(_) | Stmt::Break(_)
+    )
+}
+
+#[derive(Default)]
+struct ReadNamesCounter {
+    counts: HashMap<String, usize>,
+}
+
+impl<'a> Visitor<'a> for ReadNamesCounter {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Name(name) if name.ctx == ExprContext::Load => {
+                *self.counts.entry(name.id.to_string()).or_default() += 1;
+            }
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+/// How often each name is read by `body`, including nested scopes
+fn count_read_names(body: &[Stmt]) -> HashMap<String, usize> {
+    let mut counter = ReadNamesCounter::default();
+    counter.visit_body(body);
+    counter.counts
+}
+
+struct ScopeEffectsFinder<'b> {
+    affects_scope: bool,
+    // names the enclosing function reads outside of the visited statements
+    read_names: &'b HashSet<&'b str>,
+}
+
+impl ScopeEffectsFinder<'_> {
+    fn bind(&mut self, name: &str) {
+        if self.read_names.contains(name) {
+            self.affects_scope = true;
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for ScopeEffectsFinder<'_> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::Global(_) | Stmt::Nonlocal(_) => self.affects_scope = true,
+            // nested scopes only bind their own name in the function
+            Stmt::FunctionDef(func_def) => {
+                self.bind(func_def.name.as_str());
+                return;
+            }
+            Stmt::ClassDef(class_def) => {
+                self.bind(class_def.name.as_str());
+                return;
+            }
+            Stmt::Import(import) => {
+                for alias in &import.names {
+                    match &alias.asname {
+                        Some(asname) => self.bind(asname.as_str()),
+                        // `import a.b` binds `a`
+                        None => self.bind(alias.name.split('.').next().unwrap_or_default()),
+                    }
+                }
+            }
+            Stmt::ImportFrom(import_from) => {
+                for alias in &import_from.names {
+                    self.bind(alias.asname.as_ref().unwrap_or(&alias.name).as_str());
+                }
+            }
+            _ => {}
+        }
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Yield(_) | Expr::YieldFrom(_) => self.affects_scope = true,
+            Expr::Name(name) if name.ctx != ExprContext::Load => self.bind(name.id.as_str()),
+            Expr::Lambda(_) => return,
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+
+    fn visit_except_handler(&mut self, except_handler: &'a ExceptHandler) {
+        let ExceptHandler::ExceptHandler(handler) = except_handler;
+        if let Some(name) = &handler.name {
+            self.bind(name.as_str());
+        }
+        walk_except_handler(self, except_handler);
+    }
+
+    fn visit_pattern(&mut self, pattern: &'a Pattern) {
+        let captured_name = match pattern {
+            Pattern::MatchAs(match_as) => match_as.name.as_ref(),
+            Pattern::MatchStar(match_star) => match_star.name.as_ref(),
+            Pattern::MatchMapping(match_mapping) => match_mapping.rest.as_ref(),
+            _ => None,
+        };
+        if let Some(name) = captured_name {
+            self.bind(name.as_str());
+        }
+        walk_pattern(self, pattern);
+    }
+}
+
+fn make_pass() -> Stmt {
+    Stmt::Pass(StmtPass {
+        range: TextRange::default(),
+        node_index: AtomicNodeIndex::default(),
+    })
+}
+
+/// Removes statements which are never executed, e.g. after a `return` or in an `if False:` block,
+/// so the names they use are not counted as references.
+#[pyclass]
+pub struct DeadCodeRemover {
+    // the bundle runs with `python -O`, so `__debug__` is false and asserts are skipped
+    strip_debug: bool,
+    #[pyo3(get)]
+    statements_removed: u32,
+    // names read by the enclosing functions, innermost last
+    function_read_names: Vec<HashMap<String, usize>>,
+}
+
+#[pymethods]
+impl DeadCodeRemover {
+    #[new]
+    #[pyo3(signature = (strip_debug=false))]
+    fn new(strip_debug: bool) -> Self {
+        DeadCodeRemover {
+            strip_debug,
+            statements_removed: 0,
+            function_read_names: Vec::new(),
+        }
+    }
+
+    fn process_module(&mut self, source_path: PathBuf) -> Result<(), std::io::Error> {
+        let file_content = fs::read_to_string(&source_path)?;
+        let Ok(parsed) = parse_module(&file_content) else {
+            return Ok(());
+        };
+        let statements_removed = self.statements_removed;
+        let new_body = self.visit_body(&parsed.syntax().body);
+        if self.statements_removed > statements_removed {
+            fs::write(
+                &source_path,
+                generate_source(&new_body, parsed, &file_content),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl DeadCodeRemover {
+    /// Unreachable code still matters if it declares globals, turns a function into a generator
+    /// or binds a name the function reads elsewhere, which makes the name local to the function
+    fn affects_scope(&self, body: &[Stmt]) -> bool {
+        let read_names: HashSet<&str> = match self.function_read_names.last() {
+            Some(function_read_names) => {
+                let unreachable_read_names = count_read_names(body);
+                function_read_names
+                    .iter()
+                    .filter(|(name, count)| {
+                        unreachable_read_names
+                            .get(name.as_str())
+                            .is_none_or(|unreachable_count| unreachable_count < count)
+                    })
+                    .map(|(name, _)| name.as_str())
+                    .collect()
+            }
+            None => HashSet::new(),
+        };
+        let mut finder = ScopeEffectsFinder {
+            affects_scope: false,
+            read_names: &read_names,
+        };
+        finder.visit_body(body);
+        finder.affects_scope
+    }
+
+    fn get_constant_truth(&self, expr: &Expr) -> Option<bool> {
+        match expr {
+            Expr::BooleanLiteral(boolean) => Some(boolean.value),
+            Expr::NoneLiteral(_) => Some(false),
+            Expr::NumberLiteral(number) => match &number.value {
+                Number::Int(int) => Some(int.as_u64() != Some(0)),
+                _ => None,
+            },
+            Expr::Name(name) if name.id.as_str() == "__debug__" && self.strip_debug => Some(false),
+            Expr::UnaryOp(unary_op) if unary_op.op == UnaryOp::Not => self
+                .get_constant_truth(&unary_op.operand)
+                .map(|truth| !truth),
+            _ => None,
+        }
+    }
+
+    fn remove_stmts(&mut self, body: &[Stmt]) {
+        self.statements_removed += body.len() as u32;
+    }
+
+    fn fold_body(&mut self, body: Vec<Stmt>) -> Vec<Stmt> {
+        let mut new_body: Vec<Stmt> = Vec::new();
+        for stmt in body {
+            new_body.extend(self.fold_stmt(stmt));
+        }
+        new_body
+    }
+
+    /// Replaces statements with a constant condition by the statements which are executed
+    fn fold_stmt(&mut self, stmt: Stmt) -> Vec<Stmt> {
+        match stmt {
+            Stmt::If(stmt_if) => self.fold_if(stmt_if),
+            Stmt::While(stmt_while)
+                if self.get_constant_truth(&stmt_while.test) == Some(false)
+                    && !self.affects_scope(&stmt_while.body) =>
+            {
+                self.remove_stmts(&stmt_while.body);
+                self.fold_body(stmt_while.orelse)
+            }
+            Stmt::Assert(_) if self.strip_debug => {
+                self.statements_removed += 1;
+                Vec::new()
+            }
+            _ => vec![stmt],
+        }
+    }
+
+    fn fold_if(&mut self, mut stmt_if: StmtIf) -> Vec<Stmt> {
+        match self.get_constant_truth(&stmt_if.test) {
+            Some(true) => {
+                if stmt_if
+                    .elif_else_clauses
+                    .iter()
+                    .any(|clause| self.affects_scope(&clause.body))
+                {
+                    return vec![Stmt::If(stmt_if)];
+                }
+                for clause in &stmt_if.elif_else_clauses {
+                    self.remove_stmts(&clause.body);
+                }
+                self.fold_body(stmt_if.body)
+            }
+            Some(false) => {
+                if self.affects_scope(&stmt_if.body) {
+                    return vec![Stmt::If(stmt_if)];
+                }
+                self.remove_stmts(&stmt_if.body);
+                if stmt_if.elif_else_clauses.is_empty() {
+                    return Vec::new();
+                }
+                let clause = stmt_if.elif_else_clauses.remove(0);
+                match clause.test {
+                    Some(test) => {
+                        stmt_if.test = Box::new(test);
+                        stmt_if.body = clause.body;
+                        self.fold_if(stmt_if)
+                    }
+                    None => self.fold_body(clause.body),
+                }
+            }
+            None => {
+                let mut clauses = std::mem::take(&mut stmt_if.elif_else_clauses).into_iter();
+                while let Some(mut clause) = clauses.next() {
+                    let truth = clause
+                        .test
+                        .as_ref()
+                        .and_then(|test| self.get_constant_truth(test));
+                    match truth {
+                        Some(false) if !self.affects_scope(&clause.body) => {
+                            self.remove_stmts(&clause.body);
+                        }
+                        // the following clauses are never reached
+                        Some(true)
+                            if !clauses
+                                .as_slice()
+                                .iter()
+                                .any(|clause| self.affects_scope(&clause.body)) =>
+                        {
+                            for clause in clauses.by_ref() {
+                                self.remove_stmts(&clause.body);
+                            }
+                            clause.test = None;
+                            stmt_if.elif_else_clauses.push(clause);
+                        }
+                        _ => stmt_if.elif_else_clauses.push(clause),
+                    }
+                }
+                vec![Stmt::If(stmt_if)]
+            }
+        }
+    }
+}
+
+impl Transformer for DeadCodeRemover {
+    fn visit_stmt(&mut self, stmt: Stmt) -> Option<Stmt> {
+        let Stmt::FunctionDef(func_def) = &stmt else {
+            return transformer::walk_stmt(self, stmt);
+        };
+        self.function_read_names
+            .push(count_read_names(&func_def.body));
+        let new_stmt = transformer::walk_stmt(self, stmt);
+        self.function_read_names.pop();
+        new_stmt
+    }
+
+    fn visit_body(&mut self, body: &[Stmt]) -> Vec<Stmt> {
+        let folded_body = self.fold_body(body.to_vec());
+        let mut new_body: Vec<Stmt> = Vec::new();
+        for (index, stmt) in folded_body.iter().enumerate() {
+            if let Some(new_stmt) = self.visit_stmt(stmt.to_owned()) {
+                new_body.push(new_stmt);
+            }
+            let unreachable = &folded_body[index + 1..];
+            if is_terminal(stmt) && !unreachable.is_empty() && !self.affects_scope(unreachable) {
+                self.remove_stmts(unreachable);
+                break;
+            }
+        }
+        // blocks can't be empty
+        if new_body.is_empty() && !body.is_empty() {
+            new_body.push(make_pass());
+        }
+        new_body
+    }
+}
@@ -0,0 +1,677 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use ruff_python_ast::{
+    AtomicNodeIndex, CmpOp, ExceptHandler, Expr, ExprBooleanLiteral, ExprCall, ExprContext,
+    ExprNoneLiteral, Number, Parameters, Stmt, StmtFunctionDef, UnaryOp,
+    visitor::{Visitor, walk_expr, walk_stmt},
+};
+use ruff_python_parser::parse_module;
+use ruff_text_size::TextRange;
+
+use crate::common::{
+    ast::{
+        generate_source, get_import_from_absolute_module_spec, parse_python_source,
+        providers::fully_qualified_name_provider::FullyQualifiedNameProvider,
+    },
+    module_spec::remove_last_segment,
+    patterns::SymbolPatterns,
+};
+
+use super::purity::get_callee_fully_qualified_names;
+
+// re-exports are followed up to this depth
+const MAX_ALIAS_DEPTH: usize = 16;
+
+#[derive(Clone)]
+struct CallSite {
+    positional_count: usize,
+    keywords: HashSet<String>,
+    // arguments are unpacked with `*` or `**`, so it is unknown which parameters are passed
+    has_unpacked_arguments: bool,
+}
+
+impl CallSite {
+    fn new(call: &ExprCall) -> Self {
+        let arguments = &call.arguments;
+        CallSite {
+            positional_count: arguments.args.len(),
+            keywords: arguments
+                .keywords
+                .iter()
+                .filter_map(|keyword| keyword.arg.as_ref().map(|arg| arg.to_string()))
+                .collect(),
+            has_unpacked_arguments: arguments.args.iter().any(|arg| arg.is_starred_expr())
+                || arguments
+                    .keywords
+                    .iter()
+                    .any(|keyword| keyword.arg.is_none()),
+        }
+    }
+
+    fn passes(&self, parameter: &OptionalParameter) -> bool {
+        parameter
+            .position
+            .is_some_and(|position| position < self.positional_count)
+            || self.keywords.contains(&parameter.name)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct DefaultValue {
+    truth: bool,
+    is_none: bool,
+}
+
+struct OptionalParameter {
+    name: String,
+    // None for keyword-only parameters
+    position: Option<usize>,
+    // set for literal defaults, which conditions can be evaluated with
+    literal_default: Option<DefaultValue>,
+}
+
+fn get_literal_default(expr: &Expr) -> Option<DefaultValue> {
+    let truth = match expr {
+        Expr::NoneLiteral(_) => false,
+        Expr::BooleanLiteral(boolean) => boolean.value,
+        Expr::StringLiteral(string) => !string.value.to_str().is_empty(),
+        Expr::NumberLiteral(number) => match &number.value {
+            Number::Int(int) => int.as_u64() != Some(0),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(DefaultValue {
+        truth,
+        is_none: expr.is_none_literal_expr(),
+    })
+}
+
+fn is_literal(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::NoneLiteral(_)
+            | Expr::BooleanLiteral(_)
+            | Expr::StringLiteral(_)
+            | Expr::BytesLiteral(_)
+            | Expr::NumberLiteral(_)
+            | Expr::EllipsisLiteral(_)
+    )
+}
+
+fn get_optional_parameters(parameters: &Parameters) -> Vec<OptionalParameter> {
+    let positional = parameters
+        .posonlyargs
+        .iter()
+        .chain(&parameters.args)
+        .enumerate()
+        .map(|(position, parameter)| (Some(position), parameter));
+    let keyword_only = parameters
+        .kwonlyargs
+        .iter()
+        .map(|parameter| (None, parameter));
+    positional
+        .chain(keyword_only)
+        .filter_map(|(position, parameter)| {
+            let default = parameter.default.as_ref()?;
+            Some(OptionalParameter {
+                name: parameter.parameter.name.to_string(),
+                position,
+                literal_default: get_literal_default(default),
+            })
+        })
+        .collect()
+}
+
+struct RebindingFinder<'b> {
+    names: HashSet<&'b str>,
+    rebound_names: HashSet<String>,
+}
+
+impl<'a> Visitor<'a> for RebindingFinder<'_> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        let declared_names = match stmt {
+            Stmt::Global(global) => global.names.as_slice(),
+            Stmt::Nonlocal(nonlocal) => nonlocal.names.as_slice(),
+            _ => &[],
+        };
+        for name in declared_names {
+            self.rebound_names.insert(name.to_string());
+        }
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Name(name)
+                if name.ctx != ExprContext::Load && self.names.contains(name.id.as_str()) =>
+            {
+                self.rebound_names.insert(name.id.to_string());
+            }
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+/// Parameters which are assigned, deleted or declared global or nonlocal somewhere in `body`
+fn get_rebound_names(names: &HashMap<String, DefaultValue>, body: &[Stmt]) -> HashSet<String> {
+    let mut finder = RebindingFinder {
+        names: names.keys().map(|name| name.as_str()).collect(),
+        rebound_names: HashSet::new(),
+    };
+    finder.visit_body(body);
+    finder.rebound_names
+}
+
+/// Truth of conditions like `param`, `not param` or `param is None`,
+/// given parameters which always have their default value
+fn get_condition_truth(test: &Expr, defaults: &HashMap<String, DefaultValue>) -> Option<bool> {
+    match test {
+        Expr::Name(name) => defaults.get(name.id.as_str()).map(|default| default.truth),
+        Expr::UnaryOp(unary_op) if unary_op.op == UnaryOp::Not => {
+            get_condition_truth(&unary_op.operand, defaults).map(|truth| !truth)
+        }
+        Expr::Compare(compare) => {
+            let ([op], [comparator]) = (&*compare.ops, &*compare.comparators) else {
+                return None;
+            };
+            let Expr::Name(name) = &*compare.left else {
+                return None;
+            };
+            if !comparator.is_none_literal_expr() {
+                return None;
+            }
+            let default = defaults.get(name.id.as_str())?;
+            match op {
+                CmpOp::Is => Some(default.is_none),
+                CmpOp::IsNot => Some(!default.is_none),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn fold_condition(test: &mut Expr, defaults: &HashMap<String, DefaultValue>) -> u32 {
+    let Some(truth) = get_condition_truth(test, defaults) else {
+        return 0;
+    };
+    *test = Expr::BooleanLiteral(ExprBooleanLiteral {
+        node_index: AtomicNodeIndex::default(),
+        range: TextRange::default(),
+        value: truth,
+    });
+    1
+}
+
+/// Replaces conditions on parameters by their result,
+/// the dead code remover removes the branches which are never executed afterwards
+fn fold_conditions(body: &mut [Stmt], defaults: &HashMap<String, DefaultValue>) -> u32 {
+    let mut folded = 0;
+    for stmt in body.iter_mut() {
+        match stmt {
+            Stmt::If(stmt_if) => {
+                folded += fold_condition(&mut stmt_if.test, defaults);
+                folded += fold_conditions(&mut stmt_if.body, defaults);
+                for clause in &mut stmt_if.elif_else_clauses {
+                    if let Some(test) = &mut clause.test {
+                        folded += fold_condition(test, defaults);
+                    }
+                    folded += fold_conditions(&mut clause.body, defaults);
+                }
+            }
+            Stmt::For(stmt_for) => {
+                folded += fold_conditions(&mut stmt_for.body, defaults);
+                folded += fold_conditions(&mut stmt_for.orelse, defaults);
+            }
+            Stmt::While(stmt_while) => {
+                folded += fold_conditions(&mut stmt_while.body, defaults);
+                folded += fold_conditions(&mut stmt_while.orelse, defaults);
+            }
+            Stmt::With(stmt_with) => folded += fold_conditions(&mut stmt_with.body, defaults),
+            Stmt::Try(stmt_try) => {
+                folded += fold_conditions(&mut stmt_try.body, defaults);
+                for handler in &mut stmt_try.handlers {
+                    let ExceptHandler::ExceptHandler(handler) = handler;
+                    folded += fold_conditions(&mut handler.body, defaults);
+                }
+                folded += fold_conditions(&mut stmt_try.orelse, defaults);
+                folded += fold_conditions(&mut stmt_try.finalbody, defaults);
+            }
+            Stmt::Match(stmt_match) => {
+                for case in &mut stmt_match.cases {
+                    folded += fold_conditions(&mut case.body, defaults);
+                }
+            }
+            // nested functions and classes might shadow the parameters
+            _ => {}
+        }
+    }
+    folded
+}
+
+/// Collects how names are called and which names are used otherwise
+struct CallSitesVisitor<'b> {
+    names_provider: FullyQualifiedNameProvider,
+    module_spec: &'b str,
+    calls: &'b mut HashMap<String, Vec<CallSite>>,
+    escaped_names: &'b mut HashSet<String>,
+    aliases: &'b mut HashMap<String, String>,
+    star_imports: &'b mut HashMap<String, Vec<String>>,
+}
+
+impl CallSitesVisitor<'_> {
+    fn collect_aliases(&mut self, stmt: &Stmt) {
+        if !self.names_provider.is_global_scope() {
+            return;
+        }
+        match stmt {
+            Stmt::Import(import) => {
+                for alias in &import.names {
+                    if let Some(asname) = &alias.asname {
+                        self.aliases.insert(
+                            format!("{}.{}", self.module_spec, asname),
+                            alias.name.to_string(),
+                        );
+                    }
+                }
+            }
+            Stmt::ImportFrom(import_from) => {
+                let Ok(module_specs) = get_import_from_absolute_module_spec(
+                    import_from,
+                    &self
+                        .names_provider
+                        .get_imports_provider()
+                        .get_parent_package(),
+                    false,
+                ) else {
+                    return;
+                };
+                for module_spec in module_specs {
+                    for alias in &import_from.names {
+                        if alias.name.as_str() == "*" {
+                            self.star_imports
+                                .entry(self.module_spec.to_owned())
+                                .or_default()
+                                .push(module_spec.to_owned());
+                            continue;
+                        }
+                        let bound_name = alias.asname.as_ref().unwrap_or(&alias.name);
+                        self.aliases.insert(
+                            format!("{}.{}", self.module_spec, bound_name),
+                            format!("{}.{}", module_spec, alias.name),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for CallSitesVisitor<'_> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        self.collect_aliases(stmt);
+        // decorators, defaults and bases are evaluated in the enclosing scope
+        match stmt {
+            Stmt::FunctionDef(func_def) => {
+                for decorator in &func_def.decorator_list {
+                    self.visit_decorator(decorator);
+                }
+                self.visit_parameters(&func_def.parameters);
+                if let Some(returns) = &func_def.returns {
+                    self.visit_annotation(returns);
+                }
+            }
+            Stmt::ClassDef(class_def) => {
+                for decorator in &class_def.decorator_list {
+                    self.visit_decorator(decorator);
+                }
+                if let Some(arguments) = &class_def.arguments {
+                    self.visit_arguments(arguments);
+                }
+            }
+            _ => {}
+        }
+        let scope = self.names_provider.enter_scope(stmt);
+        self.names_provider.visit_stmt(stmt);
+        match stmt {
+            Stmt::FunctionDef(func_def) => self.visit_body(&func_def.body),
+            Stmt::ClassDef(class_def) => self.visit_body(&class_def.body),
+            _ => walk_stmt(self, stmt),
+        }
+        self.names_provider.exit_scope(scope);
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Call(call) => {
+                let callees = get_callee_fully_qualified_names(&self.names_provider, &call.func);
+                for callee in &callees {
+                    self.calls
+                        .entry(callee.to_owned())
+                        .or_default()
+                        .push(CallSite::new(call));
+                }
+                // a called name is not used otherwise
+                if callees.is_empty() {
+                    self.visit_expr(&call.func);
+                }
+                self.visit_arguments(&call.arguments);
+                return;
+            }
+            // functions used as values might be called with any arguments
+            Expr::Name(_) | Expr::Attribute(_) => {
+                self.escaped_names
+                    .extend(self.names_provider.get_expr_fully_qualified_name(expr));
+            }
+            _ => {}
+        }
+        let scope = self.names_provider.enter_expr_scope(expr);
+        walk_expr(self, expr);
+        self.names_provider.exit_expr_scope(scope);
+    }
+}
+
+/// Finds optional parameters of first-party functions which are passed by every call
+/// or by no call at all, assuming the functions are only called by the bundle itself.
+/// Defaults which are never used are replaced by `None`, conditions on parameters which
+/// always have their default value are replaced by their result, so the dead code remover
+/// removes the branches which are never executed.
+#[pyclass]
+pub struct UnusedDefaultsRemover {
+    packages: Vec<String>,
+    functions: HashMap<String, Vec<OptionalParameter>>,
+    // decorated or defined more than once, so calls can't be matched with the parameters
+    unknown_functions: HashSet<String>,
+    calls: HashMap<String, Vec<CallSite>>,
+    escaped_names: HashSet<String>,
+    // names bound by imports, mapped to the imported name
+    aliases: HashMap<String, String>,
+    // module -> modules it star imports
+    star_imports: HashMap<String, Vec<String>>,
+    // function -> parameters passed by every call
+    #[pyo3(get)]
+    always_passed: HashMap<String, HashSet<String>>,
+    // function -> parameters with a literal default which are never passed
+    #[pyo3(get)]
+    never_passed: HashMap<String, HashSet<String>>,
+    #[pyo3(get)]
+    defaults_removed: u32,
+    #[pyo3(get)]
+    conditions_folded: u32,
+}
+
+#[pymethods]
+impl UnusedDefaultsRemover {
+    #[new]
+    fn new(packages: Vec<String>) -> Self {
+        UnusedDefaultsRemover {
+            packages,
+            functions: HashMap::new(),
+            unknown_functions: HashSet::new(),
+            calls: HashMap::new(),
+            escaped_names: HashSet::new(),
+            aliases: HashMap::new(),
+            star_imports: HashMap::new(),
+            always_passed: HashMap::new(),
+            never_passed: HashMap::new(),
+            defaults_removed: 0,
+            conditions_folded: 0,
+        }
+    }
+
+    fn collect_module(
+        &mut self,
+        module_spec: String,
+        source_path: PathBuf,
+    ) -> Result<(), std::io::Error> {
+        let file_content = fs::read_to_string(&source_path)?;
+        let Ok(module) = parse_python_source(&file_content) else {
+            return Ok(());
+        };
+        let body = &module.expect_module().body;
+        if self.is_first_party(&module_spec) {
+            self.collect_functions(&module_spec, body);
+        }
+        let mut names_provider = FullyQualifiedNameProvider::new(&module_spec, &source_path);
+        names_provider.build_scopes(body);
+        let mut visitor = CallSitesVisitor {
+            names_provider,
+            module_spec: &module_spec,
+            calls: &mut self.calls,
+            escaped_names: &mut self.escaped_names,
+            aliases: &mut self.aliases,
+            star_imports: &mut self.star_imports,
+        };
+        visitor.visit_body(body);
+        Ok(())
+    }
+
+    /// Matches the collected calls with the functions,
+    /// functions matching `preserve_symbols` are called from outside of the bundle
+    fn analyze(&mut self, preserve_symbols: HashSet<String>) -> PyResult<()> {
+        let preserve_symbols = SymbolPatterns::new(preserve_symbols)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let mut calls: HashMap<String, Vec<CallSite>> = HashMap::new();
+        for (callee, call_sites) in &self.calls {
+            calls
+                .entry(self.resolve_alias(callee))
+                .or_default()
+                .extend(call_sites.iter().cloned());
+        }
+        let escaped_names: HashSet<String> = self
+            .escaped_names
+            .iter()
+            .map(|name| self.resolve_alias(name))
+            .collect();
+        for (fqn, parameters) in &self.functions {
+            if self.unknown_functions.contains(fqn)
+                || escaped_names.contains(fqn)
+                || preserve_symbols.find_match(fqn).is_some()
+            {
+                continue;
+            }
+            let Some(call_sites) = calls.get(fqn) else {
+                continue;
+            };
+            if call_sites
+                .iter()
+                .any(|call_site| call_site.has_unpacked_arguments)
+            {
+                continue;
+            }
+            for parameter in parameters {
+                let passed_count = call_sites
+                    .iter()
+                    .filter(|call_site| call_site.passes(parameter))
+                    .count();
+                if passed_count == call_sites.len() {
+                    self.always_passed
+                        .entry(fqn.to_owned())
+                        .or_default()
+                        .insert(parameter.name.to_owned());
+                } else if passed_count == 0 && parameter.literal_default.is_some() {
+                    self.never_passed
+                        .entry(fqn.to_owned())
+                        .or_default()
+                        .insert(parameter.name.to_owned());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn process_module(
+        &mut self,
+        module_spec: String,
+        source_path: PathBuf,
+    ) -> Result<(), std::io::Error> {
+        if !self.is_first_party(&module_spec) {
+            return Ok(());
+        }
+        let file_content = fs::read_to_string(&source_path)?;
+        let Ok(parsed) = parse_module(&file_content) else {
+            return Ok(());
+        };
+        let mut body = parsed.syntax().body.clone();
+        if self.rewrite_body(&module_spec, &mut body) {
+            fs::write(&source_path, generate_source(&body, parsed, &file_content))?;
+        }
+        Ok(())
+    }
+}
+
+impl UnusedDefaultsRemover {
+    fn is_first_party(&self, module_spec: &str) -> bool {
+        self.packages.iter().any(|package| {
+            module_spec == package
+                || module_spec
+                    .strip_prefix(package.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+
+    /// Follows re-exports of a name to the function it refers to
+    fn resolve_alias(&self, fqn: &str) -> String {
+        let mut resolved = fqn.to_owned();
+        for _ in 0..MAX_ALIAS_DEPTH {
+            if self.functions.contains_key(&resolved) {
+                break;
+            }
+            if let Some(target) = self.aliases.get(&resolved) {
+                resolved = target.to_owned();
+                continue;
+            }
+            let module_spec = remove_last_segment(&resolved);
+            let name = &resolved[module_spec.len()..];
+            let Some(star_imported) = self.star_imports.get(module_spec).and_then(|modules| {
+                modules
+                    .iter()
+                    .map(|module| format!("{}{}", module, name))
+                    .find(|candidate| {
+                        self.functions.contains_key(candidate)
+                            || self.aliases.contains_key(candidate)
+                    })
+            }) else {
+                break;
+            };
+            resolved = star_imported;
+        }
+        resolved
+    }
+
+    fn collect_functions(&mut self, module_spec: &str, body: &[Stmt]) {
+        for stmt in body {
+            match stmt {
+                Stmt::FunctionDef(func_def) => {
+                    let fqn = format!("{}.{}", module_spec, func_def.name);
+                    // decorated functions are called by their decorator
+                    if !func_def.decorator_list.is_empty() || self.functions.contains_key(&fqn) {
+                        self.unknown_functions.insert(fqn.to_owned());
+                    }
+                    self.functions
+                        .insert(fqn, get_optional_parameters(&func_def.parameters));
+                }
+                Stmt::If(stmt_if) => {
+                    self.collect_functions(module_spec, &stmt_if.body);
+                    for clause in &stmt_if.elif_else_clauses {
+                        self.collect_functions(module_spec, &clause.body);
+                    }
+                }
+                Stmt::Try(stmt_try) => {
+                    self.collect_functions(module_spec, &stmt_try.body);
+                    for handler in &stmt_try.handlers {
+                        let ExceptHandler::ExceptHandler(handler) = handler;
+                        self.collect_functions(module_spec, &handler.body);
+                    }
+                    self.collect_functions(module_spec, &stmt_try.orelse);
+                    self.collect_functions(module_spec, &stmt_try.finalbody);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn rewrite_body(&mut self, module_spec: &str, body: &mut [Stmt]) -> bool {
+        let mut rewritten = false;
+        for stmt in body.iter_mut() {
+            match stmt {
+                Stmt::FunctionDef(func_def) => {
+                    rewritten |= self.rewrite_function(module_spec, func_def);
+                }
+                Stmt::If(stmt_if) => {
+                    rewritten |= self.rewrite_body(module_spec, &mut stmt_if.body);
+                    for clause in &mut stmt_if.elif_else_clauses {
+                        rewritten |= self.rewrite_body(module_spec, &mut clause.body);
+                    }
+                }
+                Stmt::Try(stmt_try) => {
+                    rewritten |= self.rewrite_body(module_spec, &mut stmt_try.body);
+                    for handler in &mut stmt_try.handlers {
+                        let ExceptHandler::ExceptHandler(handler) = handler;
+                        rewritten |= self.rewrite_body(module_spec, &mut handler.body);
+                    }
+                    rewritten |= self.rewrite_body(module_spec, &mut stmt_try.orelse);
+                    rewritten |= self.rewrite_body(module_spec, &mut stmt_try.finalbody);
+                }
+                _ => {}
+            }
+        }
+        rewritten
+    }
+
+    fn rewrite_function(&mut self, module_spec: &str, func_def: &mut StmtFunctionDef) -> bool {
+        let fqn = format!("{}.{}", module_spec, func_def.name);
+        let always_passed = self.always_passed.get(&fqn);
+        let never_passed = self.never_passed.get(&fqn);
+        if always_passed.is_none() && never_passed.is_none() {
+            return false;
+        }
+        let mut defaults_removed = 0;
+        let mut defaults: HashMap<String, DefaultValue> = HashMap::new();
+        let parameters = &mut func_def.parameters;
+        for parameter in parameters
+            .posonlyargs
+            .iter_mut()
+            .chain(parameters.args.iter_mut())
+            .chain(parameters.kwonlyargs.iter_mut())
+        {
+            let name = parameter.parameter.name.as_str();
+            let Some(default) = &mut parameter.default else {
+                continue;
+            };
+            if always_passed.is_some_and(|names| names.contains(name)) {
+                // the dependencies of the default are not needed anymore
+                if !is_literal(default) {
+                    **default = Expr::NoneLiteral(ExprNoneLiteral {
+                        node_index: AtomicNodeIndex::default(),
+                        range: TextRange::default(),
+                    });
+                    defaults_removed += 1;
+                }
+            } else if never_passed.is_some_and(|names| names.contains(name)) {
+                if let Some(literal_default) = get_literal_default(default) {
+                    defaults.insert(name.to_owned(), literal_default);
+                }
+            }
+        }
+        for rebound_name in get_rebound_names(&defaults, &func_def.body) {
+            defaults.remove(&rebound_name);
+        }
+        let conditions_folded = if defaults.is_empty() {
+            0
+        } else {
+            fold_conditions(&mut func_def.body, &defaults)
+        };
+        self.defaults_removed += defaults_removed;
+        self.conditions_folded += conditions_folded;
+        defaults_removed > 0 || conditions_folded > 0
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use pyo3::{pyclass, pymethods};
+use ruff_python_ast::{ExceptHandler, Expr, Operator, Stmt};
+
+use crate::common::ast::{
+    full_name::get_full_name_for_expr, parse_python_source,
+    providers::imports_provider::ImportsTrackingProvider,
+};
+
+const DUNDER_ALL: &str = "__all__";
+
+fn is_dunder_all(expr: &Expr) -> bool {
+    expr.as_name_expr()
+        .is_some_and(|name| name.id.as_str() == DUNDER_ALL)
+}
+
+struct ModuleExports {
+    names: HashSet<String>,
+    // modules whose `__all__` is included, e.g. `__all__ = [...] + other.__all__`
+    included_modules: Vec<String>,
+    // false if any part of `__all__` can't be evaluated without running the module
+    is_static: bool,
+}
+
+struct ModuleExportsEvaluator {
+    imports_provider: ImportsTrackingProvider,
+    exports: ModuleExports,
+    found: bool,
+    // imported names are only public if they are re-exported by a package
+    is_package: bool,
+    public_names: HashSet<String>,
+}
+
+impl ModuleExportsEvaluator {
+    fn add_public_name(&mut self, name: &str) {
+        if !name.starts_with('_') {
+            self.public_names.insert(name.to_owned());
+        }
+    }
+
+    fn add_public_target(&mut self, target: &Expr) {
+        match target {
+            Expr::Name(name) => self.add_public_name(name.id.as_str()),
+            Expr::Tuple(tuple) => {
+                for element in &tuple.elts {
+                    self.add_public_target(element);
+                }
+            }
+            Expr::List(list) => {
+                for element in &list.elts {
+                    self.add_public_target(element);
+                }
+            }
+            Expr::Starred(starred) => self.add_public_target(&starred.value),
+            _ => {}
+        }
+    }
+
+    fn visit_public_names(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::FunctionDef(func_def) => self.add_public_name(func_def.name.as_str()),
+            Stmt::ClassDef(class_def) => self.add_public_name(class_def.name.as_str()),
+            Stmt::Assign(assign) => {
+                for target in &assign.targets {
+                    self.add_public_target(target);
+                }
+            }
+            Stmt::AnnAssign(ann_assign) if ann_assign.value.is_some() => {
+                self.add_public_target(&ann_assign.target);
+            }
+            Stmt::TypeAlias(type_alias) => self.add_public_target(&type_alias.name),
+            Stmt::Import(import) if self.is_package => {
+                for alias in &import.names {
+                    if let Some(asname) = &alias.asname {
+                        self.add_public_name(asname.as_str());
+                    }
+                }
+            }
+            Stmt::ImportFrom(import_from) if self.is_package => {
+                for alias in &import_from.names {
+                    if alias.name.as_str() != "*" {
+                        self.add_public_name(alias.asname.as_ref().unwrap_or(&alias.name).as_str());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_body(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            self.imports_provider.visit_stmt(stmt);
+            self.visit_public_names(stmt);
+            match stmt {
+                Stmt::Assign(assign) if assign.targets.iter().any(is_dunder_all) => {
+                    self.add_expr(&assign.value);
+                }
+                Stmt::AnnAssign(ann_assign) if is_dunder_all(&ann_assign.target) => {
+                    if let Some(value) = &ann_assign.value {
+                        self.add_expr(value);
+                    }
+                }
+                Stmt::AugAssign(aug_assign) if is_dunder_all(&aug_assign.target) => {
+                    if aug_assign.op == Operator::Add {
+                        self.add_expr(&aug_assign.value);
+                    } else {
+                        self.exports.is_static = false;
+                    }
+                }
+                Stmt::Expr(stmt_expr) => self.visit_call(&stmt_expr.value),
+                // names might be exported conditionally, all branches are included
+                Stmt::If(stmt_if) => {
+                    self.visit_body(&stmt_if.body);
+                    for clause in &stmt_if.elif_else_clauses {
+                        self.visit_body(&clause.body);
+                    }
+                }
+                Stmt::Try(stmt_try) => {
+                    self.visit_body(&stmt_try.body);
+                    for handler in &stmt_try.handlers {
+                        let ExceptHandler::ExceptHandler(handler) = handler;
+                        self.visit_body(&handler.body);
+                    }
+                    self.visit_body(&stmt_try.orelse);
+                    self.visit_body(&stmt_try.finalbody);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // `__all__.extend([...])` and `__all__.append("name")`
+    fn visit_call(&mut self, expr: &Expr) {
+        let Expr::Call(call) = expr else {
+            return;
+        };
+        let Expr::Attribute(attribute) = call.func.as_ref() else {
+            return;
+        };
+        if !is_dunder_all(&attribute.value) {
+            return;
+        }
+        match (attribute.attr.as_str(), call.arguments.args.first()) {
+            ("extend", Some(argument)) => self.add_expr(argument),
+            ("append", Some(argument)) => {
+                self.found = true;
+                self.add_element(argument);
+            }
+            // removing names only keeps more than needed
+            ("remove", _) => {}
+            _ => self.exports.is_static = false,
+        }
+    }
+
+    fn add_element(&mut self, expr: &Expr) {
+        match expr {
+            Expr::StringLiteral(string_literal) => {
+                self.exports
+                    .names
+                    .insert(string_literal.value.to_str().to_owned());
+            }
+            _ => self.exports.is_static = false,
+        }
+    }
+
+    fn add_expr(&mut self, expr: &Expr) {
+        self.found = true;
+        match expr {
+            Expr::List(list) => {
+                for element in &list.elts {
+                    self.add_element(element);
+                }
+            }
+            Expr::Tuple(tuple) => {
+                for element in &tuple.elts {
+                    self.add_element(element);
+                }
+            }
+            Expr::BinOp(bin_op) if bin_op.op == Operator::Add => {
+                self.add_expr(&bin_op.left);
+                self.add_expr(&bin_op.right);
+            }
+            // `__all__ = __all__ + [...]`
+            _ if is_dunder_all(expr) => {}
+            Expr::Attribute(attribute) if attribute.attr.as_str() == DUNDER_ALL => {
+                let module_spec = get_full_name_for_expr(&attribute.value)
+                    .last()
+                    .and_then(|name| self.imports_provider.active_imports.get(name))
+                    .cloned();
+                match module_spec {
+                    Some(module_spec) => self.exports.included_modules.push(module_spec),
+                    None => self.exports.is_static = false,
+                }
+            }
+            _ => self.exports.is_static = false,
+        }
+    }
+}
+
+/// Evaluates static `__all__` definitions of modules,
+/// which define the names bound by `from module import *`.
+/// Also collects the public names of each module, which make up its API without `__all__`.
+#[pyclass]
+pub struct ExportsCollector {
+    modules: HashMap<String, ModuleExports>,
+    #[pyo3(get)]
+    public_names: HashMap<String, HashSet<String>>,
+}
+
+#[pymethods]
+impl ExportsCollector {
+    #[new]
+    fn new() -> Self {
+        ExportsCollector {
+            modules: HashMap::new(),
+            public_names: HashMap::new(),
+        }
+    }
+
+    fn process_module(
+        &mut self,
+        module_spec: String,
+        source_path: PathBuf,
+    ) -> Result<(), std::io::Error> {
+        let file_content = fs::read_to_string(&source_path)?;
+        let Ok(module) = parse_python_source(&file_content) else {
+            return Ok(());
+        };
+        let mut evaluator = ModuleExportsEvaluator {
+            imports_provider: ImportsTrackingProvider::new(&module_spec, &source_path),
+            exports: ModuleExports {
+                names: HashSet::new(),
+                included_modules: Vec::new(),
+                is_static: true,
+            },
+            found: false,
+            is_package: source_path.ends_with("__init__.py"),
+            public_names: HashSet::new(),
+        };
+        evaluator.visit_body(&module.expect_module().body);
+        // `__main__.py` shares the module spec of its package, but is never imported
+        if !source_path.ends_with("__main__.py") {
+            self.public_names
+                .entry(module_spec.clone())
+                .or_default()
+                .extend(evaluator.public_names);
+        }
+        if evaluator.found {
+            self.modules.insert(module_spec, evaluator.exports);
+        }
+        Ok(())
+    }
+
+    /// Names exported by each module with a static `__all__`
+    #[getter]
+    fn exports(&self) -> HashMap<String, HashSet<String>> {
+        self.modules
+            .keys()
+            .filter_map(|module_spec| {
+                self.resolve_exports(module_spec, &mut HashSet::new())
+                    .map(|names| (module_spec.to_owned(), names))
+            })
+            .collect()
+    }
+}
+
+impl ExportsCollector {
+    fn resolve_exports(
+        &self,
+        module_spec: &str,
+        visited: &mut HashSet<String>,
+    ) -> Option<HashSet<String>> {
+        // modules including each other don't add anything new
+        if !visited.insert(module_spec.to_owned()) {
+            return Some(HashSet::new());
+        }
+        let exports = self.modules.get(module_spec)?;
+        if !exports.is_static {
+            return None;
+        }
+        let mut names = exports.names.clone();
+        for included_module in &exports.included_modules {
+            names.extend(self.resolve_exports(included_module, visited)?);
+        }
+        Some(names)
+    }
+}
@@ -0,0 +1,103 @@
+/// Records what a single module visit of the `ReferencesCounter` read from and wrote to the
+/// shared references counts.
+///
+/// A visit only depends on the module source and on the answers to its reads.
+/// If all recorded reads still give the same answers, applying the recorded writes
+/// has the same effect as visiting the module again.
+pub enum JournalEntry {
+    HasReferences(String, bool),
+    ModuleHasReferences(bool),
+    // whether a module spec is part of the bundle
+    IsBundledModule(String, bool),
+    Increase(String),
+    MakeKnown(String),
+    // a string literal and the name it refers to
+    StringReference(String, String),
+    PinModule(String),
+    // a name and the safe decorator pattern it matched
+    PatternMatch(String, String),
+}
+
+fn bool_to_str(value: bool) -> &'static str {
+    if value { "1" } else { "0" }
+}
+
+pub fn serialize_journal(journal: &[JournalEntry]) -> String {
+    journal
+        .iter()
+        .map(|entry| match entry {
+            JournalEntry::HasReferences(fqn, result) => {
+                format!("r {} {}", bool_to_str(*result), fqn)
+            }
+            JournalEntry::ModuleHasReferences(result) => format!("m {}", bool_to_str(*result)),
+            JournalEntry::IsBundledModule(module_spec, result) => {
+                format!("b {} {}", bool_to_str(*result), module_spec)
+            }
+            JournalEntry::Increase(fqn) => format!("i {}", fqn),
+            JournalEntry::MakeKnown(fqn) => format!("k {}", fqn),
+            JournalEntry::StringReference(string, fqn) => format!("s {} {}", fqn, string),
+            JournalEntry::PinModule(module_spec) => format!("p {}", module_spec),
+            JournalEntry::PatternMatch(fqn, pattern) => format!("t {} {}", fqn, pattern),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn deserialize_journal(value: &str) -> Option<Vec<JournalEntry>> {
+    let mut journal: Vec<JournalEntry> = Vec::new();
+    for line in value.lines() {
+        let mut parts = line.splitn(2, ' ');
+        let entry = match (parts.next()?, parts.next()?) {
+            ("r", rest) => {
+                let (result, fqn) = rest.split_once(' ')?;
+                JournalEntry::HasReferences(fqn.to_owned(), result == "1")
+            }
+            ("m", result) => JournalEntry::ModuleHasReferences(result == "1"),
+            ("b", rest) => {
+                let (result, module_spec) = rest.split_once(' ')?;
+                JournalEntry::IsBundledModule(module_spec.to_owned(), result == "1")
+            }
+            ("i", fqn) => JournalEntry::Increase(fqn.to_owned()),
+            ("k", fqn) => JournalEntry::MakeKnown(fqn.to_owned()),
+            ("s", rest) => {
+                let (fqn, string) = rest.split_once(' ')?;
+                JournalEntry::StringReference(string.to_owned(), fqn.to_owned())
+            }
+            ("p", module_spec) => JournalEntry::PinModule(module_spec.to_owned()),
+            ("t", rest) => {
+                let (fqn, pattern) = rest.split_once(' ')?;
+                JournalEntry::PatternMatch(fqn.to_owned(), pattern.to_owned())
+            }
+            _ => return None,
+        };
+        journal.push(entry);
+    }
+    Some(journal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JournalEntry, deserialize_journal, serialize_journal};
+
+    #[test]
+    fn round_trips_entries() {
+        let journal = vec![
+            JournalEntry::HasReferences("pkg.module.name".to_owned(), true),
+            JournalEntry::ModuleHasReferences(false),
+            JournalEntry::IsBundledModule("pkg.plugins".to_owned(), false),
+            JournalEntry::Increase("pkg.module.name".to_owned()),
+            JournalEntry::MakeKnown("pkg.module.other".to_owned()),
+            JournalEntry::StringReference(
+                "pkg.plugins:load".to_owned(),
+                "pkg.plugins.load".to_owned(),
+            ),
+            JournalEntry::PinModule("pkg.plugins".to_owned()),
+            JournalEntry::PatternMatch("pkg.module.task".to_owned(), "pkg.*.task".to_owned()),
+        ];
+        let serialized = serialize_journal(&journal);
+
+        let deserialized = deserialize_journal(&serialized).unwrap();
+        assert_eq!(deserialized.len(), journal.len());
+        assert_eq!(serialize_journal(&deserialized), serialized);
+    }
+}
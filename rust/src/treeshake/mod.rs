@@ -1,2 +1,13 @@
+#[cfg(feature = "python")]
+pub mod class_hierarchy;
+#[cfg(feature = "python")]
+pub mod dead_code;
+#[cfg(feature = "python")]
+pub mod defaults;
+#[cfg(feature = "python")]
+pub mod exports;
+pub mod journal;
+#[cfg(feature = "python")]
 pub mod nodes_remover;
+pub mod purity;
 pub mod references_counter;
@@ -5,9 +5,10 @@ use std::{
 };
 
 use pyo3::{pyclass, pymethods};
-use ruff_python_ast::{Alias, Stmt, StmtImport, StmtImportFrom};
+use ruff_python_ast::{Alias, Expr, Stmt, StmtAssign, StmtFunctionDef, StmtImport, StmtImportFrom};
 use ruff_python_codegen::{Generator, Stylist};
 use ruff_python_parser::parse_module;
+use ruff_text_size::{Ranged, TextSize};
 
 use crate::common::ast::{
     generate_source, get_import_from_absolute_module_spec,
@@ -15,7 +16,58 @@ use crate::common::ast::{
     transformer::{Transformer, walk_stmt},
 };
 
-use super::references_counter::ReferencesHolder;
+use super::{
+    purity::{
+        get_type_param_name, get_unpacked_assignments, get_unused_type_params, is_side_effect_free,
+    },
+    references_counter::ReferencesHolder,
+};
+
+fn set_unpacked_elts(expr: &mut Expr, elts: Vec<Expr>) {
+    match expr {
+        Expr::Tuple(tuple) => tuple.elts = elts,
+        Expr::List(list) => list.elts = elts,
+        _ => {}
+    }
+}
+
+fn get_target_names(expr: &Expr) -> Vec<String> {
+    match expr {
+        Expr::Name(name) => vec![name.id.to_string()],
+        Expr::Tuple(tuple) => tuple.elts.iter().flat_map(get_target_names).collect(),
+        Expr::List(list) => list.elts.iter().flat_map(get_target_names).collect(),
+        Expr::Starred(starred) => get_target_names(&starred.value),
+        _ => Vec::new(),
+    }
+}
+
+fn get_alias_name(alias: &Alias) -> String {
+    match &alias.asname {
+        Some(asname) => asname.to_string(),
+        // `import a.b` binds `a`
+        None => alias.name.split('.').next().unwrap_or_default().to_owned(),
+    }
+}
+
+/// Names bound by a statement in its scope
+fn get_bound_names(stmt: &Stmt) -> Vec<String> {
+    match stmt {
+        Stmt::FunctionDef(func_def) => vec![func_def.name.to_string()],
+        Stmt::ClassDef(class_def) => vec![class_def.name.to_string()],
+        Stmt::TypeAlias(type_alias) => get_target_names(&type_alias.name),
+        Stmt::Assign(assign) => assign.targets.iter().flat_map(get_target_names).collect(),
+        Stmt::AnnAssign(ann_assign) => get_target_names(&ann_assign.target),
+        Stmt::AugAssign(aug_assign) => get_target_names(&aug_assign.target),
+        Stmt::Import(import) => import.names.iter().map(get_alias_name).collect(),
+        Stmt::ImportFrom(import_from) => import_from
+            .names
+            .iter()
+            .filter(|alias| alias.name.as_str() != "*")
+            .map(get_alias_name)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
 
 #[pyclass]
 pub struct NodesRemover {
@@ -25,6 +77,11 @@ pub struct NodesRemover {
     names_provider: FullyQualifiedNameProvider,
     #[pyo3(get)]
     statements_removed: u32,
+    // names removed from the current module with the offset of their statement
+    removed_names: Vec<(String, TextSize)>,
+    // fully qualified name -> module, line and first source line of the statement removing it
+    #[pyo3(get)]
+    removed_symbols: HashMap<String, (String, usize, String)>,
 }
 #[pymethods]
 impl NodesRemover {
@@ -50,6 +107,8 @@ impl NodesRemover {
             source_path: PathBuf::new(),
             module_spec: String::new(),
             statements_removed: 0,
+            removed_names: Vec::new(),
+            removed_symbols: HashMap::new(),
         }
     }
 
@@ -65,7 +124,9 @@ impl NodesRemover {
         let file_content = fs::read_to_string(&self.source_path)?;
         let parsed = parse_module(&file_content).unwrap();
         let module = parsed.syntax();
+        self.names_provider.build_scopes(&module.body);
         let new_body = self.visit_body(&module.body);
+        self.collect_removed_symbols(&file_content);
 
         let stylist = Stylist::from_tokens(parsed.tokens(), &file_content);
         let mut generator: Generator = (&stylist).into();
@@ -108,13 +169,41 @@ impl ReferencesHolder for NodesRemover {
 }
 
 impl NodesRemover {
+    fn record_removed_names(&mut self, names: Vec<String>, offset: TextSize) {
+        for name in names {
+            let qualified_name = self.names_provider.resolve_qualified_name(&name);
+            self.removed_names
+                .push((format!("{}.{}", self.module_spec, qualified_name), offset));
+        }
+    }
+
+    fn collect_removed_symbols(&mut self, file_content: &str) {
+        for (fqn, offset) in std::mem::take(&mut self.removed_names) {
+            let start = usize::from(offset);
+            let line = file_content[..start].matches('\n').count() + 1;
+            let line_start = file_content[..start].rfind('\n').map_or(0, |pos| pos + 1);
+            let source = file_content[line_start..]
+                .lines()
+                .next()
+                .unwrap_or_default();
+            self.removed_symbols.insert(
+                fqn,
+                (self.module_spec.to_owned(), line, source.trim().to_owned()),
+            );
+        }
+    }
+
     fn visit_stmt_import(&mut self, mut stmt: StmtImport) -> Option<StmtImport> {
         let mut new_names: Vec<Alias> = Vec::new();
+        let mut removed_names: Vec<String> = Vec::new();
         for name in stmt.names {
             if self.has_references_for_str(&name.name) {
                 new_names.push(name);
+            } else {
+                removed_names.push(get_alias_name(&name));
             }
         }
+        self.record_removed_names(removed_names, stmt.range.start());
         stmt.names = new_names;
         Some(stmt)
     }
@@ -144,9 +233,70 @@ impl NodesRemover {
                 }
             }
         }
+        let removed_names = stmt
+            .names
+            .iter()
+            .filter(|name| name.name.as_str() != "*")
+            .map(get_alias_name)
+            .filter(|name| !added_names.contains(name))
+            .collect();
+        self.record_removed_names(removed_names, stmt.range.start());
         stmt.names = new_names;
         Some(stmt)
     }
+    /// Drops unused targets of an unpacking assignment with their values,
+    /// e.g. `a, b = 1, 2` becomes `a = 1` if `b` is unused
+    fn visit_stmt_assign(&mut self, mut stmt: StmtAssign) -> StmtAssign {
+        if !self.is_global_scope() {
+            return stmt;
+        }
+        let Some(assignments) = get_unpacked_assignments(&stmt) else {
+            return stmt;
+        };
+        let (kept, removed): (Vec<_>, Vec<_>) =
+            assignments.into_iter().partition(|(target, value)| {
+                self.has_references_for_expr(target) || !is_side_effect_free(value)
+            });
+        if kept.is_empty() || removed.is_empty() {
+            return stmt;
+        }
+        let removed_names = removed
+            .into_iter()
+            .flat_map(|(target, _)| get_target_names(target))
+            .collect();
+        let (targets, values): (Vec<Expr>, Vec<Expr>) = kept
+            .into_iter()
+            .map(|(target, value)| (target.clone(), value.clone()))
+            .unzip();
+        self.record_removed_names(removed_names, stmt.range.start());
+        if targets.len() == 1 {
+            stmt.targets = targets;
+            stmt.value = Box::new(values.into_iter().next().unwrap());
+        } else {
+            set_unpacked_elts(&mut stmt.targets[0], targets);
+            set_unpacked_elts(&mut stmt.value, values);
+        }
+        stmt
+    }
+
+    /// Drops unused PEP 695 type parameters of a function,
+    /// e.g. `def f[T, U](x: T) -> T` becomes `def f[T](x: T) -> T`
+    fn visit_stmt_function_def(&mut self, mut stmt: StmtFunctionDef) -> StmtFunctionDef {
+        let unused_type_params = get_unused_type_params(&stmt);
+        if unused_type_params.is_empty() {
+            return stmt;
+        }
+        if let Some(type_params) = stmt.type_params.as_mut() {
+            type_params
+                .type_params
+                .retain(|type_param| !unused_type_params.contains(get_type_param_name(type_param)));
+            if type_params.type_params.is_empty() {
+                stmt.type_params = None;
+            }
+        }
+        stmt
+    }
+
     fn fallback_stmt(&mut self, stmt: Stmt) -> Option<Stmt> {
         match stmt {
             _ => None,
@@ -162,6 +312,7 @@ impl Transformer for NodesRemover {
             | Stmt::AnnAssign(_)
             | Stmt::AugAssign(_)
             | Stmt::Assign(_)
+            | Stmt::TypeAlias(_)
             | Stmt::Import(_)
             | Stmt::ImportFrom(_) => false,
             _ => true,
@@ -171,18 +322,25 @@ impl Transformer for NodesRemover {
         }
 
         if !self.has_references_for_stmt(&stmt) {
+            let (bound_names, offset) = (get_bound_names(&stmt), stmt.start());
             let fallback = self.fallback_stmt(stmt);
             if fallback.is_none() {
                 self.statements_removed += 1;
+                self.record_removed_names(bound_names, offset);
             }
             return fallback;
         }
+        let (bound_names, offset) = (get_bound_names(&stmt), stmt.start());
         let scope = self.names_provider.enter_scope(&stmt);
         if let Some(new_stmt) = match stmt {
             Stmt::Import(import) => self.visit_stmt_import(import).map(Stmt::Import),
             Stmt::ImportFrom(import_from) => self
                 .visit_stmt_import_from(import_from)
                 .map(Stmt::ImportFrom),
+            Stmt::Assign(assign) => Some(Stmt::Assign(self.visit_stmt_assign(assign))),
+            Stmt::FunctionDef(func_def) => {
+                Some(Stmt::FunctionDef(self.visit_stmt_function_def(func_def)))
+            }
 
             _ => Some(stmt),
         } {
@@ -193,6 +351,7 @@ impl Transformer for NodesRemover {
         }
         self.names_provider.exit_scope(scope);
         self.statements_removed += 1;
+        self.record_removed_names(bound_names, offset);
         None
     }
 }
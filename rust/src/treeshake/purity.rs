@@ -0,0 +1,373 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyValueError, prelude::*};
+use ruff_python_ast::{
+    ExceptHandler, Expr, Stmt, StmtAssign, StmtFunctionDef, TypeParam,
+    visitor::{Visitor, walk_expr, walk_stmt},
+};
+
+use crate::common::{
+    ast::{
+        parse_python_source, providers::fully_qualified_name_provider::FullyQualifiedNameProvider,
+    },
+    patterns::SymbolPatterns,
+};
+
+/// Assigning to an attribute or an item changes an object which might be shared
+fn is_mutation_target(expr: &Expr) -> bool {
+    match expr {
+        Expr::Attribute(_) | Expr::Subscript(_) => true,
+        Expr::Tuple(tuple) => tuple.elts.iter().any(is_mutation_target),
+        Expr::List(list) => list.elts.iter().any(is_mutation_target),
+        Expr::Starred(starred) => is_mutation_target(&starred.value),
+        _ => false,
+    }
+}
+
+#[derive(Default)]
+struct CalledExprsCollector<'a> {
+    called_exprs: Vec<&'a Expr>,
+}
+
+impl<'a> Visitor<'a> for CalledExprsCollector<'a> {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Call(call) => self.called_exprs.push(&call.func),
+            // the body of a lambda only runs when it is called
+            Expr::Lambda(_) => return,
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+/// The callees of all calls evaluated by `expr`
+pub fn get_called_exprs(expr: &Expr) -> Vec<&Expr> {
+    let mut collector = CalledExprsCollector::default();
+    collector.visit_expr(expr);
+    collector.called_exprs
+}
+
+#[derive(Default)]
+struct SideEffectsFinder {
+    has_side_effects: bool,
+}
+
+impl<'a> Visitor<'a> for SideEffectsFinder {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Call(_)
+            | Expr::Named(_)
+            | Expr::Await(_)
+            | Expr::Yield(_)
+            | Expr::YieldFrom(_) => self.has_side_effects = true,
+            Expr::Lambda(_) => return,
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+/// Whether evaluating `expr` can be skipped, e.g. for literals and names
+pub fn is_side_effect_free(expr: &Expr) -> bool {
+    let mut finder = SideEffectsFinder::default();
+    finder.visit_expr(expr);
+    !finder.has_side_effects
+}
+
+fn get_unpacked_elts(expr: &Expr) -> Option<&[Expr]> {
+    let elts = match expr {
+        Expr::Tuple(tuple) => &tuple.elts,
+        Expr::List(list) => &list.elts,
+        _ => return None,
+    };
+    if elts.iter().any(|elt| elt.is_starred_expr()) {
+        return None;
+    }
+    Some(elts)
+}
+
+/// Targets of an assignment like `a, b = 1, compute()` paired with their values,
+/// so the values of unused targets can be dropped if they are side-effect free
+pub fn get_unpacked_assignments(assign: &StmtAssign) -> Option<Vec<(&Expr, &Expr)>> {
+    let [target] = assign.targets.as_slice() else {
+        return None;
+    };
+    let targets = get_unpacked_elts(target)?;
+    let values = get_unpacked_elts(&assign.value)?;
+    if targets.len() != values.len() || !targets.iter().all(|target| target.is_name_expr()) {
+        return None;
+    }
+    Some(targets.iter().zip(values).collect())
+}
+
+#[derive(Default)]
+struct UsedNamesCollector<'a> {
+    names: HashSet<&'a str>,
+    strings: Vec<&'a str>,
+}
+
+impl<'a> Visitor<'a> for UsedNamesCollector<'a> {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Name(name) => {
+                self.names.insert(name.id.as_str());
+            }
+            // forward references like `"T"` in annotations
+            Expr::StringLiteral(literal) => self.strings.push(literal.value.to_str()),
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+pub fn get_type_param_name(type_param: &TypeParam) -> &str {
+    match type_param {
+        TypeParam::TypeVar(type_var) => type_var.name.as_str(),
+        TypeParam::TypeVarTuple(type_var_tuple) => type_var_tuple.name.as_str(),
+        TypeParam::ParamSpec(param_spec) => param_spec.name.as_str(),
+    }
+}
+
+/// PEP 695 type parameters of a function (`def f[T, U](x: T) -> T`) which are used
+/// neither by its signature, its body nor the other type parameters.
+/// Type parameters of classes define how many arguments `C[...]` takes and are never unused.
+pub fn get_unused_type_params(func_def: &StmtFunctionDef) -> HashSet<String> {
+    let Some(type_params) = &func_def.type_params else {
+        return HashSet::new();
+    };
+    let mut collector = UsedNamesCollector::default();
+    collector.visit_type_params(type_params);
+    collector.visit_parameters(&func_def.parameters);
+    if let Some(returns) = &func_def.returns {
+        collector.visit_annotation(returns);
+    }
+    collector.visit_body(&func_def.body);
+    type_params
+        .type_params
+        .iter()
+        .map(get_type_param_name)
+        .filter(|name| {
+            !collector.names.contains(*name)
+                && !collector
+                    .strings
+                    .iter()
+                    .any(|string| string.contains(*name))
+        })
+        .map(str::to_owned)
+        .collect()
+}
+
+fn get_dotted_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Name(name) => Some(name.id.to_string()),
+        Expr::Attribute(attr) => {
+            get_dotted_name(&attr.value).map(|value| format!("{}.{}", value, attr.attr))
+        }
+        _ => None,
+    }
+}
+
+/// Candidates of the fully qualified name of a callee, which are only known
+/// for names and attributes of names, e.g. not for `Path(path).write_text`
+pub fn get_callee_fully_qualified_names(
+    names_provider: &FullyQualifiedNameProvider,
+    called_expr: &Expr,
+) -> Vec<String> {
+    let Some(dotted_name) = get_dotted_name(called_expr) else {
+        return Vec::new();
+    };
+    names_provider
+        .resolve_fully_qualified_name(&names_provider.resolve_qualified_name(&dotted_name))
+}
+
+struct FunctionEffects {
+    // state outside of the function is changed directly, e.g. by assigning to an attribute
+    has_side_effects: bool,
+    // candidates of the fully qualified names of each called function
+    callees: Vec<Vec<String>>,
+}
+
+struct FunctionEffectsVisitor<'a> {
+    names_provider: &'a FullyQualifiedNameProvider,
+    effects: FunctionEffects,
+}
+
+impl<'a> Visitor<'a> for FunctionEffectsVisitor<'_> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        let has_side_effects = match stmt {
+            Stmt::Global(_)
+            | Stmt::Nonlocal(_)
+            | Stmt::Import(_)
+            | Stmt::ImportFrom(_)
+            | Stmt::With(_)
+            | Stmt::ClassDef(_) => true,
+            // failing on import is observable, e.g. a guard raising on unsupported platforms
+            Stmt::Raise(_) | Stmt::Assert(_) => true,
+            Stmt::FunctionDef(func_def) => !func_def.decorator_list.is_empty(),
+            Stmt::Assign(assign) => assign.targets.iter().any(is_mutation_target),
+            Stmt::AugAssign(aug_assign) => is_mutation_target(&aug_assign.target),
+            Stmt::AnnAssign(ann_assign) => is_mutation_target(&ann_assign.target),
+            Stmt::Delete(delete) => delete.targets.iter().any(is_mutation_target),
+            _ => false,
+        };
+        if has_side_effects {
+            self.effects.has_side_effects = true;
+        }
+        // nested functions only run when they are called
+        if !stmt.is_function_def_stmt() {
+            walk_stmt(self, stmt);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        for called_expr in get_called_exprs(expr) {
+            self.effects.callees.push(get_callee_fully_qualified_names(
+                self.names_provider,
+                called_expr,
+            ));
+        }
+    }
+}
+
+/// Finds module level functions without side-effects, which only call functions
+/// without side-effects themselves, so calling them on import can be skipped
+/// if their result is not used.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Default)]
+pub struct PurityCollector {
+    functions: HashMap<String, FunctionEffects>,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl PurityCollector {
+    #[new]
+    fn py_new() -> Self {
+        Self::new()
+    }
+
+    #[pyo3(name = "process_module")]
+    fn py_process_module(
+        &mut self,
+        module_spec: String,
+        source_path: PathBuf,
+    ) -> Result<(), std::io::Error> {
+        self.process_module(module_spec, source_path)
+    }
+
+    #[pyo3(name = "get_pure_functions")]
+    fn py_get_pure_functions(&self, pure_callables: HashSet<String>) -> PyResult<HashSet<String>> {
+        self.get_pure_functions(pure_callables)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+}
+
+impl PurityCollector {
+    pub fn new() -> Self {
+        PurityCollector {
+            functions: HashMap::new(),
+        }
+    }
+
+    pub fn process_module(
+        &mut self,
+        module_spec: String,
+        source_path: PathBuf,
+    ) -> Result<(), std::io::Error> {
+        let file_content = fs::read_to_string(&source_path)?;
+        let Ok(module) = parse_python_source(&file_content) else {
+            return Ok(());
+        };
+        let body = &module.expect_module().body;
+        let mut names_provider = FullyQualifiedNameProvider::new(&module_spec, &source_path);
+        names_provider.build_scopes(body);
+        self.visit_body(&module_spec, &mut names_provider, body);
+        Ok(())
+    }
+
+    /// Fully qualified names of the pure functions, given the callables known to be pure
+    pub fn get_pure_functions(
+        &self,
+        pure_callables: HashSet<String>,
+    ) -> Result<HashSet<String>, regex::Error> {
+        let pure_callables = SymbolPatterns::new(pure_callables)?;
+        // functions calling each other are pure unless one of them is impure
+        let mut pure_functions: HashSet<String> = self
+            .functions
+            .iter()
+            .filter(|(_, effects)| !effects.has_side_effects)
+            .map(|(fqn, _)| fqn.to_owned())
+            .collect();
+        loop {
+            let impure_functions: Vec<String> = pure_functions
+                .iter()
+                .filter(|fqn| {
+                    self.functions[*fqn].callees.iter().any(|candidates| {
+                        !candidates.iter().any(|candidate| {
+                            pure_functions.contains(candidate)
+                                || pure_callables.find_match(candidate).is_some()
+                        })
+                    })
+                })
+                .cloned()
+                .collect();
+            if impure_functions.is_empty() {
+                return Ok(pure_functions);
+            }
+            for fqn in impure_functions {
+                pure_functions.remove(&fqn);
+            }
+        }
+    }
+
+    fn visit_body(
+        &mut self,
+        module_spec: &str,
+        names_provider: &mut FullyQualifiedNameProvider,
+        body: &[Stmt],
+    ) {
+        for stmt in body {
+            names_provider.visit_stmt(stmt);
+            match stmt {
+                // calls of decorated functions go through the decorator
+                Stmt::FunctionDef(func_def) if func_def.decorator_list.is_empty() => {
+                    let scope = names_provider.enter_scope(stmt);
+                    let mut visitor = FunctionEffectsVisitor {
+                        names_provider,
+                        effects: FunctionEffects {
+                            has_side_effects: false,
+                            callees: Vec::new(),
+                        },
+                    };
+                    visitor.visit_body(&func_def.body);
+                    let effects = visitor.effects;
+                    names_provider.exit_scope(scope);
+                    self.functions
+                        .insert(format!("{}.{}", module_spec, func_def.name), effects);
+                }
+                Stmt::If(stmt_if) => {
+                    self.visit_body(module_spec, names_provider, &stmt_if.body);
+                    for clause in &stmt_if.elif_else_clauses {
+                        self.visit_body(module_spec, names_provider, &clause.body);
+                    }
+                }
+                Stmt::Try(stmt_try) => {
+                    self.visit_body(module_spec, names_provider, &stmt_try.body);
+                    for handler in &stmt_try.handlers {
+                        let ExceptHandler::ExceptHandler(handler) = handler;
+                        self.visit_body(module_spec, names_provider, &handler.body);
+                    }
+                    self.visit_body(module_spec, names_provider, &stmt_try.orelse);
+                    self.visit_body(module_spec, names_provider, &stmt_try.finalbody);
+                }
+                _ => {}
+            }
+        }
+    }
+}
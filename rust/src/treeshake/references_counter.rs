@@ -2,19 +2,56 @@ use std::{
     collections::{HashMap, HashSet},
     fs,
     path::PathBuf,
+    sync::Mutex,
 };
 
-use pyo3::{pyclass, pymethods};
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyValueError, prelude::*};
 use ruff_python_ast::{
-    Decorator, Expr, ExprAttribute, ExprCompare, Stmt,
+    Decorator, Expr, ExprAttribute, ExprCall, ExprCompare, ExprSubscript, Stmt, StmtClassDef,
     visitor::{Visitor, walk_expr, walk_stmt},
 };
 
-use crate::common::ast::{
-    get_import_from_absolute_module_spec, parse_python_source,
-    providers::fully_qualified_name_provider::FullyQualifiedNameProvider,
+#[cfg(feature = "python")]
+use crate::common::cache::MemoryCache;
+use crate::common::{
+    ast::{
+        finders::find_lazy_imports,
+        full_name::get_full_name_for_expr,
+        get_import_from_absolute_module_spec, parse_python_source, parse_string_annotation,
+        providers::fully_qualified_name_provider::{FullyQualifiedNameProvider, is_star_imported},
+    },
+    cache::{FileCache, hash_content},
+    module_spec::{parse_dotted_reference, remove_last_segment},
+    patterns::SymbolPatterns,
 };
 
+use super::{
+    journal::{JournalEntry, deserialize_journal, serialize_journal},
+    purity::{
+        get_called_exprs, get_callee_fully_qualified_names, get_type_param_name,
+        get_unpacked_assignments, get_unused_type_params, is_side_effect_free,
+    },
+};
+
+const TYPING_MODULES: [&str; 2] = ["typing", "typing_extensions"];
+
+fn counts_have_module_references(
+    references_counts: &HashMap<String, usize>,
+    module_spec: &str,
+) -> bool {
+    for (key, count) in references_counts {
+        if key.starts_with(module_spec) && *count > 0 {
+            return true;
+        }
+    }
+    false
+}
+
+fn counts_have_references(references_counts: &HashMap<String, usize>, str_: &str) -> bool {
+    references_counts.get(str_).is_some_and(|count| *count > 0)
+}
+
 pub trait ReferencesHolder {
     fn get_references_counts(&self) -> &HashMap<String, usize>;
     fn get_names_provider(&self) -> &FullyQualifiedNameProvider;
@@ -22,20 +59,11 @@ pub trait ReferencesHolder {
     fn get_module_spec(&self) -> &String;
 
     fn module_spec_has_references(&self) -> bool {
-        let references_counts = self.get_references_counts();
-        let module_spec = self.get_module_spec();
-        for (key, count) in references_counts {
-            if key.starts_with(module_spec) && *count > 0 {
-                return true;
-            }
-        }
-        false
+        counts_have_module_references(self.get_references_counts(), self.get_module_spec())
     }
 
     fn has_references_for_str(&self, str_: &str) -> bool {
-        let references_counts = self.get_references_counts();
-        // TODO: ??? this looks wrong; someone with more rust xp please help
-        return references_counts.get(str_).unwrap_or(&(0 as usize)) > &0;
+        counts_have_references(self.get_references_counts(), str_)
     }
 
     fn has_references_for_expr(&self, expr: &Expr) -> bool {
@@ -83,33 +111,65 @@ pub trait ReferencesHolder {
     }
 
     fn is_global_scope(&self) -> bool {
-        self.get_names_provider().name_context.len() == 0
+        self.get_names_provider().is_global_scope()
     }
 }
 
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass)]
 pub struct ReferencesCounter {
     names_provider: FullyQualifiedNameProvider,
     module_spec: String,
-    #[pyo3(get, set)]
     references_counts: HashMap<String, usize>,
-    #[pyo3(get, set)]
     new_references_count: usize,
     always_bump_context: bool,
     source_path: PathBuf,
     import_aliases: HashMap<String, String>,
-    safe_decorators: HashSet<String>,
+    safe_decorators: SymbolPatterns,
+    preserve_symbols: SymbolPatterns,
+    // names kept or decorators considered safe because of a preserve symbol or safe decorator,
+    // mapped to the pattern they matched
+    pattern_matches: HashMap<String, String>,
+    cache: Option<FileCache>,
+    iteration: usize,
+    // reads and writes of the current module visit, None if the visit cannot be cached
+    journal: Mutex<Option<Vec<JournalEntry>>>,
+    // string literals in annotations are forward references
+    in_annotation: bool,
+    // names bound by star imports of modules with a static `__all__`
+    exports: HashMap<String, HashSet<String>>,
+    // count string literals which look like import paths of `modules`, e.g. `"pkg.module:attr"`
+    string_references: bool,
+    modules: HashSet<String>,
+    // string literals treated as references, mapped to the name they refer to
+    found_string_references: HashMap<String, String>,
+    // modules whose names are accessed dynamically, e.g. `getattr(module, name)`
+    pinned_modules: HashSet<String>,
+    // classes registered by a base as a side-effect of their definition
+    registered_classes: HashSet<String>,
+    // callables without side-effects, calling them on module level doesn't keep their arguments
+    pure_callables: SymbolPatterns,
+    // visiting the value of a module level assignment, which only calls pure callables
+    in_pure_assignment: bool,
 }
 
-#[pymethods]
 impl ReferencesCounter {
-    #[new]
-    fn new(
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
         references_counts: HashMap<String, usize>,
         import_aliases: HashMap<String, String>,
         safe_decorators: HashSet<String>,
-    ) -> Self {
-        ReferencesCounter {
+        cache: Option<FileCache>,
+        exports: HashMap<String, HashSet<String>>,
+        preserve_symbols: HashSet<String>,
+        string_references: bool,
+        modules: HashSet<String>,
+        registered_classes: HashSet<String>,
+        pure_callables: HashSet<String>,
+    ) -> Result<Self, regex::Error> {
+        let safe_decorators = SymbolPatterns::new(safe_decorators)?;
+        let preserve_symbols = SymbolPatterns::new(preserve_symbols)?;
+        let pure_callables = SymbolPatterns::new(pure_callables)?;
+        Ok(ReferencesCounter {
             module_spec: String::new(),
             names_provider: FullyQualifiedNameProvider::new("", &PathBuf::from("")),
             references_counts,
@@ -117,12 +177,27 @@ impl ReferencesCounter {
             new_references_count: 0,
             source_path: PathBuf::new(),
             import_aliases: import_aliases,
-            safe_decorators: safe_decorators,
-        }
+            safe_decorators,
+            preserve_symbols,
+            pattern_matches: HashMap::new(),
+            cache,
+            iteration: 0,
+            journal: Mutex::new(None),
+            in_annotation: false,
+            exports,
+            string_references,
+            modules,
+            found_string_references: HashMap::new(),
+            pinned_modules: HashSet::new(),
+            registered_classes,
+            pure_callables,
+            in_pure_assignment: false,
+        })
     }
 
     fn reset_counter(&mut self) {
         self.new_references_count = 0;
+        self.iteration += 1;
     }
 
     fn visit_module(
@@ -131,26 +206,203 @@ impl ReferencesCounter {
         source_path: PathBuf,
     ) -> Result<(), std::io::Error> {
         self.always_bump_context = false;
+        self.in_annotation = false;
+        self.in_pure_assignment = false;
         self.module_spec = module_spec;
         self.source_path = source_path;
         self.names_provider =
             FullyQualifiedNameProvider::new(&self.module_spec, self.get_source_path());
+        self.names_provider.set_star_exports(self.exports.clone());
 
         let file_content = fs::read_to_string(&self.source_path)?;
+        let file_name = self
+            .source_path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let cache_key = format!(
+            "{}-{}",
+            hash_content(&[&self.module_spec, &file_name, &file_content]),
+            self.iteration
+        );
+        if let Some(journal) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get("references", &cache_key))
+            .and_then(|value| deserialize_journal(&value))
+        {
+            if self.replay_journal(&journal) {
+                return Ok(());
+            }
+        }
+
+        self.set_journal(Some(Vec::new()));
         let module = parse_python_source(&file_content).unwrap().expect_module();
+        self.names_provider.build_scopes(&module.body);
         for stmt in &module.body {
             self.visit_stmt(stmt);
         }
+        let journal = self.journal.lock().unwrap().take();
+        if let (Some(cache), Some(journal)) = (&self.cache, journal) {
+            cache.set("references", &cache_key, &serialize_journal(&journal));
+        }
         Ok(())
     }
 }
 
+#[cfg(feature = "python")]
+#[pymethods]
 impl ReferencesCounter {
+    #[new]
+    #[pyo3(signature = (
+        references_counts,
+        import_aliases,
+        safe_decorators,
+        cache_dir=None,
+        cache_namespace=String::new(),
+        exports=HashMap::new(),
+        preserve_symbols=HashSet::new(),
+        string_references=false,
+        modules=HashSet::new(),
+        registered_classes=HashSet::new(),
+        pure_callables=HashSet::new(),
+        memory_cache=None,
+    ))]
+    fn py_new(
+        references_counts: HashMap<String, usize>,
+        import_aliases: HashMap<String, String>,
+        safe_decorators: HashSet<String>,
+        cache_dir: Option<PathBuf>,
+        cache_namespace: String,
+        exports: HashMap<String, HashSet<String>>,
+        preserve_symbols: HashSet<String>,
+        string_references: bool,
+        modules: HashSet<String>,
+        registered_classes: HashSet<String>,
+        pure_callables: HashSet<String>,
+        memory_cache: Option<PyRef<'_, MemoryCache>>,
+    ) -> PyResult<Self> {
+        ReferencesCounter::new(
+            references_counts,
+            import_aliases,
+            safe_decorators,
+            FileCache::from_options(cache_dir, memory_cache.as_deref(), &cache_namespace),
+            exports,
+            preserve_symbols,
+            string_references,
+            modules,
+            registered_classes,
+            pure_callables,
+        )
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[pyo3(name = "reset_counter")]
+    fn py_reset_counter(&mut self) {
+        self.reset_counter();
+    }
+
+    #[pyo3(name = "visit_module")]
+    fn py_visit_module(
+        &mut self,
+        module_spec: String,
+        source_path: PathBuf,
+    ) -> Result<(), std::io::Error> {
+        self.visit_module(module_spec, source_path)
+    }
+
+    #[getter(references_counts)]
+    fn get_references_counts(&self) -> HashMap<String, usize> {
+        self.references_counts.clone()
+    }
+
+    #[setter(references_counts)]
+    fn set_references_counts(&mut self, references_counts: HashMap<String, usize>) {
+        self.references_counts = references_counts;
+    }
+
+    #[getter(new_references_count)]
+    fn get_new_references_count(&self) -> usize {
+        self.new_references_count
+    }
+
+    #[setter(new_references_count)]
+    fn set_new_references_count(&mut self, new_references_count: usize) {
+        self.new_references_count = new_references_count;
+    }
+
+    #[getter(pattern_matches)]
+    fn get_pattern_matches(&self) -> HashMap<String, String> {
+        self.pattern_matches.clone()
+    }
+
+    #[getter(found_string_references)]
+    fn get_found_string_references(&self) -> HashMap<String, String> {
+        self.found_string_references.clone()
+    }
+}
+
+impl ReferencesCounter {
+    fn set_journal(&self, journal: Option<Vec<JournalEntry>>) {
+        *self.journal.lock().unwrap() = journal;
+    }
+
+    fn record(&self, entry: JournalEntry) {
+        if let Some(journal) = self.journal.lock().unwrap().as_mut() {
+            journal.push(entry);
+        }
+    }
+
+    /// Applies the writes of a cached journal as long as its reads still give the same answers.
+    /// Returns false if a read diverged and the module needs to be visited again.
+    /// Writes applied before the divergence would have been done by the new visit as well.
+    fn replay_journal(&mut self, journal: &[JournalEntry]) -> bool {
+        for entry in journal {
+            match entry {
+                JournalEntry::HasReferences(fqn, result) => {
+                    if counts_have_references(&self.references_counts, fqn) != *result {
+                        return false;
+                    }
+                }
+                JournalEntry::ModuleHasReferences(result) => {
+                    if counts_have_module_references(&self.references_counts, &self.module_spec)
+                        != *result
+                    {
+                        return false;
+                    }
+                }
+                JournalEntry::IsBundledModule(module_spec, result) => {
+                    if self.modules.contains(module_spec) != *result {
+                        return false;
+                    }
+                }
+                JournalEntry::Increase(fqn) => self.increase(fqn),
+                JournalEntry::MakeKnown(fqn) => self.make_known(fqn),
+                JournalEntry::StringReference(string, fqn) => {
+                    self.increase_string_reference(string, fqn)
+                }
+                JournalEntry::PinModule(module_spec) => self.pin_module(module_spec),
+                JournalEntry::PatternMatch(fqn, pattern) => self.record_pattern_match(fqn, pattern),
+            }
+        }
+        true
+    }
+
     fn increase(&mut self, fqn: &str) {
+        self.record(JournalEntry::Increase(fqn.to_owned()));
+        self.increase_count(fqn);
+    }
+
+    fn increase_count(&mut self, fqn: &str) {
         let old_references_count = self.references_counts.get(fqn);
 
         match old_references_count {
             Some(count) => {
+                // a known name becoming referenced keeps what an earlier visit skipped,
+                // e.g. the value of a pure assignment visited before its first use
+                if *count == 0 {
+                    self.new_references_count += 1;
+                }
                 self.references_counts.insert(fqn.to_string(), count + 1);
             }
             None => {
@@ -159,7 +411,7 @@ impl ReferencesCounter {
             }
         }
         if let Some(alias) = self.import_aliases.get(fqn).cloned() {
-            self.increase(&alias);
+            self.increase_count(&alias);
         }
     }
 
@@ -185,10 +437,229 @@ impl ReferencesCounter {
     }
 
     fn make_known(&mut self, fqn: &str) {
+        self.record(JournalEntry::MakeKnown(fqn.to_owned()));
         if !self.references_counts.contains_key(fqn) {
             self.references_counts.insert(fqn.to_owned(), 0);
             self.new_references_count += 1;
         }
+        if !counts_have_references(&self.references_counts, fqn) {
+            if let Some(pattern) = self.preserve_symbols.find_match(fqn) {
+                self.pattern_matches
+                    .insert(fqn.to_owned(), pattern.to_owned());
+                self.increase_count(fqn);
+            } else if self.pinned_modules.contains(remove_last_segment(fqn)) {
+                self.increase_count(fqn);
+            }
+        }
+    }
+
+    /// Whether evaluating `expr` only calls pure callables
+    fn is_pure_expr(&self, expr: &Expr) -> bool {
+        get_called_exprs(expr).into_iter().all(|called_expr| {
+            get_callee_fully_qualified_names(&self.names_provider, called_expr)
+                .iter()
+                .any(|fqn| self.pure_callables.find_match(fqn).is_some())
+        })
+    }
+
+    /// Whether `stmt` assigns the result of a call with side-effects on module level,
+    /// the call runs on import, so the assignment is kept even if its targets are unused
+    fn has_impure_module_level_value(&self, stmt: &Stmt) -> bool {
+        let value = match stmt {
+            Stmt::Assign(stmt_assign) => Some(&*stmt_assign.value),
+            Stmt::AnnAssign(stmt_ann_assign) => stmt_ann_assign.value.as_deref(),
+            _ => None,
+        };
+        value.is_some_and(|value| {
+            self.is_global_scope() && !self.is_pure_expr(value) && self.module_spec_has_references()
+        })
+    }
+
+    /// Names imported lazily by a pep562 `__getattr__` are referenced
+    /// by the references to them in this module, e.g. `from .dynamic import BaseModel`
+    fn maybe_increase_lazy_imports(&mut self, stmt: &Stmt) {
+        if !self.is_global_scope() {
+            return;
+        }
+        let parent_package = self
+            .names_provider
+            .get_imports_provider()
+            .get_parent_package();
+        for (name, module_spec) in find_lazy_imports(stmt, &parent_package) {
+            if self.has_references_for_str(&format!("{}.{}", self.module_spec, name)) {
+                self.increase(&format!("{}.{}", module_spec, name));
+            }
+        }
+    }
+
+    fn is_bundled_module(&self, module_spec: &str) -> bool {
+        let result = self.modules.contains(module_spec);
+        self.record(JournalEntry::IsBundledModule(
+            module_spec.to_owned(),
+            result,
+        ));
+        result
+    }
+
+    fn record_pattern_match(&mut self, fqn: &str, pattern: &str) {
+        self.record(JournalEntry::PatternMatch(
+            fqn.to_owned(),
+            pattern.to_owned(),
+        ));
+        self.pattern_matches
+            .insert(fqn.to_owned(), pattern.to_owned());
+    }
+
+    /// Keeps every name of a module, as it is accessed with names only known at runtime
+    fn pin_module(&mut self, module_spec: &str) {
+        self.record(JournalEntry::PinModule(module_spec.to_owned()));
+        self.pinned_modules.insert(module_spec.to_owned());
+        let module_names: Vec<String> = self
+            .references_counts
+            .iter()
+            .filter(|(fqn, count)| **count == 0 && remove_last_segment(fqn) == module_spec)
+            .map(|(fqn, _)| fqn.to_owned())
+            .collect();
+        for fqn in module_names {
+            self.increase_count(&fqn);
+        }
+    }
+
+    fn is_builtin_function(&self, expr: &Expr, name: &str) -> bool {
+        self.names_provider
+            .get_expr_fully_qualified_name(expr)
+            .contains(&format!("__builtin__.{}", name))
+    }
+
+    /// Fully qualified names of an object whose attributes are accessed by name.
+    /// `sys.modules[__name__]` and `globals()` refer to the current module.
+    fn get_namespace_fqns(&self, expr: &Expr) -> Vec<String> {
+        match expr {
+            Expr::Name(_) | Expr::Attribute(_) => {
+                // the longest name is the object itself, shorter ones are its parents
+                let Some(name) = get_full_name_for_expr(expr)
+                    .into_iter()
+                    .max_by_key(|name| name.len())
+                else {
+                    return Vec::new();
+                };
+                self.names_provider.resolve_fully_qualified_name(
+                    &self.names_provider.resolve_qualified_name(&name),
+                )
+            }
+            Expr::Subscript(subscript)
+                if subscript
+                    .slice
+                    .as_name_expr()
+                    .is_some_and(|name| name.id.as_str() == "__name__")
+                    && self
+                        .names_provider
+                        .get_expr_fully_qualified_name(&subscript.value)
+                        .iter()
+                        .any(|fqn| fqn == "sys.modules") =>
+            {
+                vec![self.module_spec.to_owned()]
+            }
+            Expr::Call(call)
+                if call.arguments.args.is_empty()
+                    && self.is_builtin_function(&call.func, "globals") =>
+            {
+                vec![self.module_spec.to_owned()]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Counts `object.path` for attributes accessed with a string, e.g. `getattr(module, "name")`.
+    /// Modules accessed with a name which is not a literal are pinned.
+    fn maybe_increase_attribute_path(&mut self, object: &Expr, path: &Expr) {
+        let namespace_fqns = self.get_namespace_fqns(object);
+        let Expr::StringLiteral(path) = path else {
+            for fqn in namespace_fqns {
+                if self.is_bundled_module(&fqn) {
+                    self.pin_module(&fqn);
+                }
+            }
+            return;
+        };
+        for fqn in namespace_fqns {
+            // `attrgetter("a.b")` accesses `a` as well
+            let mut attribute_fqn = fqn;
+            for segment in path.value.to_str().split('.') {
+                attribute_fqn = format!("{}.{}", attribute_fqn, segment);
+                self.increase(&attribute_fqn);
+            }
+        }
+    }
+
+    /// `getattr(obj, "name")`, `hasattr`, `setattr`, `delattr` and
+    /// `operator.attrgetter("name")(obj)`
+    fn maybe_increase_attribute_access(&mut self, call: &ExprCall) {
+        let arguments = &call.arguments.args;
+        if ["getattr", "hasattr", "setattr", "delattr"]
+            .iter()
+            .any(|name| self.is_builtin_function(&call.func, name))
+        {
+            if let (Some(object), Some(path)) = (arguments.first(), arguments.get(1)) {
+                self.maybe_increase_attribute_path(object, path);
+            }
+            return;
+        }
+        let Expr::Call(getter_call) = call.func.as_ref() else {
+            return;
+        };
+        let Some(object) = arguments.first() else {
+            return;
+        };
+        if self
+            .names_provider
+            .get_expr_fully_qualified_name(&getter_call.func)
+            .iter()
+            .any(|fqn| fqn == "operator.attrgetter")
+        {
+            for path in &getter_call.arguments.args {
+                self.maybe_increase_attribute_path(object, path);
+            }
+        }
+    }
+
+    /// `vars(module)["name"]` and `globals()["name"]`
+    fn maybe_increase_namespace_subscript(&mut self, subscript: &ExprSubscript) {
+        let Expr::Call(call) = subscript.value.as_ref() else {
+            return;
+        };
+        if self.is_builtin_function(&call.func, "vars") {
+            if let Some(object) = call.arguments.args.first() {
+                self.maybe_increase_attribute_path(object, &subscript.slice);
+            }
+        } else if self.is_builtin_function(&call.func, "globals") {
+            self.maybe_increase_attribute_path(&subscript.value, &subscript.slice);
+        }
+    }
+
+    /// Strings are only references if they name a module of the bundle
+    /// or an attribute of one
+    fn maybe_increase_string(&mut self, value: &str) {
+        let Some(reference) = parse_dotted_reference(value) else {
+            return;
+        };
+        if reference
+            .module_specs
+            .iter()
+            .any(|module_spec| self.is_bundled_module(module_spec))
+        {
+            self.increase_string_reference(value, &reference.fqn);
+        }
+    }
+
+    fn increase_string_reference(&mut self, value: &str, fqn: &str) {
+        self.record(JournalEntry::StringReference(
+            value.to_owned(),
+            fqn.to_owned(),
+        ));
+        self.found_string_references
+            .insert(value.to_owned(), fqn.to_owned());
+        self.increase_count(fqn);
     }
 
     fn make_known_stmt(&mut self, stmt: &Stmt) {
@@ -202,13 +673,68 @@ impl ReferencesCounter {
             .names_provider
             .get_expr_fully_qualified_name(&decorator.expression)
         {
-            if self.safe_decorators.contains(&fqn) {
+            if let Some(pattern) = self.safe_decorators.find_match(&fqn) {
+                let pattern = pattern.to_owned();
+                self.record_pattern_match(&fqn, &pattern);
                 return true;
             }
         }
         false
     }
 
+    /// Classes registered by a base are used as soon as their module is imported
+    fn is_registered_class(&self, class_def: &StmtClassDef) -> bool {
+        self.is_global_scope()
+            && self
+                .registered_classes
+                .contains(&format!("{}.{}", self.module_spec, class_def.name))
+            && self.module_spec_has_references()
+    }
+
+    fn is_typing_member(&self, expr: &Expr, name: &str) -> bool {
+        let fqns = self.names_provider.get_expr_fully_qualified_name(expr);
+        TYPING_MODULES
+            .iter()
+            .any(|module| fqns.contains(&format!("{}.{}", module, name)))
+    }
+
+    /// String arguments of typing helpers which are evaluated as types at runtime,
+    /// e.g. `cast("T", value)` or `TypeVar("T", bound="X")`
+    fn get_string_type_arguments<'a>(&self, call: &'a ExprCall) -> Vec<&'a Expr> {
+        let arguments = &call.arguments;
+        let type_arguments: Vec<&Expr> = if self.is_typing_member(&call.func, "cast")
+            || self.is_typing_member(&call.func, "ForwardRef")
+        {
+            arguments.args.iter().take(1).collect()
+        } else if self.is_typing_member(&call.func, "TypeVar") {
+            arguments
+                .args
+                .iter()
+                .skip(1)
+                .chain(
+                    arguments
+                        .find_keyword("bound")
+                        .map(|keyword| &keyword.value),
+                )
+                .collect()
+        } else {
+            Vec::new()
+        };
+        type_arguments
+            .into_iter()
+            .filter(|argument| argument.is_string_literal_expr())
+            .collect()
+    }
+
+    /// Expressions inside of annotations whose strings are values instead of types
+    fn is_annotation_value_expr(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Call(_) => true,
+            Expr::Subscript(subscript) => self.is_typing_member(&subscript.value, "Literal"),
+            _ => false,
+        }
+    }
+
     fn has_unsafe_decorator(&mut self, decorators: &Vec<Decorator>) -> bool {
         for decorator in decorators {
             if !self.is_safe_decorator(decorator) {
@@ -256,6 +782,18 @@ impl ReferencesHolder for ReferencesCounter {
         &self.module_spec
     }
 
+    fn module_spec_has_references(&self) -> bool {
+        let result = counts_have_module_references(&self.references_counts, &self.module_spec);
+        self.record(JournalEntry::ModuleHasReferences(result));
+        result
+    }
+
+    fn has_references_for_str(&self, str_: &str) -> bool {
+        let result = counts_have_references(&self.references_counts, str_);
+        self.record(JournalEntry::HasReferences(str_.to_owned(), result));
+        result
+    }
+
     fn get_source_path(&self) -> &PathBuf {
         &self.source_path
     }
@@ -284,14 +822,19 @@ impl Visitor<'_> for ReferencesCounter {
         }
         self.make_known_stmt(&stmt);
 
+        // targets and values of an unpacking assignment, which are visited separately
+        // so values of unused targets don't keep anything
+        let mut unpacked_assignments: Option<Vec<(&Expr, &Expr, bool)>> = None;
         match &stmt {
             Stmt::AnnAssign(_) | Stmt::AugAssign(_) => {
-                if self.has_references_for_stmt(&stmt) {
+                self.maybe_increase_lazy_imports(stmt);
+                if self.has_references_for_stmt(&stmt) || self.has_impure_module_level_value(stmt) {
                     self.maybe_increase_stmt(&stmt);
                     self.always_bump_context = true;
                 }
             }
             Stmt::Assign(stmt_assign) => {
+                self.maybe_increase_lazy_imports(stmt);
                 let mut should_bump_stmt_assign = false;
                 if self.is_global_scope() && self.module_spec_has_references() {
                     for target in &stmt_assign.targets {
@@ -309,7 +852,41 @@ impl Visitor<'_> for ReferencesCounter {
                         }
                     }
                 }
-                if self.has_references_for_stmt(&stmt) || should_bump_stmt_assign {
+                let assignments = if can_reset_context
+                    && !should_bump_stmt_assign
+                    && self.is_global_scope()
+                    && self.has_references_for_stmt(&stmt)
+                {
+                    get_unpacked_assignments(stmt_assign)
+                } else {
+                    None
+                };
+                if let Some(assignments) = assignments {
+                    // only used targets and values with side-effects are kept by the nodes remover
+                    let assignments: Vec<(&Expr, &Expr, bool)> = assignments
+                        .into_iter()
+                        .map(|(target, value)| {
+                            let is_kept =
+                                self.has_references_for_expr(target) || !is_side_effect_free(value);
+                            (target, value, is_kept)
+                        })
+                        .collect();
+                    for (target, _, is_kept) in &assignments {
+                        if *is_kept {
+                            self.maybe_increase_expr(target);
+                        }
+                    }
+                    unpacked_assignments = Some(assignments);
+                } else if self.has_references_for_stmt(&stmt)
+                    || should_bump_stmt_assign
+                    || self.has_impure_module_level_value(stmt)
+                {
+                    self.maybe_increase_stmt(&stmt);
+                    self.always_bump_context = true;
+                }
+            }
+            Stmt::TypeAlias(_) => {
+                if self.has_references_for_stmt(&stmt) {
                     self.maybe_increase_stmt(&stmt);
                     self.always_bump_context = true;
                 }
@@ -317,11 +894,12 @@ impl Visitor<'_> for ReferencesCounter {
             Stmt::ClassDef(class_def) => {
                 if self.has_unsafe_decorator(&class_def.decorator_list)
                     || self.has_references_for_stmt(&stmt)
+                    || self.is_registered_class(class_def)
                 {
                     self.maybe_increase_stmt(&stmt);
                     self.always_bump_context = true;
                 }
-                // visit decorators, bases and keywords before they are prefixed with scope
+                // visit decorators, bases and keywords in the enclosing scope
                 for decorator in &class_def.decorator_list {
                     self.visit_decorator(decorator);
                 }
@@ -343,10 +921,6 @@ impl Visitor<'_> for ReferencesCounter {
                 {
                     self.maybe_increase_stmt(&stmt);
                     self.always_bump_context = true;
-                    // visit decorators before they are prefixed with scope
-                    for decorator in &func_def.decorator_list {
-                        self.visit_decorator(&decorator);
-                    }
                 }
                 // respect pep562 by preserving __getattr__ and __dir__ on module level
                 if self.is_global_scope()
@@ -389,6 +963,17 @@ impl Visitor<'_> for ReferencesCounter {
             }
 
             Stmt::ImportFrom(stmt_import_from) => {
+                // __future__ imports change how the whole module is compiled,
+                // e.g. annotations are not evaluated with `from __future__ import annotations`
+                if stmt_import_from.level == 0
+                    && stmt_import_from
+                        .module
+                        .as_ref()
+                        .is_some_and(|module| module == "__future__")
+                    && self.module_spec_has_references()
+                {
+                    self.maybe_increase_stmt(&stmt);
+                }
                 // check if one of the names defined by this import was imported somewhere else
                 // if yes, bump reference of this import
                 for alias in &stmt_import_from.names {
@@ -414,6 +999,8 @@ impl Visitor<'_> for ReferencesCounter {
                     && stmt_import_from.names.len() == 1
                     && stmt_import_from.names[0].name.as_str() == "*"
                 {
+                    // depends on every known reference, can't be replayed from a journal
+                    self.set_journal(None);
                     if let Ok(module_specs) = get_import_from_absolute_module_spec(
                         &stmt_import_from,
                         &self
@@ -425,6 +1012,7 @@ impl Visitor<'_> for ReferencesCounter {
                         for module_spec in module_specs {
                             let mut new_names: Vec<String> = Vec::new();
                             let mut bump_names: Vec<String> = Vec::new();
+                            let module_exports = self.exports.get(&module_spec);
                             for (reference, _) in &self.references_counts {
                                 if reference.len() > module_spec.len()
                                     // && !reference.ends_with("*")
@@ -432,6 +1020,9 @@ impl Visitor<'_> for ReferencesCounter {
                                 {
                                     let imported_name =
                                         (&reference[module_spec.len() + 1..]).to_owned();
+                                    if !is_star_imported(module_exports, &imported_name) {
+                                        continue;
+                                    }
                                     let exported_name =
                                         format!("{}.{}", self.module_spec, imported_name);
 
@@ -459,12 +1050,68 @@ impl Visitor<'_> for ReferencesCounter {
             _ => {}
         };
 
+        match stmt {
+            // decorators, defaults and annotations are evaluated in the enclosing scope
+            Stmt::FunctionDef(func_def) => {
+                for decorator in &func_def.decorator_list {
+                    self.visit_decorator(decorator);
+                }
+                // bounds and defaults of unused type parameters are removed with them
+                if let Some(type_params) = &func_def.type_params {
+                    let unused_type_params = get_unused_type_params(func_def);
+                    for type_param in &type_params.type_params {
+                        if !unused_type_params.contains(get_type_param_name(type_param)) {
+                            self.visit_type_param(type_param);
+                        }
+                    }
+                }
+                self.visit_parameters(&func_def.parameters);
+                if let Some(returns) = &func_def.returns {
+                    self.visit_annotation(returns);
+                }
+            }
+            Stmt::ClassDef(class_def) => {
+                if let Some(type_params) = &class_def.type_params {
+                    self.visit_type_params(type_params);
+                }
+            }
+            _ => {}
+        }
+
+        // an unused assignment can be removed together with everything its value calls
+        let was_in_pure_assignment = self.in_pure_assignment;
+        if !self.always_bump_context && self.is_global_scope() {
+            let value = match stmt {
+                Stmt::Assign(stmt_assign) => Some(&*stmt_assign.value),
+                Stmt::AnnAssign(stmt_ann_assign) => stmt_ann_assign.value.as_deref(),
+                _ => None,
+            };
+            if value.is_some_and(|value| self.is_pure_expr(value)) {
+                self.in_pure_assignment = true;
+            }
+        }
+
         let scope = self.names_provider.enter_scope(&stmt);
         self.names_provider.visit_stmt(stmt);
-        walk_stmt(self, stmt);
+        match stmt {
+            Stmt::FunctionDef(func_def) => self.visit_body(&func_def.body),
+            Stmt::ClassDef(class_def) => self.visit_body(&class_def.body),
+            _ => {
+                if let Some(assignments) = &unpacked_assignments {
+                    for (target, value, is_kept) in assignments {
+                        self.always_bump_context = *is_kept;
+                        self.visit_expr(value);
+                        self.visit_expr(target);
+                    }
+                } else {
+                    walk_stmt(self, stmt);
+                }
+            }
+        }
         if can_reset_context {
             self.always_bump_context = false;
         }
+        self.in_pure_assignment = was_in_pure_assignment;
         self.names_provider.exit_scope(scope);
     }
 
@@ -480,21 +1127,187 @@ impl Visitor<'_> for ReferencesCounter {
         let can_reset_context = !self.always_bump_context;
         if self.always_bump_context {
             self.maybe_increase_expr(&expr);
+            if let Expr::StringLiteral(string_literal) = expr {
+                if self.string_references && !self.in_annotation {
+                    self.maybe_increase_string(string_literal.value.to_str());
+                }
+            }
         };
 
         match expr {
-            Expr::Call(_) => {
-                if self.is_global_scope() && self.module_spec_has_references() {
+            Expr::Call(call) => {
+                if self.is_global_scope()
+                    && !self.in_pure_assignment
+                    && self.module_spec_has_references()
+                {
                     self.maybe_increase_expr(&expr);
                     self.always_bump_context = true;
                 }
+                if self.always_bump_context {
+                    self.maybe_increase_attribute_access(call);
+                }
+                for argument in self.get_string_type_arguments(call) {
+                    self.visit_annotation(argument);
+                }
+            }
+            Expr::Subscript(subscript) if self.always_bump_context => {
+                self.maybe_increase_namespace_subscript(subscript);
+            }
+            Expr::StringLiteral(string_literal) if self.in_annotation => {
+                // forward references are evaluated by typing.get_type_hints, pydantic or dataclasses
+                if let Some(annotation) = parse_string_annotation(string_literal.value.to_str()) {
+                    self.visit_expr(&annotation);
+                }
             }
             _ => {}
         }
 
+        // the first iterable of a comprehension is evaluated in the enclosing scope,
+        // visiting it again inside of the comprehension only adds local references
+        let generators = match expr {
+            Expr::ListComp(list_comp) => Some(&list_comp.generators),
+            Expr::SetComp(set_comp) => Some(&set_comp.generators),
+            Expr::DictComp(dict_comp) => Some(&dict_comp.generators),
+            Expr::Generator(generator) => Some(&generator.generators),
+            _ => None,
+        };
+        if let Some(first_generator) = generators.and_then(|generators| generators.first()) {
+            self.visit_expr(&first_generator.iter);
+        }
+
+        let was_in_annotation = self.in_annotation;
+        if self.in_annotation && self.is_annotation_value_expr(expr) {
+            self.in_annotation = false;
+        }
+        let scope = self.names_provider.enter_expr_scope(expr);
         walk_expr(self, expr);
+        self.names_provider.exit_expr_scope(scope);
+        self.in_annotation = was_in_annotation;
         if can_reset_context {
             self.always_bump_context = false;
         }
     }
+
+    fn visit_annotation(&mut self, expr: &ruff_python_ast::Expr) {
+        let was_in_annotation = self.in_annotation;
+        self.in_annotation = true;
+        self.visit_expr(expr);
+        self.in_annotation = was_in_annotation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        fs,
+        path::PathBuf,
+    };
+
+    use super::ReferencesCounter;
+
+    /// Writes `(module_spec, path, source)` modules to a temporary directory and counts
+    /// references like `treeshake_package` does, until no new references are found
+    fn count_references(
+        name: &str,
+        modules: &[(&str, &str, &str)],
+        preserve_symbols: &[&str],
+    ) -> HashMap<String, usize> {
+        let source_dir =
+            std::env::temp_dir().join(format!("flay-references-{}-{}", name, std::process::id()));
+        let mut files: Vec<(String, PathBuf)> = Vec::new();
+        for (module_spec, path, source) in modules {
+            let source_path = source_dir.join(path);
+            fs::create_dir_all(source_path.parent().unwrap()).unwrap();
+            fs::write(&source_path, source).unwrap();
+            files.push((module_spec.to_string(), source_path));
+        }
+
+        let preserve_symbols: HashSet<String> = preserve_symbols
+            .iter()
+            .map(|symbol| symbol.to_string())
+            .collect();
+        let mut counter = ReferencesCounter::new(
+            preserve_symbols
+                .iter()
+                .map(|symbol| (symbol.clone(), 1))
+                .collect(),
+            HashMap::new(),
+            HashSet::new(),
+            None,
+            HashMap::new(),
+            preserve_symbols,
+            false,
+            HashSet::new(),
+            HashSet::new(),
+            HashSet::new(),
+        )
+        .unwrap();
+        let mut new_references_count = 1;
+        while new_references_count > 0 {
+            counter.reset_counter();
+            for (module_spec, source_path) in &files {
+                counter
+                    .visit_module(module_spec.clone(), source_path.clone())
+                    .unwrap();
+            }
+            new_references_count = counter.new_references_count;
+        }
+        fs::remove_dir_all(&source_dir).unwrap();
+        counter.references_counts
+    }
+
+    fn count(references_counts: &HashMap<String, usize>, fqn: &str) -> usize {
+        references_counts.get(fqn).copied().unwrap_or_default()
+    }
+
+    const HELPERS: (&str, &str, &str) = (
+        "app.helpers",
+        "app/helpers.py",
+        "def helper():\n    return format_text('x')\n\ndef format_text(text):\n    return text\n\ndef unused_helper():\n    pass\n",
+    );
+
+    #[test]
+    fn counts_references_of_preserved_functions() {
+        let references_counts = count_references(
+            "preserved",
+            &[
+                ("app", "app/__init__.py", ""),
+                HELPERS,
+                (
+                    "app.main",
+                    "app/main.py",
+                    "from app.helpers import helper, unused_helper\n\ndef main():\n    helper()\n\ndef unused():\n    unused_helper()\n",
+                ),
+            ],
+            &["app.main.main"],
+        );
+
+        assert!(count(&references_counts, "app.helpers.helper") > 0);
+        // references of referenced functions are counted in the next iterations
+        assert!(count(&references_counts, "app.helpers.format_text") > 0);
+        assert_eq!(count(&references_counts, "app.main.unused"), 0);
+        assert_eq!(count(&references_counts, "app.helpers.unused_helper"), 0);
+    }
+
+    #[test]
+    fn counts_references_of_main_modules() {
+        let references_counts = count_references(
+            "main",
+            &[
+                ("app", "app/__init__.py", ""),
+                HELPERS,
+                (
+                    "app.__main__",
+                    "app/__main__.py",
+                    "from app.helpers import helper\n\nhelper()\n",
+                ),
+            ],
+            &[],
+        );
+
+        // everything in __main__.py is kept
+        assert!(count(&references_counts, "app.helpers.helper") > 0);
+        assert_eq!(count(&references_counts, "app.helpers.unused_helper"), 0);
+    }
 }
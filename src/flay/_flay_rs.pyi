@@ -3,9 +3,21 @@ from pathlib import Path
 class FileCollector:
     package: str
     collected_files: dict[tuple[str, Path], str | None]
+    found_string_references: dict[str, str]
 
-    def __init__(self, package: str, import_aliases: dict[str, str]) -> None: ...
+    def __init__(
+        self,
+        package: str,
+        import_aliases: dict[str, str],
+        vendor_stdlib: bool = False,
+        cache_dir: Path | None = None,
+        cache_namespace: str = "",
+        string_references: bool = False,
+    ) -> None: ...
     def _process_module(self, module_spec: str) -> None: ...
+    def invalidate(self, paths: set[Path]) -> list[str]: ...
+    def refresh(self) -> list[str]: ...
+    def add_import_aliases(self, import_aliases: dict[str, str]) -> None: ...
 
 class ReferencesCounter:
     def __init__(
@@ -13,6 +25,15 @@ class ReferencesCounter:
         references_counts: dict[str, int],
         import_aliases: dict[str, str],
         safe_decorators: set[str],
+        cache_dir: Path | None = None,
+        cache_namespace: str = "",
+        exports: dict[str, set[str]] = ...,
+        preserve_symbols: set[str] = ...,
+        string_references: bool = False,
+        modules: set[str] = ...,
+        registered_classes: set[str] = ...,
+        pure_callables: set[str] = ...,
+        memory_cache: MemoryCache | None = None,
     ): ...
     def visit_module(
         self,
@@ -22,10 +43,81 @@ class ReferencesCounter:
     def reset_counter(self) -> None: ...
     references_counts: dict[str, int]
     new_references_count: int
+    pattern_matches: dict[str, str]
+    found_string_references: dict[str, str]
+
+class MemoryCache:
+    def __init__(self) -> None: ...
+    def __len__(self) -> int: ...
+
+class ExportsCollector:
+    def __init__(self) -> None: ...
+    def process_module(self, module_spec: str, source_path: Path) -> None: ...
+    @property
+    def exports(self) -> dict[str, set[str]]: ...
+    public_names: dict[str, set[str]]
+
+class DjangoSettingsCollector:
+    settings: dict[str, list[str]]
+    def __init__(self) -> None: ...
+    def process_module(self, module_spec: str, source_path: Path) -> list[str]: ...
 
 class NodesRemover:
     statements_removed: int
+    removed_symbols: dict[str, tuple[str, int, str]]
     def __init__(
         self, references_counts: dict[str, int], known_modules: set[str]
     ) -> None: ...
     def process_module(self, module_spec: str, source_path: str) -> None: ...
+
+class SharedLibrariesCollector:
+    bundled_libraries: set[str]
+    found_libraries: dict[str, Path]
+    missing_libraries: dict[str, set[Path]]
+
+    def __init__(
+        self, bundle_dir: Path, library_dirs: list[Path], sysroot: Path | None = None
+    ) -> None: ...
+    def process_file(self, path: Path) -> None: ...
+
+def write_zipapp(
+    source_dir: Path,
+    target_path: Path,
+    main_source: str,
+    native_prefix: str,
+    interpreter: str | None = None,
+) -> None: ...
+def is_symbol_pattern(pattern: str) -> bool: ...
+def match_symbols(patterns: list[str], symbols: list[str]) -> set[str]: ...
+
+class FileWatcher:
+    def __init__(self) -> None: ...
+    def watch(self, path: Path, recursive: bool = True) -> None: ...
+    def wait_for_changes(self, timeout_ms: int) -> list[Path]: ...
+
+class ClassHierarchyCollector:
+    def __init__(self) -> None: ...
+    def process_module(self, module_spec: str, source_path: Path) -> None: ...
+    def get_registered_classes(
+        self, non_registering_bases: set[str]
+    ) -> dict[str, str]: ...
+
+class DeadCodeRemover:
+    statements_removed: int
+    def __init__(self, strip_debug: bool = False) -> None: ...
+    def process_module(self, source_path: Path) -> None: ...
+
+class PurityCollector:
+    def __init__(self) -> None: ...
+    def process_module(self, module_spec: str, source_path: Path) -> None: ...
+    def get_pure_functions(self, pure_callables: set[str]) -> set[str]: ...
+
+class UnusedDefaultsRemover:
+    always_passed: dict[str, set[str]]
+    never_passed: dict[str, set[str]]
+    defaults_removed: int
+    conditions_folded: int
+    def __init__(self, packages: list[str]) -> None: ...
+    def collect_module(self, module_spec: str, source_path: Path) -> None: ...
+    def analyze(self, preserve_symbols: set[str]) -> None: ...
+    def process_module(self, module_spec: str, source_path: Path) -> None: ...
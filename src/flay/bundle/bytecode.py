@@ -0,0 +1,57 @@
+from __future__ import annotations
+from pathlib import Path
+import importlib.util
+import logging
+import os
+import py_compile
+import typing as t
+import typing_extensions as te
+
+log = logging.getLogger(__name__)
+
+BytecodeLayout: te.TypeAlias = t.Literal["pycache", "sourceless"]
+BytecodeInvalidationMode: te.TypeAlias = t.Literal["checked-hash", "unchecked-hash"]
+
+_INVALIDATION_MODES: dict[
+    BytecodeInvalidationMode, py_compile.PycInvalidationMode
+] = {
+    "checked-hash": py_compile.PycInvalidationMode.CHECKED_HASH,
+    "unchecked-hash": py_compile.PycInvalidationMode.UNCHECKED_HASH,
+}
+
+
+def compile_bundle(
+    source_dir: Path,
+    layout: BytecodeLayout = "pycache",
+    invalidation_mode: BytecodeInvalidationMode = "checked-hash",
+) -> int:
+    """
+    Compile every python source below `source_dir` to bytecode
+    for the running interpreter.
+
+    The `pycache` layout writes PEP 3147 `__pycache__` files next to the sources,
+    the `sourceless` layout writes legacy `.pyc` files in place of the sources
+    and removes them.
+    Hash based invalidation keeps the output independent of file timestamps.
+    """
+    compiled = 0
+    for path in sorted(source_dir.rglob("*.py")):
+        if "__pycache__" in path.parts:
+            continue
+        if layout == "pycache":
+            cfile = importlib.util.cache_from_source(str(path))
+        else:
+            cfile = str(path.with_suffix(".pyc"))
+        py_compile.compile(
+            str(path),
+            cfile=cfile,
+            # embed a stable path instead of the path of the build directory
+            dfile=path.relative_to(source_dir).as_posix(),
+            doraise=True,
+            invalidation_mode=_INVALIDATION_MODES[invalidation_mode],
+        )
+        if layout == "sourceless":
+            os.remove(path)
+        compiled += 1
+        log.debug("Compiled %s to %s", path, cfile)
+    return compiled
@@ -7,9 +7,24 @@ from importlib.metadata import (
     files as package_metadata_files,
 )
 from flay.common.compat import packages_distributions
+from flay.common.cache import get_cache_namespace
 from flay.common.events import Event, EventHandler, NoopEventHandler
-from flay.ecosystem.import_aliases import get_default_import_aliases
+from flay.ecosystem.django import (
+    DjangoProject,
+    bundle_django_resources,
+    evaluate_django_settings,
+    get_django_modules,
+)
+from flay.ecosystem.plugins import (
+    Ecosystem,
+    get_distributions,
+    get_entry_points,
+    load_ecosystem,
+)
 from . import DEFAULT_BUNDLE_METADATA
+from .stdlib import STDLIB_STARTUP_MODULES, is_stdlib_path, write_interpreter_config
+from .shared_libraries import bundle_shared_libraries
+from flay.trace import load_traces
 from flay.common.module_spec import (
     find_all_files_in_module_spec,
     get_parent_package,
@@ -44,27 +59,112 @@ class BundlePackageBundledMetadataEvent(Event):
     pass
 
 
+class BundlePackageMissingSharedLibrariesEvent(Event):
+    # library name -> extension modules and libraries requiring it
+    libraries: dict[str, list[str]]
+
+
+class BundlePackageStringReferencesEvent(Event):
+    # string literal -> module spec it was resolved to
+    references: dict[str, str]
+
+
 BundlePackageEvent: te.TypeAlias = t.Union[
     BundlePackageFoundModuleEvent,
     BundlePackageFoundTotalModulesEvent,
     BundlePackageProcessModuleEvent,
     BundlePackageBundledMetadataEvent,
+    BundlePackageMissingSharedLibrariesEvent,
+    BundlePackageStringReferencesEvent,
 ]
 
 
+def create_file_collector(
+    module_spec: str,
+    import_aliases: dict[str, str] | None = None,
+    vendor_stdlib: bool = False,
+    cache_dir: Path | None = None,
+    string_references: bool = False,
+) -> FileCollector:
+    aliases = dict(import_aliases or {})
+    return FileCollector(
+        package=module_spec,
+        import_aliases=aliases,
+        vendor_stdlib=vendor_stdlib,
+        cache_dir=cache_dir,
+        cache_namespace=get_cache_namespace(
+            "bundle", aliases, vendor_stdlib, string_references
+        ),
+        string_references=string_references,
+    )
+
+
+def apply_ecosystem_plugins(
+    collector: FileCollector, plugin_paths: t.Iterable[Path] = ()
+) -> Ecosystem:
+    """
+    Load the plugins of the distributions in the bundle and collect the modules
+    their import aliases and entry points refer to. These can pull in further
+    distributions, so this is repeated until no new distribution shows up.
+    """
+    distributions: set[str] = set()
+    ecosystem = Ecosystem()
+    while True:
+        bundled_distributions = get_distributions(
+            get_top_level_package(found_module)
+            for (found_module, found_path) in collector.collected_files
+            if not is_stdlib_path(found_path)
+        )
+        if bundled_distributions <= distributions:
+            return ecosystem
+        distributions |= bundled_distributions
+        ecosystem = load_ecosystem(distributions, plugin_paths)
+        collector.add_import_aliases(ecosystem.import_aliases)
+        for entry_point_module, _ in get_entry_points(ecosystem.entry_point_groups):
+            collector._process_module(entry_point_module)
+
+
 def bundle_package(
     module_spec: str,
     destination_path: Path,
     bundle_metadata: bool = DEFAULT_BUNDLE_METADATA,
     resources: dict[str, str] | None = None,
     import_aliases: dict[str, str] | None = None,
+    vendor_stdlib: bool = False,
+    sysroot: Path | None = None,
+    cache_dir: Path | None = None,
+    collector: FileCollector | None = None,
+    plugin_paths: list[Path] | None = None,
+    django_settings: str | None = None,
+    string_references: bool = False,
+    trace_files: list[Path] | None = None,
     event_handler: EventHandler[BundlePackageEvent] = NoopEventHandler(),
 ) -> None:
+    """
+    A `collector` can be passed to reuse the module graph of a previous run,
+    e.g. in watch mode. Only files it did not collect yet are read.
+
+    Ecosystem plugins of bundled distributions are loaded from `plugin_paths`
+    in addition to the plugins shipped with flay and other distributions.
+
+    With `django_settings`, the apps and dotted paths of the django project
+    configured by this settings module are bundled as well.
+
+    With `string_references`, string literals like `"pkg.module:attr"` are followed
+    if they resolve to a module, e.g. for logging configurations or task queues.
+
+    Modules imported at runtime according to the `trace_files` written by
+    `flay trace` are bundled as well.
+    """
     resources = resources or {}
-    aliases = get_default_import_aliases()
-    if import_aliases:
-        aliases.update(import_aliases)
-    collector = FileCollector(package=module_spec, import_aliases=aliases)
+    if collector is None:
+        collector = create_file_collector(
+            module_spec,
+            import_aliases=import_aliases,
+            vendor_stdlib=vendor_stdlib,
+            cache_dir=cache_dir,
+            string_references=string_references,
+        )
 
     for path in find_all_files_in_module_spec(module_spec):
         if path.match("*.py"):
@@ -78,6 +178,30 @@ def bundle_package(
             )
             collector._process_module(found_module_spec)
 
+    if vendor_stdlib:
+        for startup_module_spec in STDLIB_STARTUP_MODULES:
+            collector._process_module(startup_module_spec)
+
+    django_project: DjangoProject | None = None
+    if django_settings is not None:
+        django_project = evaluate_django_settings(django_settings)
+        for django_module_spec in get_django_modules(django_project):
+            collector._process_module(django_module_spec)
+
+    if trace_files:
+        for traced_module_spec in load_traces(trace_files).modules:
+            collector._process_module(traced_module_spec)
+
+    ecosystem = apply_ecosystem_plugins(collector, plugin_paths or [])
+    resources = {**ecosystem.resources, **resources}
+
+    if string_references and collector.found_string_references:
+        event_handler.on_event(
+            BundlePackageStringReferencesEvent(
+                references=dict(sorted(collector.found_string_references.items()))
+            )
+        )
+
     files = collector.collected_files
     event_handler.on_event(BundlePackageFoundTotalModulesEvent(count=len(files)))
     top_level_package = get_top_level_package(module_spec)
@@ -167,11 +291,31 @@ def bundle_package(
                 shutil.copy2(str(resource.locate()), str(target_file))
                 log.debug("Copied %s to %s", found_path, target_file)
 
+    if django_project is not None:
+        bundle_django_resources(django_project, destination_path)
+
+    if sys.platform.startswith("linux"):
+        missing_libraries = bundle_shared_libraries(destination_path, sysroot)
+        if missing_libraries:
+            event_handler.on_event(
+                BundlePackageMissingSharedLibrariesEvent(
+                    libraries={
+                        name: sorted(str(path) for path in required_by)
+                        for name, required_by in sorted(missing_libraries.items())
+                    }
+                )
+            )
+
+    if vendor_stdlib:
+        write_interpreter_config(destination_path)
+
     if bundle_metadata:
         package_dists = packages_distributions()
 
         all_packages = {
-            get_top_level_package(found_module) for (found_module, _) in files_keys
+            get_top_level_package(found_module)
+            for (found_module, found_path) in files_keys
+            if not is_stdlib_path(found_path)
         }
         for package in all_packages:
             if package in package_dists:
@@ -194,4 +338,9 @@ def bundle_package(
                     raise PackageNotFoundError(module_spec)
                 metadata_path.touch()
                 metadata_path.write_text(metadata, encoding=FLAY_STANDARD_ENCODING)
+                # entry points are needed to discover plugins at runtime
+                if entry_points := distribution.read_text("entry_points.txt"):
+                    (dist_info_path / "entry_points.txt").write_text(
+                        entry_points, encoding=FLAY_STANDARD_ENCODING
+                    )
         event_handler.on_event(BundlePackageBundledMetadataEvent())
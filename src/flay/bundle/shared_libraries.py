@@ -0,0 +1,85 @@
+from __future__ import annotations
+from flay._flay_rs import SharedLibrariesCollector
+from pathlib import Path
+import logging
+import os
+import shutil
+import sys
+
+log = logging.getLogger(__name__)
+
+# directory for libraries from the sysroot, needs to be added to LD_LIBRARY_PATH
+SHARED_LIBRARIES_DIR = "flay.libs"
+
+_SYSROOT_LIBRARY_DIRS = ("lib", "lib64", "usr/lib", "usr/lib64", "usr/local/lib")
+
+
+def get_sysroot_library_dirs(sysroot: Path) -> list[Path]:
+    library_dirs: list[Path] = []
+    for library_dir in _SYSROOT_LIBRARY_DIRS:
+        path = sysroot / library_dir
+        if not path.is_dir():
+            continue
+        library_dirs.append(path)
+        # multiarch directories, e.g. usr/lib/x86_64-linux-gnu
+        library_dirs.extend(
+            sorted(
+                sub_path
+                for sub_path in path.iterdir()
+                if sub_path.is_dir() and "-linux-" in sub_path.name
+            )
+        )
+    return library_dirs
+
+
+def get_wheel_library_dirs() -> list[Path]:
+    """
+    `{top_level}.libs` directories created by auditwheel next to the installed packages
+    """
+    library_dirs: list[Path] = []
+    for sys_path in sys.path:
+        if os.path.isdir(sys_path):
+            library_dirs.extend(
+                sorted(
+                    path
+                    for path in Path(sys_path).iterdir()
+                    if path.name.endswith(".libs") and path.is_dir()
+                )
+            )
+    return library_dirs
+
+
+def bundle_shared_libraries(
+    destination_path: Path, sysroot: Path | None = None
+) -> dict[str, set[Path]]:
+    """
+    Copy shared libraries needed by ELF objects of the bundle into the bundle.
+
+    Returns the libraries that could not be found with the objects requiring them.
+    These are expected to be provided by the base image, like glibc, which is never
+    bundled or reported because it has to match the dynamic loader of the host.
+    """
+    library_dirs = get_wheel_library_dirs()
+    if sysroot is not None:
+        library_dirs.extend(get_sysroot_library_dirs(sysroot))
+
+    collector = SharedLibrariesCollector(
+        destination_path.absolute(),
+        library_dirs,
+        sysroot.absolute() if sysroot is not None else None,
+    )
+    for path in sorted(destination_path.rglob("*.so*")):
+        if path.is_file():
+            collector.process_file(path.absolute())
+
+    for name, library_path in sorted(collector.found_libraries.items()):
+        if library_path.parent.name.endswith(".libs"):
+            target_dir = destination_path / library_path.parent.name
+        else:
+            target_dir = destination_path / SHARED_LIBRARIES_DIR
+        target_dir.mkdir(parents=True, exist_ok=True)
+        # follows symlinks, the library is stored under the name the loader looks for
+        shutil.copy2(str(library_path), str(target_dir / name))
+        log.debug("Copied shared library %s to %s", library_path, target_dir / name)
+
+    return collector.missing_libraries
@@ -0,0 +1,61 @@
+from __future__ import annotations
+from flay.common.compat import FLAY_STANDARD_ENCODING
+from pathlib import Path
+import sys
+import sysconfig
+
+# modules the interpreter needs during startup which are not imported by user code
+# the encodings package is never frozen and has to be available on the filesystem
+STDLIB_STARTUP_ENCODINGS = ("aliases", "ascii", "latin_1", "utf_8")
+
+STDLIB_STARTUP_MODULES = (
+    "encodings",
+    *(f"encodings.{encoding}" for encoding in STDLIB_STARTUP_ENCODINGS),
+    "_collections_abc",
+    "_sitebuiltins",
+    "abc",
+    "codecs",
+    "genericpath",
+    "io",
+    "os",
+    "posixpath",
+    "runpy",
+    "stat",
+)
+
+# codecs are looked up dynamically by encodings.search_function
+STDLIB_PRESERVE_SYMBOLS = frozenset(
+    f"encodings.{encoding}.getregentry"
+    for encoding in STDLIB_STARTUP_ENCODINGS
+    if encoding != "aliases"
+)
+
+
+def write_interpreter_config(destination_path: Path) -> None:
+    """
+    Write a `._pth` file and a `pyvenv.cfg` so that an interpreter binary placed
+    in `destination_path` only uses the bundle as its module search path
+    """
+    version = sys.version_info
+    pth_content = ".\n"
+    for pth_name in (
+        "python",
+        f"python{version.major}",
+        f"python{version.major}.{version.minor}",
+    ):
+        (destination_path / f"{pth_name}._pth").write_text(
+            pth_content, encoding=FLAY_STANDARD_ENCODING
+        )
+
+    (destination_path / "pyvenv.cfg").write_text(
+        "include-system-site-packages = false\n"
+        f"version = {version.major}.{version.minor}.{version.micro}\n",
+        encoding=FLAY_STANDARD_ENCODING,
+    )
+
+
+def is_stdlib_path(path: Path) -> bool:
+    if "site-packages" in path.parts:
+        return False
+    stdlib_paths = {sysconfig.get_path("stdlib"), sysconfig.get_path("platstdlib")}
+    return any(path.is_relative_to(stdlib_path) for stdlib_path in stdlib_paths)
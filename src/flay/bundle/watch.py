@@ -0,0 +1,209 @@
+from __future__ import annotations
+from flay._flay_rs import FileCollector, FileWatcher
+from flay.common.events import Event, EventHandler, NoopEventHandler
+from flay.common.exc import FlayFileNotFoundError
+from flay.common.module_spec import (
+    _lookup_paths_for_module_spec,
+    find_module_path,
+    get_top_level_package,
+)
+from pathlib import Path
+import filecmp
+import importlib
+import logging
+import shutil
+import tempfile
+import typing as t
+import typing_extensions as te
+
+log = logging.getLogger(__name__)
+
+# files in the working directory flay reads its configuration from
+CONFIG_FILE_NAMES = frozenset(
+    {
+        "pyproject.toml",
+        "flay.toml",
+        "flay.yaml",
+        "flay.yml",
+        "flay.json",
+        ".env",
+    }
+)
+
+_WAIT_TIMEOUT_MS = 500
+
+
+class WatchBundleSyncedEvent(Event):
+    written: int
+    removed: int
+
+
+class WatchBundleChangedFilesEvent(Event):
+    paths: list[str]
+
+
+class WatchBundleConfigChangedEvent(Event):
+    path: str
+
+
+class WatchBundleFailedEvent(Event):
+    error: str
+
+
+WatchBundleEvent: te.TypeAlias = t.Union[
+    WatchBundleSyncedEvent,
+    WatchBundleChangedFilesEvent,
+    WatchBundleConfigChangedEvent,
+    WatchBundleFailedEvent,
+]
+
+
+def get_source_root(module_spec: str) -> Path:
+    """
+    Directory of the first-party top level package, or the module file
+    if the top level is a single module
+    """
+    top_level_spec = find_module_path(get_top_level_package(module_spec))
+    if top_level_spec is None or top_level_spec.origin is None:
+        raise FlayFileNotFoundError(
+            f"Could not find file for module spec '{module_spec}'."
+        )
+    origin = Path(top_level_spec.origin)
+    if origin.name == "__init__.py":
+        return origin.parent
+    return origin
+
+
+def sync_directory(source_dir: Path, target_dir: Path) -> tuple[int, int]:
+    """
+    Make `target_dir` equal to `source_dir`, only touching files that differ.
+    Returns the number of written and removed files.
+    """
+    source_files = {
+        path.relative_to(source_dir)
+        for path in source_dir.rglob("*")
+        if path.is_file()
+    }
+    target_files = (
+        {
+            path.relative_to(target_dir)
+            for path in target_dir.rglob("*")
+            if path.is_file() or path.is_symlink()
+        }
+        if target_dir.is_dir()
+        else set()
+    )
+    written = 0
+    for relative_path in sorted(source_files):
+        source_file = source_dir / relative_path
+        target_file = target_dir / relative_path
+        if target_file.is_file() and filecmp.cmp(
+            source_file, target_file, shallow=False
+        ):
+            continue
+        target_file.parent.mkdir(parents=True, exist_ok=True)
+        shutil.copy2(str(source_file), str(target_file))
+        log.debug("Updated %s", target_file)
+        written += 1
+
+    removed = 0
+    for relative_path in sorted(target_files - source_files):
+        (target_dir / relative_path).unlink()
+        log.debug("Removed %s", target_dir / relative_path)
+        removed += 1
+
+    # deepest directories first, so parents become empty before they are checked
+    for path in sorted(target_dir.rglob("*"), reverse=True):
+        if path.is_dir() and not path.is_symlink() and not any(path.iterdir()):
+            path.rmdir()
+    return written, removed
+
+
+def _rebuild(
+    collector: FileCollector,
+    build: t.Callable[[FileCollector, Path], None],
+    output_path: Path,
+) -> tuple[int, int]:
+    for module_spec in collector.refresh():
+        log.debug("%s is no longer imported", module_spec)
+    with tempfile.TemporaryDirectory(prefix="flay-") as staging_dir:
+        build(collector, Path(staging_dir))
+        return sync_directory(Path(staging_dir), output_path)
+
+
+def watch_bundle(
+    module_spec: str,
+    output_path: Path,
+    collector: FileCollector,
+    build: t.Callable[[FileCollector, Path], None],
+    max_rebuilds: int | None = None,
+    event_handler: EventHandler[WatchBundleEvent] = NoopEventHandler(),
+) -> Path | None:
+    """
+    Build the bundle and rebuild it whenever a first-party source file changes.
+
+    `build` writes the bundle for the given collector into a staging directory,
+    which is synced into `output_path` afterwards, so unchanged files are never
+    rewritten. The collector keeps the module graph between builds, only changed
+    modules are read and parsed again and modules which are no longer imported
+    are removed from it.
+
+    Treeshaking runs incrementally if `build` passes the same `MemoryCache` to
+    every run: the reference counting keeps the journal of every module visit in
+    memory and only visits changed modules again, or modules whose references
+    changed. The journals of all other modules are replayed.
+
+    A failing build (e.g. a syntax error in a half-written file) is reported and
+    the changed modules are processed again with the next build.
+
+    Returns the changed config file, as the configuration needs to be reloaded.
+    Returns `None` after `max_rebuilds` rebuilds.
+    """
+    source_root = get_source_root(module_spec)
+    config_dir = Path.cwd()
+    watcher = FileWatcher()
+    watcher.watch(source_root, recursive=source_root.is_dir())
+    watcher.watch(config_dir, recursive=False)
+
+    rebuilds = 0
+    while True:
+        try:
+            written, removed = _rebuild(collector, build, output_path)
+        except (KeyboardInterrupt, SystemExit):
+            raise
+        # panics of the rust extension (e.g. on syntax errors) are no Exception
+        except BaseException as e:
+            log.debug("Rebuild failed", exc_info=True)
+            event_handler.on_event(
+                WatchBundleFailedEvent(error=f"{type(e).__name__}: {e}")
+            )
+        else:
+            event_handler.on_event(
+                WatchBundleSyncedEvent(written=written, removed=removed)
+            )
+        if max_rebuilds is not None and rebuilds >= max_rebuilds:
+            return None
+
+        changed_paths: list[Path] = []
+        while not changed_paths:
+            for path in watcher.wait_for_changes(_WAIT_TIMEOUT_MS):
+                if path.parent == config_dir and path.name in CONFIG_FILE_NAMES:
+                    event_handler.on_event(
+                        WatchBundleConfigChangedEvent(path=str(path))
+                    )
+                    return path
+                if (
+                    path == source_root or source_root in path.parents
+                ) and "__pycache__" not in path.parts:
+                    changed_paths.append(path)
+
+        event_handler.on_event(
+            WatchBundleChangedFilesEvent(
+                paths=sorted(str(path) for path in changed_paths)
+            )
+        )
+        # created and removed files need to be visible to the module lookup
+        importlib.invalidate_caches()
+        _lookup_paths_for_module_spec.cache_clear()
+        collector.invalidate(set(changed_paths))
+        rebuilds += 1
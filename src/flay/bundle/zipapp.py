@@ -0,0 +1,113 @@
+from __future__ import annotations
+from flay._flay_rs import write_zipapp
+from flay.bundle.bytecode import BytecodeInvalidationMode, compile_bundle
+from pathlib import Path
+import hashlib
+import logging
+
+log = logging.getLogger(__name__)
+
+ZIPAPP_NATIVE_PREFIX = ".flay_native/"
+ZIPAPP_CACHE_ENV_VAR = "FLAY_ZIPAPP_CACHE"
+
+# zipimport cannot load extension modules, therefore everything stored below
+# the native prefix gets extracted into a cache directory on first start
+_MAIN_TEMPLATE = """\
+# generated by flay
+import os
+import sys
+
+_FLAY_MODULE_SPEC = {module_spec!r}
+_FLAY_NATIVE_PREFIX = {native_prefix!r}
+_FLAY_ZIPAPP_ID = {zipapp_id!r}
+
+
+def _flay_extract_native():
+    import shutil
+    import zipfile
+
+    archive = os.path.dirname(os.path.abspath(__file__))
+    with zipfile.ZipFile(archive) as zip_file:
+        infos = [
+            info
+            for info in zip_file.infolist()
+            if info.filename.startswith(_FLAY_NATIVE_PREFIX) and not info.is_dir()
+        ]
+        if not infos:
+            return
+        cache_root = os.environ.get({cache_env_var!r}) or os.path.join(
+            os.path.expanduser("~"), ".cache", "flay", "zipapp"
+        )
+        target = os.path.join(cache_root, _FLAY_ZIPAPP_ID)
+        if not os.path.isdir(target):
+            tmp_target = "%s.%s.tmp" % (target, os.getpid())
+            for info in infos:
+                file_path = os.path.join(
+                    tmp_target, *info.filename[len(_FLAY_NATIVE_PREFIX) :].split("/")
+                )
+                os.makedirs(os.path.dirname(file_path), exist_ok=True)
+                with zip_file.open(info) as src, open(file_path, "wb") as dst:
+                    shutil.copyfileobj(src, dst)
+                os.chmod(file_path, (info.external_attr >> 16) or 0o644)
+            try:
+                os.replace(tmp_target, target)
+            except OSError:
+                # another process extracted the same archive in the meantime
+                shutil.rmtree(tmp_target, ignore_errors=True)
+    sys.path.insert(0, target)
+
+
+_flay_extract_native()
+
+if __name__ == "__main__":
+    import runpy
+
+    runpy.run_module(_FLAY_MODULE_SPEC, run_name="__main__", alter_sys=True)
+"""
+
+
+def _hash_directory(source_dir: Path) -> str:
+    digest = hashlib.sha256()
+    for path in sorted(source_dir.rglob("*")):
+        if path.is_file():
+            digest.update(path.relative_to(source_dir).as_posix().encode())
+            digest.update(path.read_bytes())
+    return digest.hexdigest()[:16]
+
+
+def generate_zipapp_main(module_spec: str, zipapp_id: str) -> str:
+    return _MAIN_TEMPLATE.format(
+        module_spec=module_spec,
+        native_prefix=ZIPAPP_NATIVE_PREFIX,
+        zipapp_id=zipapp_id,
+        cache_env_var=ZIPAPP_CACHE_ENV_VAR,
+    )
+
+
+def bundle_zipapp(
+    source_dir: Path,
+    target_path: Path,
+    module_spec: str,
+    interpreter: str | None = None,
+    compile_sources: bool = False,
+    invalidation_mode: BytecodeInvalidationMode = "checked-hash",
+) -> Path:
+    """
+    Pack an already bundled directory into a PEP 441 zipapp which runs `module_spec`
+    """
+    if target_path.suffix != ".pyz":
+        target_path = target_path.with_name(f"{target_path.name}.pyz")
+
+    if compile_sources:
+        # zipimport does not look into __pycache__ directories
+        compile_bundle(source_dir, "sourceless", invalidation_mode)
+
+    write_zipapp(
+        source_dir,
+        target_path,
+        generate_zipapp_main(module_spec, _hash_directory(source_dir)),
+        ZIPAPP_NATIVE_PREFIX,
+        interpreter=interpreter,
+    )
+    log.debug("Written zipapp of %s to %s", source_dir, target_path)
+    return target_path
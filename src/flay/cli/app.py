@@ -1,15 +1,27 @@
 from flay.bundle import DEFAULT_BUNDLE_METADATA
+from flay.bundle.stdlib import STDLIB_PRESERVE_SYMBOLS
+from flay.bundle.package import create_file_collector
+from flay._flay_rs import FileCollector, MemoryCache
+from contextlib import ExitStack
+import tempfile
 from flay.common.logging import enable_debug_logging
 
 from flay.common.pydantic import FlayBaseSettings
 
 import os
+import sys
 from pydantic import Field, AliasChoices
 from pathlib import Path
 import typing as t
-from .bundle import cli_bundle_package
+from .bundle import (
+    cli_bundle_package,
+    cli_bundle_zipapp,
+    cli_compile_bundle,
+    cli_watch_bundle,
+)
 from .treeshake import cli_treeshake_package
 from flay.common.rich import console, check
+from flay.trace import FlayTraceError, trace_command, write_trace
 from .debug import debug_app
 import click
 from clonf.integrations.click import clonf_click
@@ -45,6 +57,35 @@ class FlayMainSettings(FlayBaseSettings):
             validation_alias=AliasChoices("output", "o"),
         ),
     ] = Path("flayed")
+    format: t.Annotated[
+        t.Literal["directory", "zipapp"],
+        CliOption(),
+        Field(
+            description="Output format of the bundle. 'zipapp' writes a single executable .pyz file to the output path",
+        ),
+    ] = "directory"
+    interpreter: t.Annotated[
+        t.Optional[str],
+        CliOption(),
+        Field(
+            description="Interpreter for the shebang line of a zipapp, e.g. '/usr/bin/env python3'",
+        ),
+    ] = None
+    bytecode: t.Annotated[
+        t.Literal["none", "pycache", "sourceless"],
+        CliOption(),
+        Field(
+            description="Compile the bundle to bytecode. 'pycache' writes __pycache__ files next to the sources, 'sourceless' replaces the sources with .pyc files",
+        ),
+    ] = "none"
+    bytecode_invalidation_mode: t.Annotated[
+        t.Literal["checked-hash", "unchecked-hash"],
+        CliOption(),
+        Field(
+            alias="bytecode-invalidation-mode",
+            description="Hash based invalidation mode of compiled bytecode",
+        ),
+    ] = "checked-hash"
     bundle_metadata: t.Annotated[
         bool,
         CliOption(is_flag=True),
@@ -84,46 +125,312 @@ class FlayMainSettings(FlayBaseSettings):
         CliOption(),
         Field(
             alias="preserve-symbols",
-            description="List of symbols that should be preserved at all cost. Absolute paths are required.",
+            description="List of symbols that should be preserved at all cost. Absolute paths are required. Accepts globs (pkg.**, pkg.*.handler), regular expressions prefixed with re: and exclusions prefixed with !",
+            default_factory=list,
+        ),
+    ]
+    preserve_exports: t.Annotated[
+        list[str],
+        CliOption(),
+        Field(
+            alias="preserve-exports",
+            description="List of packages whose names in __all__ should be preserved, e.g. when bundling a library. Applies to their submodules as well.",
+            default_factory=list,
+        ),
+    ]
+    library_include: t.Annotated[
+        list[str],
+        CliOption(),
+        Field(
+            alias="library-include",
+            description="Library mode: patterns of absolute symbol paths (e.g. my_sdk.**) whose public API should be preserved, instead of only what the entry point uses. Accepts the same patterns as preserve-symbols",
+            default_factory=list,
+        ),
+    ]
+    library_exclude: t.Annotated[
+        list[str],
+        CliOption(),
+        Field(
+            alias="library-exclude",
+            description="Patterns of absolute symbol paths that are excluded from the preserved public API in library mode. Accepts the same patterns as preserve-symbols",
             default_factory=list,
         ),
     ]
+    plugin_paths: t.Annotated[
+        list[Path],
+        CliOption(),
+        Field(
+            alias="plugin-paths",
+            description="Ecosystem plugin files or directories with plugin files (*.toml) of the project. Plugins are only loaded if their distribution is part of the bundle",
+            default_factory=list,
+        ),
+    ]
+    django_settings: t.Annotated[
+        t.Optional[str],
+        CliOption(),
+        Field(
+            alias="django-settings",
+            description="Settings module of a django project, e.g. mysite.settings. Installed apps, dotted paths in the settings and modules django discovers by convention are bundled and preserved",
+        ),
+    ] = None
+    string_references: t.Annotated[
+        bool,
+        CliOption(is_flag=True),
+        Field(
+            alias="string-references",
+            description="Treat string literals which look like import paths (pkg.module.attr or pkg.module:attr) and resolve to a module as references, e.g. for logging configurations, task queues or entry point strings",
+        ),
+    ] = False
+    vendor_stdlib: t.Annotated[
+        bool,
+        CliOption(is_flag=True),
+        Field(
+            description="Bundle the reachable parts of the standard library, so the bundle can be run by a bare interpreter binary",
+            alias="vendor-stdlib",
+        ),
+    ] = False
+    sysroot: t.Annotated[
+        t.Optional[Path],
+        CliOption(),
+        Field(
+            description="Root of a filesystem to copy shared libraries needed by extension modules from. Libraries found there are written to flay.libs in the output path",
+        ),
+    ] = None
+    cache_dir: t.Annotated[
+        t.Optional[Path],
+        CliOption(),
+        Field(
+            alias="cache-dir",
+            description="Directory for caching analysis results of unchanged files between runs. Caching is disabled if not set",
+        ),
+    ] = None
     safe_decorators: t.Annotated[
         list[str],
         CliOption(),
         Field(
             alias="safe-decorators",
-            description="A list of decorators without side-effects that can be safely removed. Absolute paths are required.",
+            description="A list of decorators without side-effects that can be safely removed. Absolute paths are required. Accepts the same patterns as preserve-symbols",
+            default_factory=list,
+        ),
+    ]
+    non_registering_bases: t.Annotated[
+        list[str],
+        CliOption(),
+        Field(
+            alias="non-registering-bases",
+            description="Bases defining __init_subclass__ or a metaclass which don't register their subclasses, so unused subclasses can be removed. Absolute paths are required. Accepts the same patterns as preserve-symbols",
             default_factory=list,
         ),
     ]
+    pure_callables: t.Annotated[
+        list[str],
+        CliOption(),
+        Field(
+            alias="pure-callables",
+            description="Callables without side-effects, so unused module level assignments of their results can be removed with everything passed to them. Absolute paths are required. Accepts the same patterns as preserve-symbols",
+            default_factory=list,
+        ),
+    ]
+    strip_debug: t.Annotated[
+        bool,
+        CliOption(is_flag=True),
+        Field(
+            alias="strip-debug",
+            description="The bundle runs with python -O, so assert statements and code depending on __debug__ are removed",
+        ),
+    ] = False
+    closed_world_packages: t.Annotated[
+        list[str],
+        CliOption(),
+        Field(
+            alias="closed-world-packages",
+            description="First-party packages whose functions are only called by the bundle itself. Defaults of parameters passed by every call are removed, conditions on parameters never passed are evaluated with their default",
+            default_factory=list,
+        ),
+    ]
+    verify: t.Annotated[
+        bool,
+        CliOption(is_flag=True),
+        Field(
+            description="Import every retained module with only the bundle and the standard library available after removing unused code, and report the removed statements causing import errors",
+        ),
+    ] = False
+    smoke_command: t.Annotated[
+        t.Optional[str],
+        CliOption(),
+        Field(
+            alias="smoke-command",
+            description="Command run against the treeshaken bundle to verify it still works, e.g. 'python -m myapp --help'. python is replaced by the interpreter running flay",
+        ),
+    ] = None
+    trace_files: t.Annotated[
+        list[Path],
+        CliOption(),
+        Field(
+            alias="trace-files",
+            description="Trace files written by flay trace. The modules imported while tracing are bundled and the functions and classes called are preserved",
+            default_factory=list,
+        ),
+    ]
+    watch: t.Annotated[
+        bool,
+        CliOption(is_flag=True),
+        Field(
+            description="Keep running and update the output path whenever the source of the package or the configuration changes",
+        ),
+    ] = False
 
 
-@flay.command(name="bundle")
-@clonf_click
-def flay_main(settings: FlayMainSettings) -> None:
-    console.print(f"Starting to bundle module {settings.module_spec}...")
+def _build_bundle(
+    settings: FlayMainSettings,
+    bundle_path: Path,
+    cache_dir: t.Optional[Path],
+    collector: t.Optional[FileCollector] = None,
+    memory_cache: t.Optional[MemoryCache] = None,
+) -> None:
     cli_bundle_package(
         module_spec=settings.module_spec,
-        output_path=settings.output_path,
+        output_path=bundle_path,
         bundle_metadata=settings.bundle_metadata,
         resources=settings.resources,
         import_aliases=settings.import_aliases,
+        vendor_stdlib=settings.vendor_stdlib,
+        sysroot=settings.sysroot,
+        cache_dir=cache_dir,
+        collector=collector,
+        plugin_paths=settings.plugin_paths,
+        django_settings=settings.django_settings,
+        string_references=settings.string_references,
+        trace_files=settings.trace_files,
     )
     console.print(check, f"Finished bundling {settings.module_spec}")
     if settings.treeshake:
         console.print("Start removing unused code...")
+        preserve_symbols = set(settings.preserve_symbols)
+        if settings.vendor_stdlib:
+            preserve_symbols.update(STDLIB_PRESERVE_SYMBOLS)
 
         removed_stmts_count = cli_treeshake_package(
-            source_dir=str(settings.output_path.absolute()),
+            source_dir=str(bundle_path.absolute()),
             import_aliases=settings.import_aliases,
-            preserve_symbols=set(settings.preserve_symbols),
+            preserve_symbols=preserve_symbols,
             safe_decorators=set(settings.safe_decorators),
+            cache_dir=cache_dir,
+            preserve_exports=settings.preserve_exports,
+            library_include=settings.library_include,
+            library_exclude=settings.library_exclude,
+            plugin_paths=settings.plugin_paths,
+            django_settings=settings.django_settings,
+            string_references=settings.string_references,
+            non_registering_bases=set(settings.non_registering_bases),
+            pure_callables=set(settings.pure_callables),
+            strip_debug=settings.strip_debug,
+            closed_world_packages=settings.closed_world_packages,
+            verify=settings.verify,
+            smoke_command=settings.smoke_command,
+            trace_files=settings.trace_files,
+            memory_cache=memory_cache,
         )
         console.print(
             check,
             f"Finished removing unused code. Removed {removed_stmts_count} statements in total",
         )
+    if settings.bytecode != "none" and settings.format == "directory":
+        compiled_count = cli_compile_bundle(
+            source_dir=bundle_path,
+            layout=settings.bytecode,
+            invalidation_mode=settings.bytecode_invalidation_mode,
+        )
+        console.print(check, f"Compiled {compiled_count} modules to bytecode")
+
+
+def _watch_bundle(settings: FlayMainSettings) -> None:
+    collector = create_file_collector(
+        settings.module_spec,
+        import_aliases=settings.import_aliases,
+        vendor_stdlib=settings.vendor_stdlib,
+        string_references=settings.string_references,
+    )
+    # reference state of the treeshaking, unchanged modules are not analysed again
+    memory_cache = MemoryCache()
+    changed_config = cli_watch_bundle(
+        module_spec=settings.module_spec,
+        output_path=settings.output_path,
+        collector=collector,
+        build=lambda collector, bundle_path: _build_bundle(
+            settings, bundle_path, settings.cache_dir, collector, memory_cache
+        ),
+    )
+    if changed_config is not None:
+        # restart, so the configuration is loaded from scratch
+        os.execv(sys.executable, [sys.executable, "-m", "flay", *sys.argv[1:]])
+
+
+@flay.command(name="bundle")
+@clonf_click
+def flay_main(settings: FlayMainSettings) -> None:
+    if settings.watch:
+        if settings.format != "directory":
+            raise click.UsageError("Watch mode only supports the directory format")
+        _watch_bundle(settings)
+        return
+
+    console.print(f"Starting to bundle module {settings.module_spec}...")
+    with ExitStack() as stack:
+        bundle_path = settings.output_path
+        if settings.format == "zipapp":
+            bundle_path = Path(
+                stack.enter_context(tempfile.TemporaryDirectory(prefix="flay-"))
+            )
+        _build_bundle(settings, bundle_path, settings.cache_dir)
+        if settings.format == "zipapp":
+            zipapp_path = cli_bundle_zipapp(
+                source_dir=bundle_path,
+                output_path=settings.output_path,
+                module_spec=settings.module_spec,
+                interpreter=settings.interpreter,
+                compile_sources=settings.bytecode != "none",
+                invalidation_mode=settings.bytecode_invalidation_mode,
+            )
+            console.print(check, f"Written zipapp to {zipapp_path}")
+
+
+@flay.command(
+    name="trace",
+    context_settings={"ignore_unknown_options": True, "allow_interspersed_args": False},
+)
+@click.option(
+    "--output",
+    "-o",
+    type=click.Path(dir_okay=False, path_type=Path),
+    default=Path("flay-trace.json"),
+    show_default=True,
+    help="Trace file to write, pass it to bundle with --trace-files",
+)
+@click.option(
+    "--append",
+    is_flag=True,
+    help="Merge the trace with the trace file, e.g. to combine several runs",
+)
+@click.argument("command", nargs=-1, required=True, type=click.UNPROCESSED)
+def flay_trace(output: Path, append: bool, command: tuple[str, ...]) -> None:
+    """
+    Run COMMAND, e.g. `python -m pytest`, and record the modules it imports
+    and the functions and classes it calls
+    """
+    console.print(f"Tracing {' '.join(command)}...")
+    try:
+        trace, returncode = trace_command(command)
+    except FlayTraceError as e:
+        raise click.ClickException(str(e)) from e
+    trace = write_trace(trace, output, append=append)
+    console.print(
+        check,
+        f"Written {len(trace.modules)} modules and {len(trace.symbols)} symbols to {output}",
+    )
+    if returncode != 0:
+        console.print(f"[yellow]Traced command exited with {returncode}")
+        sys.exit(returncode)
 
 
 if os.getenv("FLAY_DEBUG_APP"):
@@ -1,15 +1,33 @@
 from __future__ import annotations
 from pathlib import Path
 from rich.progress import SpinnerColumn, TextColumn, MofNCompleteColumn
+from rich.markup import escape
 from flay.common.events import EventHandler
 from flay.common.rich import console, check
+from flay.bundle.zipapp import bundle_zipapp
+from flay.bundle.bytecode import (
+    BytecodeInvalidationMode,
+    BytecodeLayout,
+    compile_bundle,
+)
+from flay.bundle.watch import (
+    WatchBundleChangedFilesEvent,
+    WatchBundleConfigChangedEvent,
+    WatchBundleEvent,
+    WatchBundleFailedEvent,
+    WatchBundleSyncedEvent,
+    watch_bundle,
+)
+from flay._flay_rs import FileCollector
 from flay.bundle.package import (
     BundlePackageEvent,
     bundle_package,
     BundlePackageBundledMetadataEvent,
     BundlePackageFoundModuleEvent,
     BundlePackageFoundTotalModulesEvent,
+    BundlePackageMissingSharedLibrariesEvent,
     BundlePackageProcessModuleEvent,
+    BundlePackageStringReferencesEvent,
 )
 import typing_extensions as te
 import typing as t
@@ -68,6 +86,18 @@ class BundlePackageCliIO(EventHandler[BundlePackageEvent]):
         elif isinstance(event, BundlePackageBundledMetadataEvent):
             self.end_progress()
             console.print(check, "Copied package metadata")
+        elif isinstance(event, BundlePackageMissingSharedLibrariesEvent):
+            console.print(
+                "[yellow]The following shared libraries were not found and are expected to be provided by the base image:"
+            )
+            for library, required_by in event.libraries.items():
+                console.print(
+                    f"  {library} [dim](required by {', '.join(required_by)})"
+                )
+        elif isinstance(event, BundlePackageStringReferencesEvent):
+            console.print("Followed string literals as imports:")
+            for string, module_spec in event.references.items():
+                console.print(f"  {string!r} [dim]-> {module_spec}")
 
     def end_progress(self) -> None:
         if self.progress.live._started:
@@ -81,12 +111,39 @@ class BundlePackageCliIO(EventHandler[BundlePackageEvent]):
         self.end_progress()
 
 
+class WatchBundleCliIO(EventHandler[WatchBundleEvent]):
+    def on_event(self, event: WatchBundleEvent) -> None:
+        if isinstance(event, WatchBundleSyncedEvent):
+            console.print(
+                check,
+                f"Updated output: {event.written} files written, {event.removed} files removed",
+            )
+            console.print("[dim]Watching for changes... (press Ctrl+C to stop)")
+        elif isinstance(event, WatchBundleChangedFilesEvent):
+            console.print(
+                f"Detected changes in {', '.join(event.paths)}, rebuilding..."
+            )
+        elif isinstance(event, WatchBundleConfigChangedEvent):
+            console.print(f"Detected changes in {event.path}, restarting...")
+        elif isinstance(event, WatchBundleFailedEvent):
+            console.print(f"[red]Rebuild failed[/]: {escape(event.error)}")
+            console.print("[dim]Watching for changes... (press Ctrl+C to stop)")
+
+
 def cli_bundle_package(
     module_spec: str,
     output_path: Path,
     bundle_metadata: bool,
     resources: dict[str, str],
     import_aliases: dict[str, str],
+    vendor_stdlib: bool,
+    sysroot: Path | None,
+    cache_dir: Path | None,
+    collector: FileCollector | None = None,
+    plugin_paths: list[Path] | None = None,
+    django_settings: str | None = None,
+    string_references: bool = False,
+    trace_files: list[Path] | None = None,
 ) -> None:
     with BundlePackageCliIO(initial_module_spec=module_spec) as io:
         bundle_package(
@@ -95,8 +152,68 @@ def cli_bundle_package(
             bundle_metadata=bundle_metadata,
             resources=resources,
             import_aliases=import_aliases,
+            vendor_stdlib=vendor_stdlib,
+            sysroot=sysroot,
+            cache_dir=cache_dir,
+            collector=collector,
+            plugin_paths=plugin_paths,
+            django_settings=django_settings,
+            string_references=string_references,
+            trace_files=trace_files,
             event_handler=io,
         )
 
 
-__all__ = ["cli_bundle_package"]
+def cli_bundle_zipapp(
+    source_dir: Path,
+    output_path: Path,
+    module_spec: str,
+    interpreter: str | None,
+    compile_sources: bool,
+    invalidation_mode: BytecodeInvalidationMode,
+) -> Path:
+    with console.status("Writing zipapp..."):
+        return bundle_zipapp(
+            source_dir,
+            output_path,
+            module_spec,
+            interpreter=interpreter,
+            compile_sources=compile_sources,
+            invalidation_mode=invalidation_mode,
+        )
+
+
+def cli_compile_bundle(
+    source_dir: Path,
+    layout: BytecodeLayout,
+    invalidation_mode: BytecodeInvalidationMode,
+) -> int:
+    with console.status("Compiling bytecode..."):
+        return compile_bundle(source_dir, layout, invalidation_mode)
+
+
+def cli_watch_bundle(
+    module_spec: str,
+    output_path: Path,
+    collector: FileCollector,
+    build: t.Callable[[FileCollector, Path], None],
+) -> Path | None:
+    try:
+        return watch_bundle(
+            module_spec,
+            output_path,
+            collector,
+            build,
+            event_handler=WatchBundleCliIO(),
+        )
+    except KeyboardInterrupt:
+        console.print("Stopped watching")
+        return None
+
+
+__all__ = [
+    "cli_bundle_package",
+    "cli_bundle_zipapp",
+    "cli_compile_bundle",
+    "cli_watch_bundle",
+]
@@ -5,14 +5,21 @@ from flay.treeshake.package import (
     TreeshakePackageFoundModuleEvent,
     TreeshakePackageNodesRemovalEvent,
     TreeshakePackageReferencesIterationEvent,
+    TreeshakePackageStringReferencesEvent,
     TreeshakePackageTotalModulesEvent,
+    TreeshakePackageVerificationEvent,
 )
+from flay.treeshake.verify import TreeshakeVerificationError
+from flay._flay_rs import MemoryCache
+from rich.markup import escape
 from rich.progress import Progress
+from pathlib import Path
 import typing as t
 import typing_extensions as te
 from flay.common.rich import console, check
 from rich.progress import TextColumn, BarColumn, SpinnerColumn, MofNCompleteColumn
 from flay.common.events import EventHandler
+import click
 
 
 class TreeshakePackageCliIO(EventHandler[TreeshakePackageEvent]):
@@ -72,6 +79,18 @@ class TreeshakePackageCliIO(EventHandler[TreeshakePackageEvent]):
                 visible=True,
                 spec=event.module_spec,
             )
+        elif isinstance(event, TreeshakePackageStringReferencesEvent):
+            console.print("Counted string literals as references:")
+            for string, fqn in event.references.items():
+                console.print(f"  {string!r} [dim]-> {fqn}")
+        elif isinstance(event, TreeshakePackageVerificationEvent):
+            self.end_progress()
+            if event.count:
+                console.print(
+                    f"Verifying that {event.count} modules can be imported..."
+                )
+            if event.smoke_command:
+                console.print(f"Running smoke command {event.smoke_command!r}...")
 
     def end_progress(self) -> None:
         if self.progress.live._started:
@@ -90,15 +109,58 @@ def cli_treeshake_package(
     import_aliases: dict[str, str],
     preserve_symbols: set[str],
     safe_decorators: set[str],
+    cache_dir: Path | None,
+    preserve_exports: list[str] | None = None,
+    library_include: list[str] | None = None,
+    library_exclude: list[str] | None = None,
+    plugin_paths: list[Path] | None = None,
+    django_settings: str | None = None,
+    string_references: bool = False,
+    non_registering_bases: set[str] | None = None,
+    pure_callables: set[str] | None = None,
+    strip_debug: bool = False,
+    closed_world_packages: list[str] | None = None,
+    verify: bool = False,
+    smoke_command: str | None = None,
+    trace_files: list[Path] | None = None,
+    memory_cache: MemoryCache | None = None,
 ) -> int:
-    with TreeshakePackageCliIO() as io:
-        return treeshake_package(
-            source_dir=source_dir,
-            import_aliases=import_aliases,
-            preserve_symbols=preserve_symbols,
-            safe_decorators=safe_decorators,
-            event_handler=io,
-        )
+    try:
+        with TreeshakePackageCliIO() as io:
+            return treeshake_package(
+                source_dir=source_dir,
+                import_aliases=import_aliases,
+                preserve_symbols=preserve_symbols,
+                safe_decorators=safe_decorators,
+                cache_dir=cache_dir,
+                preserve_exports=preserve_exports,
+                library_include=library_include,
+                library_exclude=library_exclude,
+                plugin_paths=plugin_paths,
+                django_settings=django_settings,
+                string_references=string_references,
+                non_registering_bases=non_registering_bases,
+                pure_callables=pure_callables,
+                strip_debug=strip_debug,
+                closed_world_packages=closed_world_packages,
+                verify=verify,
+                smoke_command=smoke_command,
+                trace_files=trace_files,
+                memory_cache=memory_cache,
+                event_handler=io,
+            )
+    except TreeshakeVerificationError as e:
+        for failure in e.failures:
+            console.print(f"[red]{escape(failure.source)}[/]: {escape(failure.error)}")
+            if failure.removed_statement is not None:
+                module_spec, line, source = failure.removed_statement
+                console.print(
+                    f"  [dim]caused by removing {module_spec}:{line}:[/]",
+                    escape(source),
+                )
+        raise click.ClickException(
+            "Verification of the treeshaken bundle failed"
+        ) from e
 
 
 __all__ = ["cli_treeshake_package"]
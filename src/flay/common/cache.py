@@ -0,0 +1,18 @@
+from __future__ import annotations
+from importlib.metadata import version
+import hashlib
+import json
+import sys
+import typing as t
+
+
+def get_cache_namespace(*config: t.Any) -> str:
+    """
+    Cache entries are only valid for the same flay version, target interpreter
+    and configuration
+    """
+    digest = hashlib.sha256()
+    digest.update(version("flay").encode())
+    digest.update(str(sys.implementation.cache_tag).encode())
+    digest.update(json.dumps(config, sort_keys=True, default=sorted).encode())
+    return digest.hexdigest()[:16]
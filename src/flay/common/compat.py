@@ -4,9 +4,11 @@ FLAY_STANDARD_ENCODING = "utf-8" if sys.platform.startswith("win") else None
 
 if sys.version_info < (3, 11):
     # packages_distributions is available, but does not work as expected with cpython@3.10 (rich was not discovered)
-    from importlib_metadata import packages_distributions  # type: ignore[import-not-found,unused-ignore]
+    from importlib_metadata import packages_distributions, entry_points  # type: ignore[import-not-found,unused-ignore]
+    import tomli as tomllib  # type: ignore[import-not-found,unused-ignore]
 else:
-    from importlib.metadata import packages_distributions  # type: ignore[attr-defined]
+    from importlib.metadata import packages_distributions, entry_points  # type: ignore[attr-defined]
+    import tomllib
 
 
-__all__ = ["packages_distributions"]
+__all__ = ["entry_points", "packages_distributions", "tomllib"]
@@ -0,0 +1,174 @@
+"""
+Django loads apps, middlewares and backends from dotted strings in its settings and
+discovers migrations, template tags and management commands by convention,
+none of which is visible as an import.
+"""
+
+from __future__ import annotations
+from flay._flay_rs import DjangoSettingsCollector
+from flay.common.module_spec import (
+    find_module_path,
+    get_parent_package,
+    get_top_level_package,
+)
+from flay.common.pydantic import FlayBaseModel
+from pathlib import Path
+import logging
+import re
+import shutil
+import typing as t
+import typing_extensions as te
+
+log = logging.getLogger(__name__)
+
+DOTTED_PATH_PATTERN = re.compile(r"[A-Za-z_]\w*(\.[A-Za-z_]\w*)+")
+
+# modules of an app which are imported by django or by convention
+APP_MODULES = ("apps", "models", "admin", "urls")
+# packages of an app whose modules are discovered by listing their directory
+APP_DISCOVERED_PACKAGES = ("migrations", "templatetags", "management.commands")
+APP_RESOURCE_DIRS = ("templates", "static")
+
+ModuleFileFinder: te.TypeAlias = t.Callable[[str], t.Optional[Path]]
+
+
+def find_module_file(module_spec: str) -> Path | None:
+    found_spec = find_module_path(module_spec)
+    if found_spec is None or found_spec.origin is None:
+        return None
+    return Path(found_spec.origin)
+
+
+class DjangoProject(FlayBaseModel):
+    # the settings module and the modules it star imports
+    settings_modules: list[str]
+    # packages of the installed apps
+    apps: list[str]
+    # dotted paths of importable modules and symbols in the settings
+    import_paths: list[str]
+    root_urlconf: t.Optional[str] = None
+
+
+def _resolve_app(app: str, find_file: ModuleFileFinder) -> str | None:
+    """
+    Apps are installed by their package or by the dotted path of their AppConfig,
+    which is usually located in the apps module of the package
+    """
+    app_file = find_file(app)
+    if app_file is not None and app_file.name == "__init__.py":
+        return app
+    app_package = get_parent_package(app)
+    if app_package.endswith(".apps"):
+        app_package = get_parent_package(app_package)
+    if find_file(app_package) is None:
+        log.warning("Could not find the package of the installed app %s", app)
+        return None
+    return app_package
+
+
+def evaluate_django_settings(
+    settings_module: str, find_file: ModuleFileFinder = find_module_file
+) -> DjangoProject:
+    """
+    `find_file` locates the file of a module, which differs between the environment
+    flay runs in and the bundle
+    """
+    collector = DjangoSettingsCollector()
+    settings_modules: list[str] = []
+    pending_modules = [settings_module]
+    while pending_modules:
+        module_spec = pending_modules.pop()
+        if module_spec in settings_modules:
+            continue
+        module_file = find_file(module_spec)
+        if module_file is None:
+            log.warning("Could not find the django settings module %s", module_spec)
+            continue
+        settings_modules.append(module_spec)
+        pending_modules.extend(collector.process_module(module_spec, module_file))
+
+    settings = collector.settings
+    import_paths = sorted(
+        {
+            string
+            for strings in settings.values()
+            for string in strings
+            if DOTTED_PATH_PATTERN.fullmatch(string)
+            and find_file(get_top_level_package(string)) is not None
+        }
+    )
+    apps: list[str] = []
+    for app in settings.get("INSTALLED_APPS", []):
+        app_package = _resolve_app(app, find_file)
+        if app_package is not None and app_package not in apps:
+            apps.append(app_package)
+    root_urlconfs = settings.get("ROOT_URLCONF", [])
+    return DjangoProject(
+        settings_modules=settings_modules,
+        apps=apps,
+        import_paths=import_paths,
+        root_urlconf=root_urlconfs[0] if root_urlconfs else None,
+    )
+
+
+def get_django_modules(
+    project: DjangoProject, find_file: ModuleFileFinder = find_module_file
+) -> list[str]:
+    """
+    Module specs django imports by name, which need to be collected
+    """
+    module_specs = list(project.settings_modules)
+    for import_path in project.import_paths:
+        # symbols are imported from their module
+        module_specs.extend((import_path, get_parent_package(import_path)))
+    for app in project.apps:
+        module_specs.append(app)
+        module_specs.extend(f"{app}.{module}" for module in APP_MODULES)
+        for package in APP_DISCOVERED_PACKAGES:
+            package_spec = f"{app}.{package}"
+            package_file = find_file(package_spec)
+            if package_file is None or package_file.name != "__init__.py":
+                continue
+            module_specs.extend((get_parent_package(package_spec), package_spec))
+            module_specs.extend(
+                f"{package_spec}.{module_file.stem}"
+                for module_file in sorted(package_file.parent.glob("*.py"))
+                if module_file.name != "__init__.py"
+            )
+    return module_specs
+
+
+def get_django_preserve_symbols(project: DjangoProject) -> set[str]:
+    """
+    Symbols django looks up by name, as patterns of preserve-symbols
+    """
+    symbols = set(project.import_paths)
+    for settings_module in project.settings_modules:
+        symbols.add(rf"re:{re.escape(settings_module)}\.[A-Z][A-Z0-9_]*")
+    if project.root_urlconf:
+        symbols.add(f"{project.root_urlconf}.*")
+    for app in project.apps:
+        symbols.update(f"{app}.{module}.**" for module in APP_MODULES)
+        symbols.update(f"{app}.{package}.**" for package in APP_DISCOVERED_PACKAGES)
+    return symbols
+
+
+def bundle_django_resources(
+    project: DjangoProject,
+    destination_path: Path,
+    find_file: ModuleFileFinder = find_module_file,
+) -> None:
+    """
+    Copy the templates and static files of the installed apps
+    """
+    for app in project.apps:
+        app_file = find_file(app)
+        if app_file is None:
+            continue
+        for resource_dir_name in APP_RESOURCE_DIRS:
+            resource_dir = app_file.parent / resource_dir_name
+            if not resource_dir.is_dir():
+                continue
+            target_dir = destination_path.joinpath(*app.split("."), resource_dir_name)
+            shutil.copytree(str(resource_dir), str(target_dir), dirs_exist_ok=True)
+            log.debug("Copied %s to %s", resource_dir, target_dir)
@@ -0,0 +1,15 @@
+from functools import cache
+
+
+# typing constructs and enums use metaclasses and `__init_subclass__`
+# without registering their subclasses anywhere
+_stdlib_bases: set[str] = {
+    "enum.*",
+    "typing.*",
+    "typing_extensions.*",
+}
+
+
+@cache
+def get_default_non_registering_bases() -> set[str]:
+    return {*_stdlib_bases}
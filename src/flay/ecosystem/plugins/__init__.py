@@ -0,0 +1,190 @@
+"""
+Ecosystem plugins declare what flay needs to know about a distribution to bundle it,
+e.g. lazily imported names or decorators without side-effects.
+
+Plugins are TOML files, which are shipped with flay, provided by other distributions
+with an entry point in the `flay.plugins` group pointing to a package with TOML files,
+or passed as plugin paths from the project configuration.
+A plugin is only loaded if its distribution is part of the bundle.
+"""
+
+from __future__ import annotations
+from flay.common.compat import entry_points, packages_distributions, tomllib
+from flay.common.pydantic import FlayBaseModel
+from functools import cache
+from importlib.resources import files
+from pathlib import Path
+from pydantic import ConfigDict, Field
+import importlib
+import importlib.util
+import logging
+import re
+import typing as t
+
+log = logging.getLogger(__name__)
+
+PLUGINS_ENTRY_POINT_GROUP = "flay.plugins"
+
+
+class DynamicImports(FlayBaseModel):
+    """
+    Mapping of a module with a module level `__getattr__` from names to the modules
+    they are lazily imported from. Values are absolute module names or tuples of
+    a package and a (relative) module name, `__module__` marks names of submodules.
+    """
+
+    model_config = ConfigDict(extra="forbid")
+
+    module: str
+    attribute: str
+    # keep the lazily imported symbols, as they might be accessed by name only
+    preserve: bool = False
+
+
+class EcosystemPlugin(FlayBaseModel):
+    model_config = ConfigDict(extra="forbid", populate_by_name=True)
+
+    distribution: str
+    import_aliases: dict[str, str] = Field(
+        default_factory=dict, alias="import-aliases"
+    )
+    preserve_symbols: list[str] = Field(default_factory=list, alias="preserve-symbols")
+    safe_decorators: list[str] = Field(default_factory=list, alias="safe-decorators")
+    # bases with a metaclass or `__init_subclass__` which don't register subclasses
+    non_registering_bases: list[str] = Field(
+        default_factory=list, alias="non-registering-bases"
+    )
+    # callables without side-effects, e.g. factories of module level constants
+    pure_callables: list[str] = Field(default_factory=list, alias="pure-callables")
+    # mapping with a module as key and a glob pattern as value, like the resources
+    resources: dict[str, str] = Field(default_factory=dict)
+    # entry points of these groups are loaded at runtime, e.g. by a plugin system
+    entry_point_groups: list[str] = Field(
+        default_factory=list, alias="entry-point-groups"
+    )
+    dynamic_imports: list[DynamicImports] = Field(
+        default_factory=list, alias="dynamic-imports"
+    )
+
+
+class Ecosystem(FlayBaseModel):
+    import_aliases: dict[str, str] = Field(default_factory=dict)
+    preserve_symbols: set[str] = Field(default_factory=set)
+    safe_decorators: set[str] = Field(default_factory=set)
+    non_registering_bases: set[str] = Field(default_factory=set)
+    pure_callables: set[str] = Field(default_factory=set)
+    resources: dict[str, str] = Field(default_factory=dict)
+    entry_point_groups: set[str] = Field(default_factory=set)
+
+
+def normalize_distribution_name(name: str) -> str:
+    return re.sub(r"[-_.]+", "-", name).lower()
+
+
+def read_plugin(content: str) -> EcosystemPlugin:
+    return EcosystemPlugin.model_validate(tomllib.loads(content))
+
+
+@cache
+def _get_shipped_plugins() -> tuple[EcosystemPlugin, ...]:
+    plugins: list[EcosystemPlugin] = []
+    for entry_point in entry_points(group=PLUGINS_ENTRY_POINT_GROUP):
+        for resource in files(entry_point.module).iterdir():
+            if resource.name.endswith(".toml"):
+                log.debug("Found plugin %s of %s", resource.name, entry_point.value)
+                plugins.append(read_plugin(resource.read_text()))
+    for resource in files(__name__).iterdir():
+        if resource.name.endswith(".toml"):
+            plugins.append(read_plugin(resource.read_text()))
+    return tuple(plugins)
+
+
+def discover_plugins(plugin_paths: t.Iterable[Path] = ()) -> list[EcosystemPlugin]:
+    """
+    Project plugins come first, so they take precedence over shipped plugins
+    when they declare the same import alias
+    """
+    plugins: list[EcosystemPlugin] = []
+    for plugin_path in plugin_paths:
+        paths = [plugin_path]
+        if plugin_path.is_dir():
+            paths = sorted(plugin_path.glob("*.toml"))
+        plugins.extend(read_plugin(path.read_text()) for path in paths)
+    plugins.extend(_get_shipped_plugins())
+    return plugins
+
+
+def get_distributions(packages: t.Iterable[str]) -> set[str]:
+    """
+    Normalized names of the distributions providing the given top level packages
+    """
+    package_dists = packages_distributions()
+    return {
+        normalize_distribution_name(dist_name)
+        for package in packages
+        for dist_name in package_dists.get(package, ())
+    }
+
+
+def _resolve_dynamic_imports(
+    dynamic_imports: DynamicImports,
+) -> dict[str, str]:
+    try:
+        module = importlib.import_module(dynamic_imports.module)
+    except ImportError:
+        log.warning(
+            "Could not import %s for its dynamic imports", dynamic_imports.module
+        )
+        return {}
+    aliases: dict[str, str] = {}
+    for name, target in getattr(module, dynamic_imports.attribute, {}).items():
+        if isinstance(target, str):
+            target_module = target
+        else:
+            package, relative_module = target
+            if relative_module == "__module__":
+                continue
+            target_module = importlib.util.resolve_name(relative_module, package)
+        aliases[f"{dynamic_imports.module}.{name}"] = f"{target_module}.{name}"
+    return aliases
+
+
+def load_ecosystem(
+    distributions: t.Iterable[str], plugin_paths: t.Iterable[Path] = ()
+) -> Ecosystem:
+    """
+    Merge the plugins of the given distributions
+    """
+    distribution_names = {normalize_distribution_name(dist) for dist in distributions}
+    ecosystem = Ecosystem()
+    for plugin in discover_plugins(plugin_paths):
+        if normalize_distribution_name(plugin.distribution) not in distribution_names:
+            continue
+        log.debug("Loading plugin for %s", plugin.distribution)
+        import_aliases = dict(plugin.import_aliases)
+        for dynamic_imports in plugin.dynamic_imports:
+            dynamic_aliases = _resolve_dynamic_imports(dynamic_imports)
+            import_aliases.update(dynamic_aliases)
+            if dynamic_imports.preserve:
+                ecosystem.preserve_symbols.update(dynamic_aliases.values())
+        for search, replacement in import_aliases.items():
+            ecosystem.import_aliases.setdefault(search, replacement)
+        ecosystem.preserve_symbols.update(plugin.preserve_symbols)
+        ecosystem.safe_decorators.update(plugin.safe_decorators)
+        ecosystem.non_registering_bases.update(plugin.non_registering_bases)
+        ecosystem.pure_callables.update(plugin.pure_callables)
+        for module_spec, glob_pattern in plugin.resources.items():
+            ecosystem.resources.setdefault(module_spec, glob_pattern)
+        ecosystem.entry_point_groups.update(plugin.entry_point_groups)
+    return ecosystem
+
+
+def get_entry_points(groups: t.Iterable[str]) -> list[tuple[str, str | None]]:
+    """
+    Module and attribute of every installed entry point in the given groups
+    """
+    return [
+        (entry_point.module, entry_point.attr)
+        for group in sorted(groups)
+        for entry_point in entry_points(group=group)
+    ]
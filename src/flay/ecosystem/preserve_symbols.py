@@ -1,22 +1,3 @@
-from functools import cache
-from pydantic import _dynamic_imports as pydantic_dynamic_imports
-
-
-@cache
-def get_default_preserve_symbols() -> set[str]:
-    res: set[str] = {
-        *(),
-    }
-
-    for obj, (parent, module) in pydantic_dynamic_imports.items():
-        if module == "__module__":
-            continue
-        else:
-            res.add(f"{parent}{module}.{obj}")
-
-    return res
-
-
 def enrich_preserve_symbols_from_import_aliases(
     symbols: set[str], import_aliases: dict[str, str]
 ) -> None:
@@ -0,0 +1,92 @@
+from functools import cache
+
+
+_builtin_callables: set[str] = {
+    "__builtin__.abs",
+    "__builtin__.all",
+    "__builtin__.any",
+    "__builtin__.bool",
+    "__builtin__.bytes",
+    "__builtin__.chr",
+    "__builtin__.dict",
+    "__builtin__.divmod",
+    "__builtin__.enumerate",
+    "__builtin__.float",
+    "__builtin__.frozenset",
+    "__builtin__.int",
+    "__builtin__.isinstance",
+    "__builtin__.issubclass",
+    "__builtin__.len",
+    "__builtin__.list",
+    "__builtin__.max",
+    "__builtin__.min",
+    "__builtin__.object",
+    "__builtin__.ord",
+    "__builtin__.range",
+    "__builtin__.repr",
+    "__builtin__.reversed",
+    "__builtin__.round",
+    "__builtin__.set",
+    "__builtin__.slice",
+    "__builtin__.sorted",
+    "__builtin__.str",
+    "__builtin__.sum",
+    "__builtin__.tuple",
+    "__builtin__.zip",
+}
+
+# constructors and functions whose result only depends on their arguments,
+# calls reading the environment or the filesystem (e.g. os.getenv or pathlib.Path)
+# are not part of the defaults. logging.getLogger only registers an unconfigured logger
+_stdlib_callables: set[str] = {
+    "collections.ChainMap",
+    "collections.Counter",
+    "collections.OrderedDict",
+    "collections.defaultdict",
+    "collections.deque",
+    "collections.namedtuple",
+    "contextvars.ContextVar",
+    "dataclasses.field",
+    "datetime.date",
+    "datetime.datetime",
+    "datetime.time",
+    "datetime.timedelta",
+    "datetime.timezone",
+    "decimal.Decimal",
+    "enum.auto",
+    "fractions.Fraction",
+    "functools.partial",
+    "logging.getLogger",
+    "operator.attrgetter",
+    "operator.itemgetter",
+    "operator.methodcaller",
+    "pathlib.PurePath",
+    "re.compile",
+    "struct.Struct",
+    "threading.Event",
+    "threading.Lock",
+    "threading.RLock",
+    "threading.local",
+    "typing.NamedTuple",
+    "typing.NewType",
+    "typing.ParamSpec",
+    "typing.TypeVar",
+    "typing.TypeVarTuple",
+    "typing.TypedDict",
+    "typing.cast",
+    "typing_extensions.NamedTuple",
+    "typing_extensions.NewType",
+    "typing_extensions.ParamSpec",
+    "typing_extensions.TypeVar",
+    "typing_extensions.TypeVarTuple",
+    "typing_extensions.TypedDict",
+    "typing_extensions.cast",
+    "weakref.WeakKeyDictionary",
+    "weakref.WeakSet",
+    "weakref.WeakValueDictionary",
+}
+
+
+@cache
+def get_default_pure_callables() -> set[str]:
+    return {*_builtin_callables, *_stdlib_callables}
@@ -24,17 +24,7 @@ _stdlib_decorators: set[str] = {
     "typing_extensions.dataclass_transform",
 }
 
-_ecosystem_decorators: set[str] = {
-    "attr.attrs",
-    "attr._make.attrs",
-    "clonf.clonf_click",
-    "pydantic.root_validator",
-    "pydantic.validator",
-    "pydantic.v1.class_validators.root_validator",
-    "pydantic.v1.class_validators.validator",
-}
-
 
 @cache
 def get_default_safe_decorators() -> set[str]:
-    return {*_builtin_decorators, *_stdlib_decorators, *_ecosystem_decorators}
+    return {*_builtin_decorators, *_stdlib_decorators}
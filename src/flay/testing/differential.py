@@ -0,0 +1,292 @@
+from __future__ import annotations
+from flay.bundle.package import bundle_package
+from flay.common.module_spec import get_top_level_package
+from flay.common.pydantic import FlayBaseModel
+from flay.treeshake.package import treeshake_package
+from pathlib import Path
+from pydantic import Field
+import json
+import logging
+import os
+import subprocess
+import sys
+import typing as t
+import typing_extensions as te
+
+log = logging.getLogger(__name__)
+
+# imports the modules passed on stdin and prints what they provide as json
+_SNAPSHOT_SCRIPT = """
+import importlib, inspect, json, re, sys
+
+if len(sys.argv) > 1:
+    sys.path.insert(0, sys.argv[1])
+
+
+def get_signature(value):
+    if isinstance(value, (staticmethod, classmethod)):
+        value = value.__func__
+    if not callable(value):
+        return None
+    try:
+        # defaults like objects are shown with their address, which differs per run
+        return re.sub(r" at 0x[0-9a-fA-F]+", "", str(inspect.signature(value)))
+    except (TypeError, ValueError):
+        return None
+
+
+def describe(value):
+    if inspect.ismodule(value):
+        return {"kind": "module"}
+    if inspect.isclass(value):
+        return {
+            "kind": "class",
+            "signature": get_signature(value),
+            "resolution_order": [
+                f"{cls.__module__}.{cls.__qualname__}" for cls in value.__mro__
+            ],
+            "members": {
+                name: get_signature(member)
+                for name, member in vars(value).items()
+                if not name.startswith("_") or name == "__init__"
+            },
+        }
+    if callable(value):
+        return {"kind": "function", "signature": get_signature(value)}
+    return {"kind": type(value).__qualname__}
+
+
+snapshots = {}
+for module_spec in sys.stdin.read().split():
+    try:
+        module = importlib.import_module(module_spec)
+    except Exception as e:
+        snapshots[module_spec] = {"error": f"{type(e).__name__}: {e}"}
+        continue
+    exports = getattr(module, "__all__", None)
+    snapshots[module_spec] = {
+        "exports": list(exports) if exports is not None else None,
+        "names": {
+            name: describe(value)
+            for name, value in vars(module).items()
+            if not name.startswith("_")
+        },
+    }
+print(json.dumps(snapshots))
+"""
+
+DifferenceKind: te.TypeAlias = t.Literal[
+    "import_error", "exports", "added_name", "kind", "signature", "mro", "member"
+]
+
+
+class SymbolSnapshot(FlayBaseModel):
+    # module, class, function or the type of other values
+    kind: str
+    signature: t.Optional[str] = None
+    # fully qualified names of the classes in the method resolution order
+    resolution_order: t.Optional[list[str]] = None
+    # public attributes of a class and `__init__` -> signature if callable
+    members: t.Optional[dict[str, t.Optional[str]]] = None
+
+
+class ModuleSnapshot(FlayBaseModel):
+    # the import failed with this error
+    error: t.Optional[str] = None
+    # `__all__` of the module
+    exports: t.Optional[list[str]] = None
+    # public names of the module
+    names: dict[str, SymbolSnapshot] = Field(default_factory=dict)
+
+
+class ApiDifference(FlayBaseModel):
+    module_spec: str
+    kind: DifferenceKind
+    name: t.Optional[str] = None
+    original: t.Optional[str] = None
+    flayed: t.Optional[str] = None
+
+    def __str__(self) -> str:
+        symbol = f"{self.module_spec}.{self.name}" if self.name else self.module_spec
+        return f"{symbol}: {self.kind} differs, {self.original!r} != {self.flayed!r}"
+
+
+class DifferentialResult(FlayBaseModel):
+    differences: list[ApiDifference] = Field(default_factory=list)
+    # module -> public names which were removed, which is expected for unused names
+    removed_names: dict[str, list[str]] = Field(default_factory=dict)
+
+
+def get_package_modules(source_dir: Path, packages: t.Iterable[str]) -> list[str]:
+    """Module specs of the python files of `packages` below `source_dir`"""
+    module_specs: set[str] = set()
+    for package in packages:
+        package_dir = source_dir / package.replace(".", os.path.sep)
+        package_files = sorted(package_dir.rglob("*.py"))
+        package_file = package_dir.with_suffix(".py")
+        if package_file.exists():
+            package_files.append(package_file)
+        for path in package_files:
+            parts = path.relative_to(source_dir).with_suffix("").parts
+            # importing __main__ would run the application
+            if parts[-1] == "__main__":
+                continue
+            if parts[-1] == "__init__":
+                parts = parts[:-1]
+            module_specs.add(".".join(parts))
+    return sorted(module_specs)
+
+
+def snapshot_modules(
+    module_specs: t.Iterable[str], path: Path | None = None
+) -> dict[str, ModuleSnapshot]:
+    """
+    Import the modules in a separate interpreter and record what they provide.
+    With a `path`, only the modules there and the standard library are importable,
+    otherwise the modules are imported from the environment running flay.
+    """
+    args = [sys.executable, "-B"]
+    if path is not None:
+        args.extend(["-I", "-S"])
+    args.extend(["-c", _SNAPSHOT_SCRIPT])
+    if path is not None:
+        args.append(str(path.absolute()))
+    result = subprocess.run(
+        args,
+        input="\n".join(module_specs),
+        capture_output=True,
+        text=True,
+        check=True,
+    )
+    return {
+        module_spec: ModuleSnapshot.model_validate(snapshot)
+        for module_spec, snapshot in json.loads(result.stdout).items()
+    }
+
+
+def _diff_symbols(
+    module_spec: str,
+    name: str,
+    original: SymbolSnapshot,
+    flayed: SymbolSnapshot,
+) -> t.Iterator[ApiDifference]:
+    if original.kind != flayed.kind:
+        yield ApiDifference(
+            module_spec=module_spec,
+            kind="kind",
+            name=name,
+            original=original.kind,
+            flayed=flayed.kind,
+        )
+        return
+    if original.signature != flayed.signature:
+        yield ApiDifference(
+            module_spec=module_spec,
+            kind="signature",
+            name=name,
+            original=original.signature,
+            flayed=flayed.signature,
+        )
+    if original.resolution_order != flayed.resolution_order:
+        yield ApiDifference(
+            module_spec=module_spec,
+            kind="mro",
+            name=name,
+            original=", ".join(original.resolution_order or []),
+            flayed=", ".join(flayed.resolution_order or []),
+        )
+    original_members = original.members or {}
+    # unused methods might be removed, but retained ones must not change
+    for member, signature in (flayed.members or {}).items():
+        if member not in original_members or original_members[member] != signature:
+            yield ApiDifference(
+                module_spec=module_spec,
+                kind="member",
+                name=f"{name}.{member}",
+                original=original_members.get(member),
+                flayed=signature,
+            )
+
+
+def diff_snapshots(
+    original: dict[str, ModuleSnapshot], flayed: dict[str, ModuleSnapshot]
+) -> DifferentialResult:
+    """
+    Compare the modules retained by flay with the original modules.
+    Retained names have to be the same as before, removed names are only listed.
+    """
+    result = DifferentialResult()
+    for module_spec, flayed_module in sorted(flayed.items()):
+        original_module = original.get(module_spec)
+        if original_module is None or original_module.error is not None:
+            log.debug("Skipped %s, it can't be imported originally", module_spec)
+            continue
+        if flayed_module.error is not None:
+            result.differences.append(
+                ApiDifference(
+                    module_spec=module_spec,
+                    kind="import_error",
+                    flayed=flayed_module.error,
+                )
+            )
+            continue
+        if original_module.exports != flayed_module.exports:
+            result.differences.append(
+                ApiDifference(
+                    module_spec=module_spec,
+                    kind="exports",
+                    original=repr(original_module.exports),
+                    flayed=repr(flayed_module.exports),
+                )
+            )
+        for name, flayed_symbol in flayed_module.names.items():
+            original_symbol = original_module.names.get(name)
+            if original_symbol is None:
+                result.differences.append(
+                    ApiDifference(
+                        module_spec=module_spec,
+                        kind="added_name",
+                        name=name,
+                        flayed=flayed_symbol.kind,
+                    )
+                )
+                continue
+            result.differences.extend(
+                _diff_symbols(module_spec, name, original_symbol, flayed_symbol)
+            )
+        removed_names = sorted(set(original_module.names) - set(flayed_module.names))
+        if removed_names:
+            result.removed_names[module_spec] = removed_names
+    return result
+
+
+def compare_bundle(
+    bundle_dir: Path,
+    packages: t.Iterable[str],
+    original_path: Path | None = None,
+) -> DifferentialResult:
+    """
+    Compare the modules of `packages` in a bundle with the original modules,
+    which are imported from `original_path` or the environment running flay
+    """
+    module_specs = get_package_modules(bundle_dir, packages)
+    return diff_snapshots(
+        snapshot_modules(module_specs, original_path),
+        snapshot_modules(module_specs, bundle_dir),
+    )
+
+
+def bundle_and_compare(
+    module_spec: str,
+    destination_path: Path,
+    treeshake: bool = True,
+    **treeshake_options: t.Any,
+) -> DifferentialResult:
+    """
+    Bundle and treeshake `module_spec` like `flay bundle` would, then compare
+    the package in the bundle with the package in the environment running flay
+    """
+    bundle_package(module_spec, destination_path, bundle_metadata=False)
+    if treeshake:
+        treeshake_package(str(destination_path.absolute()), **treeshake_options)
+    return compare_bundle(destination_path, [get_top_level_package(module_spec)])
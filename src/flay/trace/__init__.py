@@ -0,0 +1,114 @@
+"""
+Runtime traces pin what static analysis can't see, e.g. modules imported by a
+computed name or functions looked up with getattr. `flay trace` runs a command,
+e.g. the test suite, and records every module it imports and every function
+and class it calls. The bundle includes the traced modules and treeshaking
+preserves the traced symbols.
+"""
+
+from __future__ import annotations
+
+import json
+import logging
+import os
+import shutil
+import subprocess
+import sys
+import tempfile
+import typing as t
+from pathlib import Path
+
+from pydantic import Field
+
+from flay.common.exc import FlayError
+from flay.common.pydantic import FlayBaseModel
+
+log = logging.getLogger(__name__)
+
+_RUNNER_PATH = Path(__file__).parent / "_runner.py"
+
+
+class FlayTraceError(FlayError):
+    pass
+
+
+class Trace(FlayBaseModel):
+    # modules imported while the command was running
+    modules: list[str] = Field(default_factory=list)
+    # fully qualified names of the functions, methods and classes called
+    symbols: list[str] = Field(default_factory=list)
+
+    def merge(self, other: Trace) -> Trace:
+        return Trace(
+            modules=sorted({*self.modules, *other.modules}),
+            symbols=sorted({*self.symbols, *other.symbols}),
+        )
+
+
+def load_traces(paths: t.Iterable[Path]) -> Trace:
+    trace = Trace()
+    for path in paths:
+        trace = trace.merge(Trace.model_validate_json(path.read_text()))
+    return trace
+
+
+def get_runner_args(command: t.Sequence[str]) -> list[str]:
+    """
+    Arguments for the runner from a command like `python -m myapp`,
+    `python script.py` or a console script like `pytest`
+    """
+    if not command:
+        raise FlayTraceError("No command to trace")
+    executable, *args = command
+    if executable == sys.executable or os.path.basename(executable).startswith(
+        "python"
+    ):
+        if not args:
+            raise FlayTraceError("Tracing an interactive interpreter is not supported")
+        return args
+    if executable.endswith(".py"):
+        return [executable, *args]
+    # console scripts of the environment are python scripts
+    script = shutil.which(executable)
+    if script is None:
+        raise FlayTraceError(f"Command {executable} not found")
+    return [script, *args]
+
+
+def trace_command(command: t.Sequence[str]) -> tuple[Trace, int]:
+    """
+    Run `command` with the interpreter running flay and record what it uses,
+    returns the trace and the exit code of the command
+    """
+    with tempfile.TemporaryDirectory(prefix="flay-trace-") as temp_dir:
+        output = Path(temp_dir) / "trace.json"
+        result = subprocess.run(
+            [sys.executable, str(_RUNNER_PATH), str(output), *get_runner_args(command)]
+        )
+        if not output.exists():
+            raise FlayTraceError(
+                f"Command exited with {result.returncode} before writing a trace"
+            )
+        trace = Trace.model_validate(json.loads(output.read_text()))
+    log.debug(
+        "Traced %s modules and %s symbols", len(trace.modules), len(trace.symbols)
+    )
+    return trace, result.returncode
+
+
+def write_trace(trace: Trace, path: Path, append: bool = False) -> Trace:
+    """Write `trace` to `path`, merged with the trace found there if `append` is set"""
+    if append and path.exists():
+        trace = load_traces([path]).merge(trace)
+    path.parent.mkdir(parents=True, exist_ok=True)
+    path.write_text(trace.model_dump_json(indent=2))
+    return trace
+
+
+__all__ = [
+    "FlayTraceError",
+    "Trace",
+    "load_traces",
+    "trace_command",
+    "write_trace",
+]
@@ -0,0 +1,171 @@
+"""
+Runs a python program like `python` would and records the modules it imports
+and the functions it calls.
+
+Usage: python _runner.py OUTPUT (-m MODULE | -c CODE | SCRIPT) [ARGS...]
+
+This file is run as a script, so it must only import the standard library,
+everything imported here would end up in the trace otherwise.
+"""
+
+import json
+import os
+import runpy
+import sys
+import threading
+import typing as t
+from importlib.util import find_spec
+from types import CodeType, FrameType
+
+# modules imported by the interpreter on startup and by this runner itself
+_INITIAL_MODULES = set(sys.modules)
+
+# flag of the code objects of functions, module and class bodies are excluded
+_CO_NEWLOCALS = 0x0002
+
+_called_code: set[CodeType] = set()
+# file -> first line and name of its code objects -> qualified name, before 3.11
+_file_qualnames: dict[str, dict[tuple[int, str], str]] = {}
+# file of the module run with `-m` -> its name, it is only `__main__` while running
+_main_module_files: dict[str, str] = {}
+
+
+def _install_monitoring() -> None:
+    monitoring = sys.monitoring
+    tool_id = monitoring.PROFILER_ID
+    monitoring.use_tool_id(tool_id, "flay")
+
+    def on_start(code: CodeType, instruction_offset: int) -> t.Any:
+        _called_code.add(code)
+        # every code object only needs to be seen once
+        return monitoring.DISABLE
+
+    monitoring.register_callback(tool_id, monitoring.events.PY_START, on_start)
+    monitoring.set_events(tool_id, monitoring.events.PY_START)
+
+
+def _install_profile() -> None:
+    def profile(frame: FrameType, event: str, arg: t.Any) -> None:
+        if event == "call":
+            _called_code.add(frame.f_code)
+
+    threading.setprofile(profile)
+    sys.setprofile(profile)
+
+
+def _get_qualnames(filename: str) -> dict[tuple[int, str], str]:
+    """
+    Qualified names of the code objects compiled from a file, the way the
+    compiler names them, e.g. `Cls.method.<locals>.inner`
+    """
+    qualnames: dict[tuple[int, str], str] = {}
+
+    def visit(code: CodeType, prefix: str) -> None:
+        for const in code.co_consts:
+            if isinstance(const, CodeType):
+                qualname = f"{prefix}{const.co_name}"
+                qualnames[(const.co_firstlineno, const.co_name)] = qualname
+                if const.co_flags & _CO_NEWLOCALS:
+                    visit(const, f"{qualname}.<locals>.")
+                else:
+                    visit(const, f"{qualname}.")
+
+    try:
+        with open(filename, "rb") as f:
+            visit(compile(f.read(), filename, "exec"), "")
+    except (OSError, SyntaxError, ValueError):
+        pass
+    return qualnames
+
+
+def _get_qualname(code: CodeType) -> str:
+    if hasattr(code, "co_qualname"):
+        return code.co_qualname
+    # code objects only know their qualified name since 3.11, the file is
+    # compiled again to find the classes and functions defining them
+    if code.co_filename not in _file_qualnames:
+        _file_qualnames[code.co_filename] = _get_qualnames(code.co_filename)
+    return _file_qualnames[code.co_filename].get(
+        (code.co_firstlineno, code.co_name), code.co_name
+    )
+
+
+def _get_symbols(code: CodeType) -> list[str]:
+    """
+    Qualified names of a function and its parents, e.g. `Cls` and `Cls.method`,
+    nested functions are recorded as the function defining them
+    """
+    qualname = _get_qualname(code)
+    parts: list[str] = []
+    for part in qualname.split("."):
+        if part.startswith("<"):
+            break
+        parts.append(part)
+    return [".".join(parts[: index + 1]) for index in range(len(parts))]
+
+
+def _write_trace(output: str) -> None:
+    imported_modules = {
+        name
+        for name in set(sys.modules) - _INITIAL_MODULES
+        if name != "__main__" and sys.modules.get(name) is not None
+    }
+    module_files = dict(_main_module_files)
+    for name, module in list(sys.modules.items()):
+        file = getattr(module, "__file__", None)
+        if file and name != "__main__":
+            module_files[os.path.realpath(file)] = name
+    symbols: set[str] = set()
+    for code in list(_called_code):
+        if not code.co_flags & _CO_NEWLOCALS:
+            continue
+        module_name = module_files.get(os.path.realpath(code.co_filename))
+        if module_name is None:
+            continue
+        symbols.update(f"{module_name}.{symbol}" for symbol in _get_symbols(code))
+
+    with open(output, "w") as f:
+        json.dump(
+            {"modules": sorted(imported_modules), "symbols": sorted(symbols)}, f
+        )
+
+
+def _find_main_module(module_name: str) -> None:
+    spec = find_spec(module_name)
+    if spec is not None and spec.submodule_search_locations is not None:
+        spec = find_spec(f"{module_name}.__main__")
+    if spec is not None and spec.origin is not None:
+        # `python -m pkg` runs `pkg.__main__`, which is bundled as `pkg`
+        _main_module_files[os.path.realpath(spec.origin)] = spec.name.removesuffix(
+            ".__main__"
+        )
+
+
+def main() -> None:
+    output = sys.argv[1]
+    args = sys.argv[2:]
+    if hasattr(sys, "monitoring"):
+        _install_monitoring()
+    else:
+        _install_profile()
+    try:
+        if args[0] == "-m":
+            sys.argv = [args[1], *args[2:]]
+            sys.path[0] = os.getcwd()
+            _find_main_module(args[1])
+            runpy.run_module(args[1], run_name="__main__", alter_sys=True)
+        elif args[0] == "-c":
+            sys.argv = ["-c", *args[2:]]
+            sys.path[0] = ""
+            exec(compile(args[1], "<string>", "exec"), {"__name__": "__main__"})
+        else:
+            sys.argv = args
+            sys.path[0] = os.path.dirname(os.path.abspath(args[0]))
+            runpy.run_path(args[0], run_name="__main__")
+    finally:
+        sys.setprofile(None)
+        _write_trace(output)
+
+
+if __name__ == "__main__":
+    main()
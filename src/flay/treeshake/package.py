@@ -1,6 +1,17 @@
 from __future__ import annotations
 
-from flay._flay_rs import NodesRemover, ReferencesCounter
+from flay._flay_rs import (
+    ClassHierarchyCollector,
+    DeadCodeRemover,
+    ExportsCollector,
+    MemoryCache,
+    NodesRemover,
+    PurityCollector,
+    ReferencesCounter,
+    UnusedDefaultsRemover,
+    is_symbol_pattern,
+    match_symbols,
+)
 from pathlib import Path
 
 import os
@@ -8,14 +19,18 @@ from collections import defaultdict
 import typing as t
 import logging
 
+from flay.common.cache import get_cache_namespace
 from flay.common.events import Event, EventHandler, NoopEventHandler
-from flay.ecosystem.import_aliases import get_default_import_aliases
-from flay.ecosystem.preserve_symbols import (
-    get_default_preserve_symbols,
-    enrich_preserve_symbols_from_import_aliases,
-)
+from flay.common.module_spec import get_top_level_package
+from flay.ecosystem.django import evaluate_django_settings, get_django_preserve_symbols
+from flay.ecosystem.plugins import get_distributions, get_entry_points, load_ecosystem
+from flay.ecosystem.preserve_symbols import enrich_preserve_symbols_from_import_aliases
+from flay.ecosystem.pure_callables import get_default_pure_callables
+from flay.trace import load_traces
+from flay.treeshake.verify import TreeshakeVerificationError, verify_bundle
 import typing_extensions as te
 
+from flay.ecosystem.non_registering_bases import get_default_non_registering_bases
 from flay.ecosystem.safe_decorators import get_default_safe_decorators
 
 log = logging.getLogger(__name__)
@@ -37,14 +52,68 @@ class TreeshakePackageNodesRemovalEvent(Event):
     module_spec: str
 
 
+class TreeshakePackageStringReferencesEvent(Event):
+    # string literal -> name it refers to
+    references: dict[str, str]
+
+
+class TreeshakePackageVerificationEvent(Event):
+    # number of retained modules which are imported
+    count: int
+    smoke_command: t.Optional[str] = None
+
+
 TreeshakePackageEvent: te.TypeAlias = t.Union[
     TreeshakePackageFoundModuleEvent,
     TreeshakePackageTotalModulesEvent,
     TreeshakePackageReferencesIterationEvent,
     TreeshakePackageNodesRemovalEvent,
+    TreeshakePackageStringReferencesEvent,
+    TreeshakePackageVerificationEvent,
 ]
 
 
+def get_preserved_exports(
+    exports: dict[str, set[str]], packages: t.Iterable[str]
+) -> set[str]:
+    """
+    Symbols listed in `__all__` of the given packages and their submodules,
+    which are the public API of a library
+    """
+    return {
+        f"{module_spec}.{name}"
+        for module_spec, names in exports.items()
+        for package in packages
+        if module_spec == package or module_spec.startswith(f"{package}.")
+        for name in names
+    }
+
+
+def get_library_symbols(
+    public_names: dict[str, set[str]],
+    exports: dict[str, set[str]],
+    include: list[str],
+    exclude: list[str] | None = None,
+) -> set[str]:
+    """
+    Public API of the modules matching the patterns in `include`, unless the
+    symbol matches a pattern in `exclude`. Patterns are matched against absolute
+    symbol paths like preserve-symbols, e.g. `my_sdk.**`.
+
+    A module exports the names in its static `__all__`, otherwise every name not
+    starting with an underscore, including names re-exported by a package.
+    Modules with a private segment (e.g. `my_sdk._internal`) are not public.
+    """
+    public_symbols = [
+        f"{module_spec}.{name}"
+        for module_spec, names in public_names.items()
+        if not any(segment.startswith("_") for segment in module_spec.split("."))
+        for name in exports.get(module_spec, names)
+    ]
+    patterns = [*include, *(f"!{pattern}" for pattern in exclude or [])]
+    return match_symbols(patterns, public_symbols)
+
+
 def _process_modules(
     references_counter: ReferencesCounter,
     file_modules: list[str],
@@ -64,6 +133,21 @@ def treeshake_package(
     import_aliases: dict[str, str] | None = None,
     preserve_symbols: set[str] | None = None,
     safe_decorators: set[str] | None = None,
+    cache_dir: Path | None = None,
+    preserve_exports: list[str] | None = None,
+    library_include: list[str] | None = None,
+    library_exclude: list[str] | None = None,
+    plugin_paths: list[Path] | None = None,
+    django_settings: str | None = None,
+    string_references: bool = False,
+    non_registering_bases: set[str] | None = None,
+    pure_callables: set[str] | None = None,
+    strip_debug: bool = False,
+    closed_world_packages: list[str] | None = None,
+    verify: bool = False,
+    smoke_command: str | None = None,
+    trace_files: list[Path] | None = None,
+    memory_cache: MemoryCache | None = None,
     event_handler: EventHandler[TreeshakePackageEvent] = NoopEventHandler(),
 ) -> int:
     source_files: set[str] = set()
@@ -100,18 +184,123 @@ def treeshake_package(
 
     new_references_count = 1
 
-    aliases = get_default_import_aliases()
+    ecosystem = load_ecosystem(
+        get_distributions(
+            {
+                get_top_level_package(module_spec)
+                for module_spec in known_module_specs.values()
+            }
+        ),
+        plugin_paths or [],
+    )
+    aliases = dict(ecosystem.import_aliases)
     if import_aliases:
         aliases.update(import_aliases)
 
-    preserve_symbols = get_default_preserve_symbols().union(preserve_symbols or [])
+    # unreachable code is removed first, so nothing it uses counts as referenced
+    dead_code_remover = DeadCodeRemover(strip_debug=strip_debug)
+    for file_path in file_modules:
+        dead_code_remover.process_module(source_path=Path(file_path))
+    log.debug(
+        "Removed %s unreachable statements", dead_code_remover.statements_removed
+    )
+
+    exports_collector = ExportsCollector()
+    class_hierarchy_collector = ClassHierarchyCollector()
+    purity_collector = PurityCollector()
+    for file_path in file_modules:
+        exports_collector.process_module(
+            module_spec=known_module_specs[file_path], source_path=Path(file_path)
+        )
+        class_hierarchy_collector.process_module(
+            module_spec=known_module_specs[file_path], source_path=Path(file_path)
+        )
+        purity_collector.process_module(
+            module_spec=known_module_specs[file_path], source_path=Path(file_path)
+        )
+    exports = exports_collector.exports
+    # subclasses registering themselves are kept with their module
+    registered_classes = class_hierarchy_collector.get_registered_classes(
+        get_default_non_registering_bases().union(
+            ecosystem.non_registering_bases, non_registering_bases or []
+        )
+    )
+    for registered_class, base in sorted(registered_classes.items()):
+        log.debug("%s is registered by %s", registered_class, base)
+    # unused module level assignments from pure calls are removed with their arguments
+    p_calls = get_default_pure_callables().union(
+        ecosystem.pure_callables, pure_callables or []
+    )
+    pure_functions = purity_collector.get_pure_functions(p_calls)
+    for pure_function in sorted(pure_functions):
+        log.debug("%s has no side-effects", pure_function)
+    p_calls.update(pure_functions)
+
+    preserve_symbols = ecosystem.preserve_symbols.union(preserve_symbols or [])
+    # entry points are loaded by name at runtime
+    preserve_symbols.update(
+        f"{module}.{attr}"
+        for module, attr in get_entry_points(ecosystem.entry_point_groups)
+        if attr
+    )
+    if preserve_exports:
+        preserve_symbols.update(get_preserved_exports(exports, preserve_exports))
+    # library mode, the public API is kept instead of only what an entry point uses
+    if library_include:
+        preserve_symbols.update(
+            get_library_symbols(
+                exports_collector.public_names,
+                exports,
+                include=library_include,
+                exclude=library_exclude,
+            )
+        )
+    if django_settings is not None:
+        # settings and apps are evaluated from the bundle, not the environment
+        bundled_module_files = {
+            module_spec: Path(file_path)
+            for file_path, module_spec in known_module_specs.items()
+            if file_path.endswith(".py") and not file_path.endswith("__main__.py")
+        }
+        preserve_symbols.update(
+            get_django_preserve_symbols(
+                evaluate_django_settings(django_settings, bundled_module_files.get)
+            )
+        )
+    # functions and classes called at runtime according to `flay trace`
+    if trace_files:
+        preserve_symbols.update(load_traces(trace_files).symbols)
     enrich_preserve_symbols_from_import_aliases(preserve_symbols, aliases)
 
-    for symbol in preserve_symbols:
+    if closed_world_packages:
+        # the bundle is the only caller of these packages, so their call sites
+        # show which defaults are never used and which parameters are never passed
+        unused_defaults_remover = UnusedDefaultsRemover(closed_world_packages)
+        for file_path in file_modules:
+            unused_defaults_remover.collect_module(
+                module_spec=known_module_specs[file_path], source_path=Path(file_path)
+            )
+        unused_defaults_remover.analyze(preserve_symbols)
+        for file_path in file_modules:
+            unused_defaults_remover.process_module(
+                module_spec=known_module_specs[file_path], source_path=Path(file_path)
+            )
+            # branches on parameters which are never passed are unreachable now
+            dead_code_remover.process_module(source_path=Path(file_path))
+        log.debug(
+            "Removed %s defaults and folded %s conditions",
+            unused_defaults_remover.defaults_removed,
+            unused_defaults_remover.conditions_folded,
+        )
+
+    exact_preserve_symbols = {
+        symbol for symbol in preserve_symbols if not is_symbol_pattern(symbol)
+    }
+    for symbol in exact_preserve_symbols:
         references_counts[symbol] = 1
-    new_references_count += len(preserve_symbols)
+    new_references_count += len(exact_preserve_symbols)
 
-    s_decs = get_default_safe_decorators()
+    s_decs = get_default_safe_decorators().union(ecosystem.safe_decorators)
     if safe_decorators:
         s_decs = s_decs.union(safe_decorators)
 
@@ -119,6 +308,24 @@ def treeshake_package(
         references_counts,
         import_aliases=aliases,
         safe_decorators=s_decs,
+        cache_dir=cache_dir,
+        cache_namespace=get_cache_namespace(
+            "treeshake",
+            aliases,
+            s_decs,
+            preserve_symbols - exact_preserve_symbols,
+            string_references,
+            set(registered_classes),
+            p_calls,
+        ),
+        exports=exports,
+        preserve_symbols=preserve_symbols,
+        string_references=string_references,
+        modules=set(known_module_specs.values()),
+        registered_classes=set(registered_classes),
+        pure_callables=p_calls,
+        # reference state of previous runs, e.g. in watch mode
+        memory_cache=memory_cache,
     )
     treeshake_iteration = 1
     # count references until no new references get added
@@ -138,12 +345,23 @@ def treeshake_package(
             known_module_specs=known_module_specs,
         )
         new_references_count = references_counter.new_references_count
+    references_counter.reset_counter()
     _process_modules(
         references_counter=references_counter,
         file_modules=file_modules,
         known_module_specs=known_module_specs,
     )
     references_counts |= references_counter.references_counts
+    for symbol, pattern in sorted(references_counter.pattern_matches.items()):
+        log.debug("%s matched pattern %s", symbol, pattern)
+    if string_references and references_counter.found_string_references:
+        event_handler.on_event(
+            TreeshakePackageStringReferencesEvent(
+                references=dict(
+                    sorted(references_counter.found_string_references.items())
+                )
+            )
+        )
 
     log.debug("Counted references: %s", references_counts)
 
@@ -157,4 +375,33 @@ def treeshake_package(
         )
         nodes_remover.process_module(module_spec=module_spec, source_path=file_path)
 
-    return nodes_remover.statements_removed
+    if verify or smoke_command:
+        # nothing outside of the bundle is available when it is imported
+        retained_modules = (
+            sorted(
+                {
+                    module_spec
+                    for file_path, module_spec in known_module_specs.items()
+                    if file_path.endswith(".py")
+                    and not file_path.endswith("__main__.py")
+                    and os.path.exists(file_path)
+                }
+            )
+            if verify
+            else []
+        )
+        event_handler.on_event(
+            TreeshakePackageVerificationEvent(
+                count=len(retained_modules), smoke_command=smoke_command
+            )
+        )
+        failures = verify_bundle(
+            Path(source_dir),
+            retained_modules,
+            nodes_remover.removed_symbols,
+            smoke_command=smoke_command,
+        )
+        if failures:
+            raise TreeshakeVerificationError(failures)
+
+    return nodes_remover.statements_removed + dead_code_remover.statements_removed
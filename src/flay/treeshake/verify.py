@@ -0,0 +1,294 @@
+from __future__ import annotations
+
+import json
+import logging
+import os
+import re
+import shlex
+import subprocess
+import sys
+import typing as t
+from pathlib import Path
+
+import typing_extensions as te
+
+from flay.common.exc import FlayError
+from flay.common.pydantic import FlayBaseModel
+
+log = logging.getLogger(__name__)
+
+# imports every module passed on stdin and reports the errors raised by missing names,
+# isolated mode ignores PYTHONPATH, so the bundle is added to the path by the script
+_IMPORT_MODULES_SCRIPT = """
+import importlib, json, sys
+
+sys.path.insert(0, sys.argv[1])
+for module_spec in sys.stdin.read().split():
+    try:
+        importlib.import_module(module_spec)
+    except (ImportError, NameError, AttributeError) as e:
+        frame_module = None
+        tb = e.__traceback__
+        while tb is not None:
+            frame_module = tb.tb_frame.f_globals.get("__name__", frame_module)
+            tb = tb.tb_next
+        print(json.dumps({
+            "module_spec": module_spec,
+            "error": f"{type(e).__name__}: {e}",
+            "frame_module": frame_module,
+        }))
+    except BaseException as e:
+        print(f"{module_spec}: {type(e).__name__}: {e}", file=sys.stderr)
+"""
+
+# runs a `python` smoke command in isolated mode with the bundle inserted into the path,
+# arguments are the bundle and the command line after the interpreter options
+_SMOKE_COMMAND_SCRIPT = """
+import runpy, sys
+
+sys.path.insert(0, sys.argv[1])
+args = sys.argv[2:]
+if args[0] == "-m":
+    sys.argv = [args[1], *args[2:]]
+    runpy.run_module(args[1], run_name="__main__", alter_sys=True)
+elif args[0] == "-c":
+    sys.argv = ["-c", *args[2:]]
+    exec(compile(args[1], "<string>", "exec"), {"__name__": "__main__"})
+else:
+    sys.argv = args
+    runpy.run_path(args[0], run_name="__main__")
+"""
+
+# interpreter options which take the next argument as their value
+_OPTIONS_WITH_VALUE = frozenset({"-W", "-X", "--check-hash-based-pycs"})
+
+_NAME_ERROR_RE = re.compile(r"NameError: name '(?P<name>[\w.]+)' is not defined")
+_ATTRIBUTE_ERROR_RE = re.compile(
+    r"AttributeError: (?:module|type object) '(?P<owner>[\w.]+)' "
+    r"has no attribute '(?P<name>\w+)'"
+)
+_IMPORT_ERROR_RE = re.compile(
+    r"ImportError: cannot import name '(?P<name>\w+)' from '(?P<owner>[\w.]+)'"
+)
+_TRACEBACK_FILE_RE = re.compile(r'^\s*File "(?P<path>[^"]+)", line \d+', re.MULTILINE)
+
+# fully qualified name -> module, line and first source line of the removed statement
+RemovedSymbols: te.TypeAlias = dict[str, tuple[str, int, str]]
+
+
+class VerificationFailure(FlayBaseModel):
+    # module whose import failed or the smoke command
+    source: str
+    error: str
+    # the removed statement which most likely caused the error, if any
+    removed_symbol: t.Optional[str] = None
+    removed_statement: t.Optional[tuple[str, int, str]] = None
+
+    def __str__(self) -> str:
+        message = f"{self.source}: {self.error}"
+        if self.removed_statement is not None:
+            module_spec, line, source = self.removed_statement
+            message += f"\n  caused by removing {module_spec}:{line}: {source}"
+        return message
+
+
+class TreeshakeVerificationError(FlayError):
+    def __init__(self, failures: list[VerificationFailure]) -> None:
+        self.failures = failures
+        super().__init__(
+            "Verification of the treeshaken bundle failed:\n"
+            + "\n".join(str(failure) for failure in failures)
+        )
+
+
+def get_missing_symbols(error: str, frame_module: str | None) -> list[str]:
+    """
+    Candidates of the fully qualified name an error message complains about,
+    e.g. `pkg.mod.name` for `cannot import name 'name' from 'pkg.mod'`
+    """
+    if match := _IMPORT_ERROR_RE.search(error) or _ATTRIBUTE_ERROR_RE.search(error):
+        return [f"{match['owner']}.{match['name']}"]
+    if match := _NAME_ERROR_RE.search(error):
+        if frame_module is not None:
+            return [f"{frame_module}.{match['name']}"]
+        return [match["name"]]
+    return []
+
+
+def find_removed_statement(
+    error: str, frame_module: str | None, removed_symbols: RemovedSymbols
+) -> tuple[str, tuple[str, int, str]] | None:
+    for symbol in get_missing_symbols(error, frame_module):
+        if symbol in removed_symbols:
+            return symbol, removed_symbols[symbol]
+        # the module of a name error is unknown, so any removed name matches
+        if "." not in symbol:
+            candidates = sorted(
+                fqn for fqn in removed_symbols if fqn.rsplit(".", 1)[-1] == symbol
+            )
+            if len(candidates) == 1:
+                return candidates[0], removed_symbols[candidates[0]]
+    return None
+
+
+def _make_failure(
+    source: str,
+    error: str,
+    frame_module: str | None,
+    removed_symbols: RemovedSymbols,
+) -> VerificationFailure:
+    removed = find_removed_statement(error, frame_module, removed_symbols)
+    return VerificationFailure(
+        source=source,
+        error=error,
+        removed_symbol=removed[0] if removed else None,
+        removed_statement=removed[1] if removed else None,
+    )
+
+
+def _get_bundle_env(bundle_dir: Path) -> dict[str, str]:
+    env = dict(os.environ)
+    # the bundle has to work without the environment it was created from
+    env["PYTHONPATH"] = str(bundle_dir.absolute())
+    env["PYTHONNOUSERSITE"] = "1"
+    env.pop("PYTHONHOME", None)
+    return env
+
+
+def verify_imports(
+    bundle_dir: Path,
+    module_specs: t.Iterable[str],
+    removed_symbols: RemovedSymbols,
+) -> list[VerificationFailure]:
+    """
+    Import every module in a subprocess with `bundle_dir` as the only path
+    besides the standard library
+    """
+    result = subprocess.run(
+        # isolated mode ignores the working directory, site-packages
+        # and the PYTHON* environment variables
+        [
+            sys.executable,
+            "-I",
+            "-S",
+            "-c",
+            _IMPORT_MODULES_SCRIPT,
+            str(bundle_dir.absolute()),
+        ],
+        input="\n".join(module_specs),
+        capture_output=True,
+        text=True,
+    )
+    if result.returncode != 0:
+        return [
+            VerificationFailure(
+                source="import",
+                error=result.stderr.strip() or f"exited with {result.returncode}",
+            )
+        ]
+    for line in result.stderr.splitlines():
+        log.debug("Ignored error on import: %s", line)
+    failures: list[VerificationFailure] = []
+    for line in result.stdout.splitlines():
+        report = json.loads(line)
+        failures.append(
+            _make_failure(
+                report["module_spec"],
+                report["error"],
+                report["frame_module"],
+                removed_symbols,
+            )
+        )
+    return failures
+
+
+def get_traceback_module(stderr: str, bundle_dir: Path) -> str | None:
+    """Module of the last frame in a traceback which belongs to the bundle"""
+    module_spec = None
+    for match in _TRACEBACK_FILE_RE.finditer(stderr):
+        path = Path(match["path"])
+        if not path.is_relative_to(bundle_dir):
+            continue
+        parts = path.relative_to(bundle_dir).with_suffix("").parts
+        if parts[-1] in ("__init__", "__main__"):
+            parts = parts[:-1]
+        module_spec = ".".join(parts)
+    return module_spec
+
+
+def get_isolated_smoke_command(bundle_dir: Path, args: list[str]) -> list[str]:
+    """
+    Command line running `python ...` arguments in isolated mode, so neither the
+    working directory, site-packages nor PYTHONPATH are importable besides the bundle
+    """
+    interpreter_options: list[str] = []
+    index = 1
+    while index < len(args) and args[index].startswith("-"):
+        if args[index] in ("-m", "-c"):
+            break
+        interpreter_options.append(args[index])
+        if args[index] in _OPTIONS_WITH_VALUE and index + 1 < len(args):
+            index += 1
+            interpreter_options.append(args[index])
+        index += 1
+    if index >= len(args):
+        raise FlayError(f"Smoke command has nothing to run: {shlex.join(args)}")
+    return [
+        sys.executable,
+        *interpreter_options,
+        "-I",
+        "-S",
+        "-c",
+        _SMOKE_COMMAND_SCRIPT,
+        str(bundle_dir.absolute()),
+        *args[index:],
+    ]
+
+
+def run_smoke_command(
+    bundle_dir: Path, smoke_command: str, removed_symbols: RemovedSymbols
+) -> VerificationFailure | None:
+    """
+    Run a command like `python -m myapp --help` against the bundle,
+    `python` is the interpreter running flay and runs as isolated as the import
+    verification. Other commands only get the bundle in PYTHONPATH.
+    """
+    args = shlex.split(smoke_command)
+    if args and args[0] == "python":
+        args = get_isolated_smoke_command(bundle_dir, args)
+    result = subprocess.run(
+        args, capture_output=True, text=True, env=_get_bundle_env(bundle_dir)
+    )
+    if result.returncode == 0:
+        return None
+    lines = result.stderr.strip().splitlines()
+    error = lines[-1] if lines else f"exited with {result.returncode}"
+    return _make_failure(
+        smoke_command,
+        error,
+        get_traceback_module(result.stderr, bundle_dir.absolute()),
+        removed_symbols,
+    )
+
+
+def verify_bundle(
+    bundle_dir: Path,
+    module_specs: list[str],
+    removed_symbols: RemovedSymbols,
+    smoke_command: str | None = None,
+) -> list[VerificationFailure]:
+    failures: list[VerificationFailure] = []
+    if module_specs:
+        failures.extend(verify_imports(bundle_dir, module_specs, removed_symbols))
+    if smoke_command:
+        failure = run_smoke_command(bundle_dir, smoke_command, removed_symbols)
+        if failure is not None:
+            failures.append(failure)
+    return failures
+
+
+__all__ = [
+    "TreeshakeVerificationError",
+    "VerificationFailure",
+    "verify_bundle",
+]
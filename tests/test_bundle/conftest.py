@@ -16,6 +16,9 @@ class RunBundlePackageT(t.Protocol):
         module_spec: str,
         resources: dict[str, str] | None = None,
         import_aliases: dict[str, str] | None = None,
+        vendor_stdlib: bool = False,
+        string_references: bool = False,
+        trace_files: list[Path] | None = None,
     ) -> tuple[Path, Path]: ...
 
 
@@ -29,6 +32,9 @@ def run_bundle_package(tmp_path: Path) -> RunBundlePackageT:
         module_spec: str,
         resources: dict[str, str] | None = None,
         import_aliases: dict[str, str] | None = None,
+        vendor_stdlib: bool = False,
+        string_references: bool = False,
+        trace_files: list[Path] | None = None,
     ) -> tuple[Path, Path]:
         pre_bundle_path = tmp_path / "pre_bundle"
         bundled_path = tmp_path / "bundled"
@@ -61,6 +67,9 @@ def run_bundle_package(tmp_path: Path) -> RunBundlePackageT:
                 resources=resources or {},
                 bundle_metadata=False,
                 import_aliases=import_aliases,
+                vendor_stdlib=vendor_stdlib,
+                string_references=string_references,
+                trace_files=trace_files,
             )
         finally:
             if build_before:
@@ -0,0 +1,8 @@
+LOGGING = {
+    "version": 1,
+    "handlers": {
+        "console": {"class": "string_references.plugins.handlers.ConsoleHandler"},
+    },
+}
+WORKER = "string_references.plugins.workers:run_worker"
+MISSING = "string_references.plugins.missing.Handler"
@@ -0,0 +1,5 @@
+import logging
+
+
+class ConsoleHandler(logging.StreamHandler):
+    pass
@@ -0,0 +1,2 @@
+def useless() -> None:
+    pass
@@ -5,8 +5,26 @@ import os
 import pytest
 import sys
 from flay.common.exc import FlayFileNotFoundError
-from flay.bundle.package import bundle_package
+from flay.bundle.package import bundle_package, create_file_collector
+from flay.bundle.watch import (
+    WatchBundleEvent,
+    WatchBundleFailedEvent,
+    sync_directory,
+    watch_bundle,
+)
+from flay.common.events import EventHandler
+from flay._flay_rs import FileCollector
+from flay.bundle.zipapp import bundle_zipapp, ZIPAPP_NATIVE_PREFIX
+from flay.bundle.bytecode import compile_bundle
+from flay.bundle.shared_libraries import SHARED_LIBRARIES_DIR, bundle_shared_libraries
+from flay.ecosystem.plugins import load_ecosystem
+from flay.trace import Trace, write_trace
+import importlib.util
 import ast
+import subprocess
+import zipfile
+import threading
+import time
 from importlib.metadata import Distribution, requires
 from packaging.requirements import Requirement
 
@@ -151,6 +169,18 @@ def test_bundle_package_dynamic_imports(
     assert (result_path / "../clonf/integrations/click/__init__.py").exists()
 
 
+def test_bundle_package_trace_files(
+    run_bundle_package: RunBundlePackageT, tmp_path_factory: pytest.TempPathFactory
+) -> None:
+    trace_file = tmp_path_factory.mktemp("traces") / "trace.json"
+    write_trace(Trace(modules=["dynamic_imports.sub_module.useless"]), trace_file)
+    _, result_path = run_bundle_package(
+        "dynamic_imports", "dynamic_imports", trace_files=[trace_file]
+    )
+
+    assert (result_path / "sub_module/useless.py").exists()
+
+
 def test_bundle_package_import_aliases(
     run_bundle_package: RunBundlePackageT,
 ) -> None:
@@ -169,6 +199,19 @@ def test_bundle_package_import_aliases(
     assert not (result_path / "sub/useless.py").exists()
 
 
+def test_bundle_package_string_references(
+    run_bundle_package: RunBundlePackageT,
+) -> None:
+    _, result_path = run_bundle_package(
+        "string_references", "string_references", string_references=True
+    )
+
+    # modules of the package itself are always bundled, the plugins only by strings
+    assert (result_path / "plugins/handlers.py").exists()
+    assert (result_path / "plugins/workers.py").exists()
+    assert not (result_path / "plugins/useless.py").exists()
+
+
 def test_bundle_package_bundle_metadata(tmp_path: Path) -> None:
     bundle_package("flay", tmp_path, bundle_metadata=True)
     root_dist = Distribution.from_name("flay")
@@ -197,3 +240,357 @@ def test_bundle_package_so_libs(tmp_path: Path, name: str) -> None:
 def test_bundle_package_so_libs_external(tmp_path: Path) -> None:
     bundle_package("flay", tmp_path)
     assert (tmp_path / "pydantic_core.libs").exists(), os.listdir(tmp_path)
+
+
+def test_bundle_zipapp(run_bundle_package: RunBundlePackageT, tmp_path: Path) -> None:
+    _, result_path = run_bundle_package("hello_world", "hello_world")
+    zipapp_path = bundle_zipapp(
+        result_path.parent, tmp_path / "hello_world", "hello_world.cli"
+    )
+    assert zipapp_path == tmp_path / "hello_world.pyz"
+
+    with zipfile.ZipFile(zipapp_path) as zip_file:
+        names = zip_file.namelist()
+    assert names[0] == "__main__.py"
+    assert "hello_world/cli.py" in names
+    assert ".gitignore" not in names
+
+    result = subprocess.run(
+        [sys.executable, str(zipapp_path)], capture_output=True, text=True
+    )
+    assert result.returncode == 0
+    assert "Hello world!" in result.stdout
+
+    # the archive is written deterministically
+    first_content = zipapp_path.read_bytes()
+    bundle_zipapp(result_path.parent, zipapp_path, "hello_world.cli")
+    assert zipapp_path.read_bytes() == first_content
+
+
+def test_bundle_zipapp_c_extension(
+    run_bundle_package: RunBundlePackageT, tmp_path: Path
+) -> None:
+    _, result_path = run_bundle_package("fibunacci", "fibunacci")
+    zipapp_path = bundle_zipapp(
+        result_path.parent,
+        tmp_path / "fibunacci.pyz",
+        "fibunacci",
+        interpreter="/usr/bin/env python3",
+    )
+    assert zipapp_path.read_bytes().startswith(b"#!/usr/bin/env python3\n")
+
+    with zipfile.ZipFile(zipapp_path) as zip_file:
+        names = zip_file.namelist()
+    assert f"{ZIPAPP_NATIVE_PREFIX}fibunacci/__init__.py" in names
+    assert "fibunacci/__init__.py" not in names
+
+
+def test_bundle_compile_bytecode_pycache(
+    run_bundle_package: RunBundlePackageT,
+) -> None:
+    _, result_path = run_bundle_package("hello_world", "hello_world")
+    assert compile_bundle(result_path, "pycache", "checked-hash") == 3
+
+    source_path = result_path / "hello_world.py"
+    assert source_path.exists()
+    pyc_path = Path(importlib.util.cache_from_source(str(source_path)))
+    assert pyc_path.exists()
+    # flags field of the pyc header: hash based and checked
+    assert int.from_bytes(pyc_path.read_bytes()[4:8], "little") == 0b11
+
+
+def test_bundle_compile_bytecode_sourceless(
+    run_bundle_package: RunBundlePackageT,
+) -> None:
+    _, result_path = run_bundle_package("hello_world", "hello_world")
+    compile_bundle(result_path, "sourceless", "unchecked-hash")
+
+    assert not list(result_path.rglob("*.py"))
+    pyc_path = result_path / "hello_world.pyc"
+    assert pyc_path.exists()
+    assert int.from_bytes(pyc_path.read_bytes()[4:8], "little") == 0b01
+
+    result = subprocess.run(
+        [sys.executable, "-m", "hello_world.cli"],
+        capture_output=True,
+        text=True,
+        cwd=str(result_path.parent),
+    )
+    assert result.returncode == 0
+    assert "Hello world!" in result.stdout
+
+
+def test_bundle_vendor_stdlib(run_bundle_package: RunBundlePackageT) -> None:
+    _, result_path = run_bundle_package(
+        "hello_world", "hello_world", vendor_stdlib=True
+    )
+    bundle_root = result_path.parent
+
+    assert (bundle_root / "typing.py").exists()
+    assert (bundle_root / "encodings" / "__init__.py").exists()
+    assert (bundle_root / "encodings" / "utf_8.py").exists()
+    # not reachable from hello_world, tkinter is through imports in functions,
+    # e.g. pickle imports doctest for its tests
+    assert not (bundle_root / "idlelib").exists()
+    assert not (bundle_root / "turtle.py").exists()
+    assert not (bundle_root / "encodings" / "cp1252.py").exists()
+
+    assert (bundle_root / "python._pth").read_text() == ".\n"
+    pyvenv_cfg_content = (bundle_root / "pyvenv.cfg").read_text()
+    assert "include-system-site-packages = false" in pyvenv_cfg_content
+
+
+# the interpreter of the tests already started with its own standard library,
+# every module imported afterwards has to come from the path config of the bundle
+BARE_INTERPRETER_SCRIPT = """
+import os, sys
+
+bundle_root = sys.argv[1]
+with open(os.path.join(bundle_root, "python._pth")) as f:
+    sys.path[:] = [os.path.join(bundle_root, line.strip()) for line in f]
+import typing
+import hello_world.cli
+
+hello_world.cli.main()
+print(typing.__file__)
+"""
+
+
+def test_bundle_vendor_stdlib_bare_interpreter(
+    run_bundle_package: RunBundlePackageT,
+) -> None:
+    _, result_path = run_bundle_package(
+        "hello_world", "hello_world", vendor_stdlib=True
+    )
+    bundle_root = result_path.parent
+
+    result = subprocess.run(
+        [sys.executable, "-I", "-S", "-c", BARE_INTERPRETER_SCRIPT, str(bundle_root)],
+        capture_output=True,
+        text=True,
+        cwd=str(bundle_root),
+    )
+    assert result.returncode == 0, result.stderr
+    assert "Hello world!" in result.stdout
+    typing_file = Path(result.stdout.splitlines()[-1])
+    assert typing_file.is_relative_to(bundle_root)
+
+
+@pytest.mark.skipif(
+    not sys.platform.startswith("linux"),
+    reason="Shared libraries are only collected for ELF objects",
+)
+def test_bundle_shared_libraries(run_bundle_package: RunBundlePackageT) -> None:
+    _, result_path = run_bundle_package("fibunacci", "fibunacci")
+    bundle_root = result_path.parent
+
+    # glibc is provided by the base image and has to match its dynamic loader
+    missing_libraries = bundle_shared_libraries(bundle_root)
+    assert not any(name.startswith("libc.") for name in missing_libraries)
+
+    missing_libraries = bundle_shared_libraries(bundle_root, sysroot=Path("/"))
+    assert not any(name.startswith("libc.") for name in missing_libraries)
+    assert not list(bundle_root.glob(f"{SHARED_LIBRARIES_DIR}/libc.*"))
+    assert not list(bundle_root.glob(f"{SHARED_LIBRARIES_DIR}/ld-linux*"))
+
+
+def test_bundle_package_cache(tmp_path: Path) -> None:
+    cache_dir = tmp_path / "cache"
+    results: list[list[str]] = []
+    for run in ("first", "second"):
+        target_path = tmp_path / run
+        bundle_package("rich", target_path, bundle_metadata=False, cache_dir=cache_dir)
+        results.append(
+            sorted(
+                str(path.relative_to(target_path)) for path in target_path.rglob("*")
+            )
+        )
+
+    assert list(cache_dir.iterdir())
+    assert results[0] == results[1]
+
+
+def test_sync_directory(tmp_path: Path) -> None:
+    source_path = tmp_path / "source"
+    target_path = tmp_path / "target"
+    (source_path / "pkg").mkdir(parents=True)
+    (source_path / "pkg" / "a.py").write_text("a = 1")
+    (source_path / "pkg" / "b.py").write_text("b = 1")
+    assert sync_directory(source_path, target_path) == (2, 0)
+    assert sync_directory(source_path, target_path) == (0, 0)
+
+    (source_path / "pkg" / "a.py").unlink()
+    (source_path / "pkg" / "b.py").write_text("b = 2")
+    (target_path / "stale").mkdir()
+    (target_path / "stale" / "c.py").write_text("c = 1")
+    assert sync_directory(source_path, target_path) == (1, 2)
+    assert (target_path / "pkg" / "b.py").read_text() == "b = 2"
+    assert not (target_path / "pkg" / "a.py").exists()
+    assert not (target_path / "stale").exists()
+
+
+@pytest.mark.skipif(
+    not sys.platform.startswith("linux"),
+    reason="Watch mode is only tested with inotify",
+)
+def test_watch_bundle(tmp_path: Path, monkeypatch: pytest.MonkeyPatch) -> None:
+    package_path = tmp_path / "src" / "watched"
+    (package_path / "sub").mkdir(parents=True)
+    (package_path / "__init__.py").write_text("from .sub.a import value\n")
+    (package_path / "sub" / "__init__.py").write_text("")
+    (package_path / "sub" / "a.py").write_text("value = 1\n")
+    output_path = tmp_path / "output"
+    monkeypatch.chdir(tmp_path)
+    monkeypatch.syspath_prepend(str(tmp_path / "src"))
+
+    def build(collector: FileCollector, bundle_path: Path) -> None:
+        bundle_package(
+            "watched", bundle_path, bundle_metadata=False, collector=collector
+        )
+
+    thread = threading.Thread(
+        target=watch_bundle,
+        args=("watched", output_path, create_file_collector("watched"), build),
+        kwargs={"max_rebuilds": 1},
+    )
+    thread.start()
+    deadline = time.monotonic() + 30
+    while not (output_path / "watched" / "sub" / "a.py").exists():
+        assert time.monotonic() < deadline
+        time.sleep(0.05)
+
+    (package_path / "sub" / "b.py").write_text("other = 2\n")
+    (package_path / "sub" / "a.py").write_text("from .b import other\nvalue = other\n")
+    thread.join(timeout=30)
+    assert not thread.is_alive()
+    result_path = output_path / "watched" / "sub"
+    assert (result_path / "b.py").read_text() == "other = 2\n"
+    assert "from .b import other" in (result_path / "a.py").read_text()
+
+
+
+@pytest.mark.skipif(
+    not sys.platform.startswith("linux"),
+    reason="Watch mode is only tested with inotify",
+)
+def test_watch_bundle_syntax_error(
+    tmp_path: Path, monkeypatch: pytest.MonkeyPatch
+) -> None:
+    package_path = tmp_path / "src" / "watched_broken"
+    package_path.mkdir(parents=True)
+    (package_path / "__init__.py").write_text("from .a import value\n")
+    (package_path / "a.py").write_text("value = 1\n")
+    output_path = tmp_path / "output"
+    monkeypatch.chdir(tmp_path)
+    monkeypatch.syspath_prepend(str(tmp_path / "src"))
+
+    events: list[WatchBundleEvent] = []
+
+    class RecordingEventHandler(EventHandler[WatchBundleEvent]):
+        def on_event(self, event: WatchBundleEvent) -> None:
+            events.append(event)
+
+    def build(collector: FileCollector, bundle_path: Path) -> None:
+        bundle_package(
+            "watched_broken", bundle_path, bundle_metadata=False, collector=collector
+        )
+
+    thread = threading.Thread(
+        target=watch_bundle,
+        args=("watched_broken", output_path, create_file_collector("watched_broken")),
+        kwargs={
+            "build": build,
+            "max_rebuilds": 2,
+            "event_handler": RecordingEventHandler(),
+        },
+    )
+    thread.start()
+    deadline = time.monotonic() + 30
+    while not (output_path / "watched_broken" / "a.py").exists():
+        assert time.monotonic() < deadline
+        time.sleep(0.05)
+
+    (package_path / "a.py").write_text("value = (\n")
+    while not any(isinstance(event, WatchBundleFailedEvent) for event in events):
+        assert thread.is_alive()
+        assert time.monotonic() < deadline
+        time.sleep(0.05)
+    # the previous output is kept and the next change is picked up again
+    assert (output_path / "watched_broken" / "a.py").read_text() == "value = 1\n"
+
+    (package_path / "b.py").write_text("other = 2\n")
+    (package_path / "a.py").write_text("from .b import other\nvalue = other\n")
+    thread.join(timeout=30)
+    assert not thread.is_alive()
+    result_path = output_path / "watched_broken"
+    assert (result_path / "b.py").read_text() == "other = 2\n"
+    assert "from .b import other" in (result_path / "a.py").read_text()
+
+
+@pytest.mark.skipif(
+    not sys.platform.startswith("linux"),
+    reason="Watch mode is only tested with inotify",
+)
+def test_watch_bundle_removed_import(
+    tmp_path: Path, monkeypatch: pytest.MonkeyPatch
+) -> None:
+    package_path = tmp_path / "src" / "watched_imports"
+    package_path.mkdir(parents=True)
+    (package_path / "__init__.py").write_text("import watched_helper\n")
+    # outside of the watched package, only bundled because it is imported
+    (tmp_path / "src" / "watched_helper.py").write_text("value = 1\n")
+    output_path = tmp_path / "output"
+    monkeypatch.chdir(tmp_path)
+    monkeypatch.syspath_prepend(str(tmp_path / "src"))
+
+    def build(collector: FileCollector, bundle_path: Path) -> None:
+        bundle_package(
+            "watched_imports", bundle_path, bundle_metadata=False, collector=collector
+        )
+
+    collector = create_file_collector("watched_imports")
+    thread = threading.Thread(
+        target=watch_bundle,
+        args=("watched_imports", output_path, collector, build),
+        kwargs={"max_rebuilds": 1},
+    )
+    thread.start()
+    deadline = time.monotonic() + 30
+    while not (output_path / "watched_helper.py").exists():
+        assert time.monotonic() < deadline
+        time.sleep(0.05)
+
+    (package_path / "__init__.py").write_text("value = 2\n")
+    thread.join(timeout=30)
+    assert not thread.is_alive()
+    assert not (output_path / "watched_helper.py").exists()
+    assert (output_path / "watched_imports" / "__init__.py").read_text() == (
+        "value = 2\n"
+    )
+    assert not any(
+        module_spec == "watched_helper"
+        for module_spec, _ in collector.collected_files
+    )
+
+
+def test_load_ecosystem(tmp_path: Path) -> None:
+    (tmp_path / "rich.toml").write_text(
+        'distribution = "rich"\n'
+        'safe-decorators = ["rich.repr.auto"]\n'
+        'entry-point-groups = ["rich.plugins"]\n'
+        "[import-aliases]\n"
+        '"rich.Console" = "rich.console.Console"\n'
+    )
+    (tmp_path / "not_bundled.toml").write_text(
+        'distribution = "not-bundled"\nsafe-decorators = ["not_bundled.decorator"]\n'
+    )
+    ecosystem = load_ecosystem(["Rich", "pydantic"], [tmp_path])
+
+    assert ecosystem.import_aliases["rich.Console"] == "rich.console.Console"
+    assert "rich.repr.auto" in ecosystem.safe_decorators
+    assert "not_bundled.decorator" not in ecosystem.safe_decorators
+    assert ecosystem.entry_point_groups == {"rich.plugins"}
+    # shipped plugin, resolved from the lazy imports of pydantic
+    assert ecosystem.import_aliases["pydantic.BaseModel"] == "pydantic.main.BaseModel"
+    assert "pydantic.main.BaseModel" in ecosystem.preserve_symbols
+    assert "pydantic.validator" in ecosystem.safe_decorators
@@ -0,0 +1,13 @@
+import importlib
+
+from traced_app.core import Service, helper
+
+
+def run() -> None:
+    plugin = importlib.import_module(f"traced_app.{'plugin'}")
+    getattr(plugin, "load")()
+    Service().start()
+    helper()
+
+
+run()
@@ -0,0 +1,23 @@
+class Service:
+    class Config:
+        @staticmethod
+        def defaults() -> None:
+            pass
+
+    def start(self) -> None:
+        def inner() -> None:
+            pass
+
+        inner()
+        self.Config.defaults()
+
+    def stop(self) -> None:
+        pass
+
+
+def helper() -> None:
+    pass
+
+
+def unused() -> None:
+    pass
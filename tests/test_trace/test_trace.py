@@ -0,0 +1,98 @@
+from __future__ import annotations
+from pathlib import Path
+import shutil
+import sys
+import pytest
+from flay.trace import _runner
+from flay.trace import (
+    FlayTraceError,
+    Trace,
+    get_runner_args,
+    load_traces,
+    trace_command,
+    write_trace,
+)
+
+TEST_PACKAGES_DIR = Path(__file__).parent / "packages"
+
+
+@pytest.fixture
+def traced_app_dir(tmp_path: Path, monkeypatch: pytest.MonkeyPatch) -> Path:
+    shutil.copytree(
+        str(TEST_PACKAGES_DIR / "traced_app"), str(tmp_path / "traced_app")
+    )
+    monkeypatch.chdir(tmp_path)
+    return tmp_path
+
+
+def test_trace_command(traced_app_dir: Path) -> None:
+    trace, returncode = trace_command(["python", "-m", "traced_app"])
+
+    assert returncode == 0
+    assert {"traced_app", "traced_app.core", "traced_app.plugin"} <= set(
+        trace.modules
+    )
+    assert {
+        # __main__ of a package is bundled as the package
+        "traced_app.run",
+        "traced_app.core.Service",
+        "traced_app.core.Service.start",
+        "traced_app.core.Service.Config.defaults",
+        "traced_app.core.helper",
+        "traced_app.plugin.load",
+    } <= set(trace.symbols)
+    assert "traced_app.core.unused" not in trace.symbols
+    assert "traced_app.core.Service.stop" not in trace.symbols
+    assert not any("<" in symbol for symbol in trace.symbols)
+
+
+def test_get_qualnames() -> None:
+    qualnames = _runner._get_qualnames(
+        str(TEST_PACKAGES_DIR / "traced_app" / "core.py")
+    )
+
+    assert qualnames[(1, "Service")] == "Service"
+    assert qualnames[(2, "Config")] == "Service.Config"
+    assert qualnames[(3, "defaults")] == "Service.Config.defaults"
+    assert qualnames[(7, "start")] == "Service.start"
+    assert qualnames[(8, "inner")] == "Service.start.<locals>.inner"
+    assert qualnames[(18, "helper")] == "helper"
+
+
+def test_trace_command_code(traced_app_dir: Path) -> None:
+    trace, returncode = trace_command(
+        [sys.executable, "-c", "import traced_app.core; traced_app.core.helper()"]
+    )
+
+    assert returncode == 0
+    assert "traced_app.core.helper" in trace.symbols
+    assert "traced_app.plugin" not in trace.modules
+
+
+def test_trace_command_exit_code(traced_app_dir: Path) -> None:
+    _, returncode = trace_command(["python", "-c", "raise SystemExit(3)"])
+
+    assert returncode == 3
+
+
+def test_get_runner_args() -> None:
+    assert get_runner_args(["python3", "-m", "pytest"]) == ["-m", "pytest"]
+    assert get_runner_args(["app.py", "--help"]) == ["app.py", "--help"]
+    with pytest.raises(FlayTraceError):
+        get_runner_args(["python"])
+    with pytest.raises(FlayTraceError):
+        get_runner_args(["flay-command-which-does-not-exist"])
+
+
+def test_write_trace_append(tmp_path: Path) -> None:
+    trace_file = tmp_path / "trace.json"
+    write_trace(Trace(modules=["a"], symbols=["a.f"]), trace_file)
+    write_trace(Trace(modules=["b"], symbols=["b.g"]), trace_file, append=True)
+
+    assert load_traces([trace_file]) == Trace(
+        modules=["a", "b"], symbols=["a.f", "b.g"]
+    )
+
+    write_trace(Trace(modules=["c"]), trace_file)
+
+    assert load_traces([trace_file]) == Trace(modules=["c"])
@@ -13,6 +13,18 @@ class RunTreeshakePackageT(t.Protocol):
         preserve_symbols: set[str] | None = None,
         import_aliases: dict[str, str] | None = None,
         safe_decorators: set[str] | None = None,
+        preserve_exports: list[str] | None = None,
+        library_include: list[str] | None = None,
+        library_exclude: list[str] | None = None,
+        django_settings: str | None = None,
+        string_references: bool = False,
+        non_registering_bases: set[str] | None = None,
+        pure_callables: set[str] | None = None,
+        strip_debug: bool = False,
+        closed_world_packages: list[str] | None = None,
+        verify: bool = False,
+        smoke_command: str | None = None,
+        trace_files: list[Path] | None = None,
     ) -> Path: ...
 
 
@@ -23,15 +35,47 @@ def run_treeshake_package(tmp_path: Path) -> RunTreeshakePackageT:
         preserve_symbols: set[str] | None = None,
         import_aliases: dict[str, str] | None = None,
         safe_decorators: set[str] | None = None,
+        preserve_exports: list[str] | None = None,
+        library_include: list[str] | None = None,
+        library_exclude: list[str] | None = None,
+        django_settings: str | None = None,
+        string_references: bool = False,
+        non_registering_bases: set[str] | None = None,
+        pure_callables: set[str] | None = None,
+        strip_debug: bool = False,
+        closed_world_packages: list[str] | None = None,
+        verify: bool = False,
+        smoke_command: str | None = None,
+        trace_files: list[Path] | None = None,
     ) -> Path:
         assert path.is_dir(), "Must specifiy a directory!"
         target_path = tmp_path / path.name
-        shutil.copytree(str(path), str(target_path))
+        # snapshots of the expected output (see test_snapshots.py) are not part
+        # of the fixture package
+        shutil.copytree(
+            str(path),
+            str(target_path),
+            ignore=lambda directory, names: (
+                ["expected"] if Path(directory) == path else []
+            ),
+        )
         treeshake_package(
             str(tmp_path),
             preserve_symbols=preserve_symbols,
             import_aliases=import_aliases,
             safe_decorators=safe_decorators,
+            preserve_exports=preserve_exports,
+            library_include=library_include,
+            library_exclude=library_exclude,
+            django_settings=django_settings,
+            string_references=string_references,
+            non_registering_bases=non_registering_bases,
+            pure_callables=pure_callables,
+            strip_debug=strip_debug,
+            closed_world_packages=closed_world_packages,
+            verify=verify,
+            smoke_command=smoke_command,
+            trace_files=trace_files,
         )
         return target_path
 
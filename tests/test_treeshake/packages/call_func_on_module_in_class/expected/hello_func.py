@@ -0,0 +1,4 @@
+def the_hello_func(who: str) -> str:
+    return f"Hello {who}!"
+
+
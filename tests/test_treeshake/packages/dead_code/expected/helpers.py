@@ -0,0 +1,16 @@
+def debug_only() -> None:
+    pass
+
+
+def checked() -> bool:
+    return True
+
+
+def kept_by_yield() -> int:
+    return 0
+
+
+def kept_by_binding() -> int:
+    return 0
+
+
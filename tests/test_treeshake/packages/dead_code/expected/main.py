@@ -0,0 +1,34 @@
+import json
+import typing as t
+from dead_code.helpers import checked, debug_only, kept_by_binding, kept_by_yield
+
+
+def load(text: str) -> dict:
+    return json.loads(text)
+
+
+def find(items: list[str]) -> str:
+    for item in items:
+        if item:
+            return item
+    raise LookupError("nothing found")
+
+
+def empty() -> t.Iterator[int]:
+    return
+    yield kept_by_yield()
+
+
+def count(items: list[str]) -> int:
+    total = len(items)
+    return total
+    total = kept_by_binding()
+
+
+def main() -> None:
+    if __debug__:
+        debug_only()
+    print(load("{}"), find(["a"]), list(empty()), count(["a"]))
+    assert checked()
+
+
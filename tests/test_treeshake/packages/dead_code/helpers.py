@@ -0,0 +1,22 @@
+def legacy(text: str) -> dict:
+    return {"text": text}
+
+
+def unused_branch() -> None:
+    pass
+
+
+def debug_only() -> None:
+    pass
+
+
+def checked() -> bool:
+    return True
+
+
+def kept_by_yield() -> int:
+    return 0
+
+
+def kept_by_binding() -> int:
+    return 0
@@ -0,0 +1,48 @@
+import json
+import shutil
+import typing as t
+
+from dead_code.helpers import (
+    checked,
+    debug_only,
+    kept_by_binding,
+    kept_by_yield,
+    legacy,
+    unused_branch,
+)
+
+
+def load(text: str) -> dict:
+    return json.loads(text)
+    return legacy(text)
+
+
+def find(items: list[str]) -> str:
+    for item in items:
+        if item:
+            return item
+            unused_branch()
+    raise LookupError("nothing found")
+    shutil.rmtree("build")
+
+
+def empty() -> t.Iterator[int]:
+    return
+    yield kept_by_yield()
+
+
+def count(items: list[str]) -> int:
+    total = len(items)
+    return total
+    total = kept_by_binding()
+
+
+def main() -> None:
+    if 0:
+        unused_branch()
+    elif __debug__:
+        debug_only()
+    while False:
+        unused_branch()
+    print(load("{}"), find(["a"]), list(empty()), count(["a"]))
+    assert checked()
@@ -0,0 +1,5 @@
+SECRET_KEY = "not-a-secret"
+
+
+def get_database_name() -> str:
+    return "db.sqlite3"
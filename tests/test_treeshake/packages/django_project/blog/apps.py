@@ -0,0 +1,2 @@
+class BlogConfig:
+    name = "django_project.blog"
@@ -0,0 +1,6 @@
+class TimingMiddleware:
+    pass
+
+
+class UnusedMiddleware:
+    pass
@@ -0,0 +1,5 @@
+from django_project.base_settings import *
+
+INSTALLED_APPS = ["django_project.blog.apps.BlogConfig"]
+MIDDLEWARE = ["django_project.middleware.TimingMiddleware"]
+ROOT_URLCONF = "django_project.urls"
@@ -0,0 +1,18 @@
+from .api import *  # noqa: F403
+from .private import *  # noqa: F403
+
+
+def helper() -> str:
+    return "main helper"
+
+
+def _private() -> str:
+    return "main private"
+
+
+def main() -> None:
+    print(public(), extra(), shared(), visible(), helper(), _private())  # noqa: F405
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,17 @@
+from . import base
+from .base import *  # noqa: F403
+
+__all__ = ["public"] + base.__all__
+__all__ += ["extra"]
+
+
+def public() -> str:
+    return "public"
+
+
+def extra() -> str:
+    return "extra"
+
+
+def helper() -> str:
+    return "api helper"
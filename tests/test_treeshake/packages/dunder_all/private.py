@@ -0,0 +1,6 @@
+def visible() -> str:
+    return "visible"
+
+
+def _private() -> str:
+    return "private"
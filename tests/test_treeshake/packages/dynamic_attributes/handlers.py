@@ -0,0 +1,18 @@
+def on_create() -> None:
+    pass
+
+
+def on_delete() -> None:
+    pass
+
+
+def on_update() -> None:
+    pass
+
+
+def on_rename() -> None:
+    pass
+
+
+def unused() -> None:
+    pass
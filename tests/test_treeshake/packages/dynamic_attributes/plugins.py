@@ -0,0 +1,6 @@
+def alpha() -> None:
+    pass
+
+
+def beta() -> None:
+    pass
@@ -0,0 +1,15 @@
+import operator
+
+from dynamic_attributes import handlers, plugins
+
+
+def dispatch() -> None:
+    getattr(handlers, "on_create")()
+    if hasattr(handlers, "on_delete"):
+        pass
+    operator.attrgetter("on_update")(handlers)()
+    vars(handlers)["on_rename"]()
+
+
+def load_plugin(name: str) -> object:
+    return getattr(plugins, name)
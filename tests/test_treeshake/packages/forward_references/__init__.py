@@ -0,0 +1,12 @@
+from __future__ import annotations
+import typing as t
+from .models import CastTarget, Model, identity
+
+
+def main() -> None:
+    print(t.get_type_hints(Model))
+    print(t.cast("CastTarget", identity(None)))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,32 @@
+from __future__ import annotations
+import typing as t
+from dataclasses import dataclass
+
+
+class Item:
+    pass
+
+
+class CastTarget:
+    pass
+
+
+class Bound:
+    pass
+
+
+class Unused:
+    pass
+
+
+T = t.TypeVar("T", bound="Bound")
+
+
+@dataclass
+class Model:
+    items: "list[Item]"
+    kind: t.Literal["Unused"] = "Unused"
+
+
+def identity(value: T) -> T:
+    return value
@@ -0,0 +1,38 @@
+counter = 0
+unused_counter = 0
+value = "module"
+
+
+def increase() -> None:
+    global counter
+    counter += 1
+
+
+def make_step():
+    count = 0
+
+    def step() -> int:
+        nonlocal count
+        count += 1
+        return count
+
+    return step
+
+
+def outer() -> str:
+    value = "outer"
+
+    def inner() -> str:
+        global value
+        return value
+
+    return inner() + value
+
+
+def main() -> None:
+    increase()
+    print(make_step()(), outer())
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,3 @@
+from .bundle1 import func1
+if __name__ == "__main__":
+    func1()
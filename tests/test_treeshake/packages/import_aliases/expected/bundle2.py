@@ -0,0 +1,4 @@
+def func1() -> None:
+    pass
+
+
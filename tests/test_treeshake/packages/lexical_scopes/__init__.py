@@ -0,0 +1,27 @@
+def value() -> int:
+    return 1
+
+
+def helper() -> int:
+    return 2
+
+
+def error() -> None:
+    pass
+
+
+def used() -> int:
+    return 3
+
+
+def main() -> None:
+    print([value for value in range(3)])
+    print((lambda helper: helper)(used()))
+    try:
+        raise ValueError("Hello world!")
+    except ValueError as error:
+        print(error)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,15 @@
+def used() -> int:
+    return 3
+
+
+def main() -> None:
+    print([value for value in range(3)])
+    print((lambda helper: helper)(used()))
+    try:
+        raise ValueError("Hello world!")
+    except ValueError as error:
+        print(error)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,4 @@
+from .client import Client
+from ._internal import internal_helper as _internal_helper
+
+_internal_helper()
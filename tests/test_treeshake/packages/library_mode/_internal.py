@@ -0,0 +1,6 @@
+def internal_helper() -> None:
+    print("setup")
+
+
+def internal_unused() -> None:
+    print("unused")
@@ -0,0 +1,15 @@
+def _connect() -> str:
+    return "connected"
+
+
+def _unused_private() -> str:
+    return "unused"
+
+
+class Client:
+    def connect(self) -> str:
+        return _connect()
+
+
+def create_client() -> Client:
+    return Client()
@@ -0,0 +1,2 @@
+def create_fake_client() -> str:
+    return "fake"
@@ -0,0 +1,9 @@
+__all__ = ["exported"]
+
+
+def exported() -> str:
+    return "exported"
+
+
+def internal() -> str:
+    return "internal"
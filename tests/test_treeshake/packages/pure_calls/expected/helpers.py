@@ -0,0 +1,24 @@
+import sys
+REGISTRY: list[type] = []
+
+
+def shout(text: str) -> str:
+    return text.upper()
+
+
+def register(cls: type) -> type:
+    REGISTRY.append(cls)
+    return cls
+
+
+def check_platform() -> bool:
+    if sys.platform == "unsupported":
+        raise RuntimeError("unsupported platform")
+    return True
+
+
+def check_version() -> bool:
+    assert sys.version_info >= (3, 9)
+    return True
+
+
@@ -0,0 +1,12 @@
+from pure_calls.helpers import check_platform, check_version, register, shout
+from pure_calls.models import Small, Tiny
+SHOUTED = shout("tiny")
+registered = register(Small)
+_platform_checked = check_platform()
+_version_checked = check_version()
+
+
+def main() -> None:
+    print(Tiny)
+
+
@@ -0,0 +1,8 @@
+class Small:
+    pass
+
+
+class Tiny:
+    pass
+
+
@@ -0,0 +1,29 @@
+import logging
+import re
+import typing as t
+
+from pure_calls.helpers import (
+    check_platform,
+    check_version,
+    make_label,
+    register,
+    shout,
+)
+from pure_calls.models import Big, Small, Tiny
+
+logger = logging.getLogger(__name__)
+
+T = t.TypeVar("T", bound=Big)
+
+PATTERN = re.compile(make_label("pattern"))
+
+SHOUTED = shout("tiny")
+
+registered = register(Small)
+
+_platform_checked = check_platform()
+_version_checked = check_version()
+
+
+def main() -> None:
+    print(Tiny)
@@ -0,0 +1,10 @@
+class Big:
+    pass
+
+
+class Small:
+    pass
+
+
+class Tiny:
+    pass
@@ -0,0 +1,19 @@
+from registries.registry import Command, Model, Plugin
+
+VERSION = 1
+
+
+class CsvPlugin(Plugin):
+    pass
+
+
+class TsvPlugin(CsvPlugin):
+    pass
+
+
+class GreetCommand(Command):
+    pass
+
+
+class User(Model):
+    pass
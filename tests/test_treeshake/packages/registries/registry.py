@@ -0,0 +1,24 @@
+PLUGINS: dict[str, type] = {}
+COMMANDS: dict[str, type] = {}
+
+
+class Plugin:
+    def __init_subclass__(cls, **kwargs: object) -> None:
+        super().__init_subclass__(**kwargs)
+        PLUGINS[cls.__name__] = cls
+
+
+class CommandMeta(type):
+    def __new__(mcs, name: str, bases: tuple[type, ...], namespace: dict) -> type:
+        cls = super().__new__(mcs, name, bases, namespace)
+        COMMANDS[name] = cls
+        return cls
+
+
+class Command(metaclass=CommandMeta):
+    pass
+
+
+class Model:
+    def __init_subclass__(cls, **kwargs: object) -> None:
+        super().__init_subclass__(**kwargs)
@@ -0,0 +1,12 @@
+from secrets import token_urlsafe
+import random
+
+
+def main() -> None:
+    print("Hooray!")
+    print("Is this your token?", token_urlsafe())
+    print("Is this your lucky number?", random.random())
+
+
+if __name__ == "__main__":
+    main()
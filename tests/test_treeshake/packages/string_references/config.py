@@ -0,0 +1,7 @@
+LOGGING = {
+    "version": 1,
+    "handlers": {
+        "console": {"class": "string_references.handlers.ConsoleHandler"},
+    },
+}
+WORKER = "string_references.workers:run_worker"
@@ -0,0 +1,9 @@
+import logging
+
+
+class ConsoleHandler(logging.StreamHandler):
+    pass
+
+
+class FileHandler(logging.FileHandler):
+    pass
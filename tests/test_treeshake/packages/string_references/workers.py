@@ -0,0 +1,6 @@
+def run_worker() -> None:
+    print("working")
+
+
+def stop_worker() -> None:
+    print("stopping")
@@ -0,0 +1,11 @@
+from .registry import register, route
+
+
+@route
+def index() -> None:
+    pass
+
+
+@register
+def plugin() -> None:
+    pass
@@ -0,0 +1,10 @@
+class UserHandler:
+    pass
+
+
+class OrderHandler:
+    pass
+
+
+def handler_factory() -> None:
+    pass
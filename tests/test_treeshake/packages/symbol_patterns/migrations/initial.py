@@ -0,0 +1,6 @@
+def forwards() -> None:
+    print("forwards")
+
+
+def backwards() -> None:
+    print("backwards")
@@ -0,0 +1,10 @@
+import typing as t
+
+
+def route(func: t.Callable[[], None]) -> t.Callable[[], None]:
+    return func
+
+
+def register(func: t.Callable[[], None]) -> t.Callable[[], None]:
+    print(func)
+    return func
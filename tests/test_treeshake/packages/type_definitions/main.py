@@ -0,0 +1,39 @@
+import typing as t
+
+from type_definitions.models import Big, Bound, Extra, Point, Small
+
+type BigList = list[Big]
+type SmallList = list[Small]
+
+T = t.TypeVar("T", bound=Big)
+P = t.ParamSpec("P")
+S = t.TypeVar("S", bound=Small)
+Ts = t.TypeVarTuple("Ts")
+
+ORIGIN, UNUSED_CLASS = Point, Extra
+WIDTH, HEIGHT = 640, 480
+
+
+def first(items: SmallList) -> Small:
+    return items[0]
+
+
+def pick(item: S) -> S:
+    return item
+
+
+def identity[V, Unused: Bound](item: V) -> V:
+    return item
+
+
+def forward[V](item: "V") -> None:
+    print(item)
+
+
+class Pair[A, B]:
+    pass
+
+
+def main() -> None:
+    print(first([Small()]), pick(Small()), ORIGIN, WIDTH)
+    print(identity(1), forward(2), Pair[int, str]())
@@ -0,0 +1,18 @@
+class Big:
+    pass
+
+
+class Small:
+    pass
+
+
+class Point:
+    pass
+
+
+class Extra:
+    pass
+
+
+class Bound:
+    pass
@@ -0,0 +1,7 @@
+from unused_defaults.helpers import trace
+
+
+def describe(text: str, verbose: bool = False) -> str:
+    if verbose:
+        trace(text)
+    return text
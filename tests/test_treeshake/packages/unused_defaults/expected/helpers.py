@@ -0,0 +1,19 @@
+class UpperFormatter:
+
+    def format(self, text: str) -> str:
+        return text.upper()
+
+
+def trace(text: str) -> None:
+    print("rendering", text)
+
+
+def notify(text: str) -> None:
+    print("rendered", text)
+
+
+def render(text: str, formatter=None, verbose: bool=False, cache: dict[str, str] | None=None, notify_done: bool=True) -> str:
+    notify(text)
+    return formatter.format(text)
+
+
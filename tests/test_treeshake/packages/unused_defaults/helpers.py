@@ -0,0 +1,37 @@
+class DefaultFormatter:
+    def format(self, text: str) -> str:
+        return text
+
+
+class UpperFormatter:
+    def format(self, text: str) -> str:
+        return text.upper()
+
+
+def trace(text: str) -> None:
+    print("rendering", text)
+
+
+def store(cache: dict[str, str], text: str) -> None:
+    cache[text] = text
+
+
+def notify(text: str) -> None:
+    print("rendered", text)
+
+
+def render(
+    text: str,
+    formatter=DefaultFormatter(),
+    verbose: bool = False,
+    cache: dict[str, str] | None = None,
+    notify_done: bool = True,
+) -> str:
+    if verbose:
+        trace(text)
+    if cache is not None:
+        store(cache, text)
+    if not notify_done:
+        return formatter.format(text)
+    notify(text)
+    return formatter.format(text)
@@ -0,0 +1,8 @@
+from unused_defaults.api import describe
+from unused_defaults.helpers import UpperFormatter, render
+
+
+def main() -> None:
+    print(render("a", UpperFormatter()))
+    print(render("b", formatter=UpperFormatter()))
+    print(describe("c"))
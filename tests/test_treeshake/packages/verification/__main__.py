@@ -0,0 +1,3 @@
+from .main import VALUE
+
+print(VALUE)
@@ -0,0 +1,6 @@
+def compute() -> int:
+    return 42
+
+
+# the call is only visible at runtime
+VALUE = eval("compute()")
@@ -0,0 +1,92 @@
+from __future__ import annotations
+from pathlib import Path
+import sys
+import typing as t
+import pytest
+from flay.testing.differential import (
+    ModuleSnapshot,
+    SymbolSnapshot,
+    compare_bundle,
+    diff_snapshots,
+)
+
+TEST_PACKAGES_DIR = Path(__file__).parent / "packages"
+if t.TYPE_CHECKING:
+    from .conftest import RunTreeshakePackageT
+
+
+def test_diff_snapshots() -> None:
+    original = {
+        "pkg": ModuleSnapshot(
+            exports=["Base", "run"],
+            names={
+                "Base": SymbolSnapshot(
+                    kind="class",
+                    signature="()",
+                    resolution_order=["pkg.Base", "builtins.object"],
+                    members={"start": "(self)", "stop": "(self)"},
+                ),
+                "run": SymbolSnapshot(kind="function", signature="(x=1)"),
+                "unused": SymbolSnapshot(kind="function", signature="()"),
+            },
+        ),
+        "pkg.broken": ModuleSnapshot(error="ImportError: missing dependency"),
+    }
+    flayed = {
+        "pkg": ModuleSnapshot(
+            exports=["Base", "run"],
+            names={
+                "Base": SymbolSnapshot(
+                    kind="class",
+                    signature="()",
+                    resolution_order=["pkg.Base", "builtins.object"],
+                    members={"start": "(self)"},
+                ),
+                "run": SymbolSnapshot(kind="function", signature="(x=None)"),
+            },
+        ),
+        "pkg.broken": ModuleSnapshot(error="ImportError: missing dependency"),
+    }
+
+    result = diff_snapshots(original, flayed)
+
+    assert [(d.module_spec, d.kind, d.name) for d in result.differences] == [
+        ("pkg", "signature", "run")
+    ]
+    assert result.removed_names == {"pkg": ["unused"]}
+
+
+def test_differential_dead_code(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    result_path = run_treeshake_package(
+        TEST_PACKAGES_DIR / "dead_code", preserve_symbols={"dead_code.main.main"}
+    )
+
+    result = compare_bundle(result_path.parent, ["dead_code"], TEST_PACKAGES_DIR)
+
+    assert result.differences == []
+    assert "legacy" in result.removed_names["dead_code.helpers"]
+    assert "shutil" in result.removed_names["dead_code.main"]
+
+
+@pytest.mark.skipif(
+    sys.version_info < (3, 10), reason="test package uses union annotations"
+)
+def test_differential_unused_defaults_closed_world(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    result_path = run_treeshake_package(
+        TEST_PACKAGES_DIR / "unused_defaults",
+        preserve_symbols={"unused_defaults.main.main", "unused_defaults.api.describe"},
+        closed_world_packages=["unused_defaults"],
+    )
+
+    result = compare_bundle(result_path.parent, ["unused_defaults"], TEST_PACKAGES_DIR)
+
+    # the default of formatter was replaced, callers passing it are not affected,
+    # main imports render, so it is part of its names as well
+    assert [(d.module_spec, d.kind, d.name) for d in result.differences] == [
+        ("unused_defaults.helpers", "signature", "render"),
+        ("unused_defaults.main", "signature", "render"),
+    ]
@@ -0,0 +1,86 @@
+from __future__ import annotations
+from pathlib import Path
+import os
+import shutil
+import typing as t
+import pytest
+
+if t.TYPE_CHECKING:
+    from .conftest import RunTreeshakePackageT
+
+TEST_PACKAGES_DIR = Path(__file__).parent / "packages"
+# expected output of a fixture, a directory next to its sources
+SNAPSHOT_DIR_NAME = "expected"
+# rewrite the snapshots with the current output instead of comparing them
+UPDATE_SNAPSHOTS = bool(os.environ.get("FLAY_UPDATE_SNAPSHOTS"))
+# snapshots are committed, so a missing one is only tolerated locally
+IS_CI = bool(os.environ.get("CI"))
+
+# fixture -> options passed to the treeshaking
+SNAPSHOT_FIXTURES: dict[str, dict[str, t.Any]] = {
+    "call_func_on_module_in_class": {},
+    "class_bases": {},
+    "dead_code": {"preserve_symbols": {"dead_code.main.main"}},
+    "import_aliases": {
+        "import_aliases": {
+            "import_aliases.bundle1.func1": "import_aliases.bundle2.func1"
+        }
+    },
+    "lexical_scopes": {},
+    "pure_calls": {"preserve_symbols": {"pure_calls.main.main"}},
+    "remove_unused_import": {},
+    "unused_defaults": {
+        "preserve_symbols": {
+            "unused_defaults.main.main",
+            "unused_defaults.api.describe",
+        },
+        "closed_world_packages": ["unused_defaults"],
+    },
+}
+
+
+def read_tree(path: Path) -> dict[str, bytes]:
+    return {
+        file_path.relative_to(path).as_posix(): file_path.read_bytes()
+        for file_path in sorted(path.rglob("*"))
+        if file_path.is_file() and "__pycache__" not in file_path.parts
+    }
+
+
+def write_tree(path: Path, tree: dict[str, bytes]) -> None:
+    if path.exists():
+        shutil.rmtree(str(path))
+    for relative_path, content in tree.items():
+        file_path = path / relative_path
+        file_path.parent.mkdir(parents=True, exist_ok=True)
+        file_path.write_bytes(content)
+
+
+@pytest.mark.parametrize("fixture_name", sorted(SNAPSHOT_FIXTURES))
+def test_treeshake_snapshot(
+    fixture_name: str, run_treeshake_package: RunTreeshakePackageT
+) -> None:
+    source_path = TEST_PACKAGES_DIR / fixture_name
+    snapshot_path = source_path / SNAPSHOT_DIR_NAME
+    result_path = run_treeshake_package(
+        source_path, **SNAPSHOT_FIXTURES[fixture_name]
+    )
+    result_tree = read_tree(result_path)
+
+    if UPDATE_SNAPSHOTS:
+        write_tree(snapshot_path, result_tree)
+        return
+    if not snapshot_path.is_dir():
+        message = (
+            f"No snapshot of {fixture_name}, record it with FLAY_UPDATE_SNAPSHOTS=1"
+        )
+        if IS_CI:
+            pytest.fail(message)
+        pytest.skip(message)
+
+    expected_tree = read_tree(snapshot_path)
+    assert sorted(result_tree) == sorted(expected_tree)
+    for relative_path, content in result_tree.items():
+        # compared as text to get a readable diff, the bytes have to be equal anyway
+        assert content.decode() == expected_tree[relative_path].decode(), relative_path
+        assert content == expected_tree[relative_path], relative_path
@@ -2,6 +2,12 @@ from __future__ import annotations
 from pathlib import Path
 import typing as t
 import os
+import shutil
+import pytest
+from flay._flay_rs import MemoryCache
+from flay.treeshake.package import treeshake_package
+from flay.treeshake.verify import TreeshakeVerificationError
+from flay.trace import Trace, write_trace
 
 
 TEST_DIR = Path(__file__).parent
@@ -326,3 +332,545 @@ def test_treeshake_package_preserve_symbols(
     assert bundle3.exists()
     assert "def func1() -> None:" in bundle3.read_text()
     assert not (result_path / "useless.py").exists()
+
+
+def test_treeshake_package_cache(tmp_path: Path) -> None:
+    cache_dir = tmp_path / "cache"
+    results: list[dict[str, str]] = []
+    for run in ("first", "second"):
+        target_path = tmp_path / run
+        shutil.copytree(
+            str(TEST_PACKAGES_DIR / "re_exports"), str(target_path / "re_exports")
+        )
+        treeshake_package(str(target_path), cache_dir=cache_dir)
+        results.append(
+            {
+                str(path.relative_to(target_path)): path.read_text()
+                for path in target_path.rglob("*.py")
+            }
+        )
+
+    assert list(cache_dir.iterdir())
+    assert results[0] == results[1]
+
+
+def test_treeshake_package_cache_bundled_modules(tmp_path: Path) -> None:
+    cache_dir = tmp_path / "cache"
+    for run in ("first", "second"):
+        package_path = tmp_path / run / "app"
+        package_path.mkdir(parents=True)
+        (package_path / "__init__.py").write_text('HANDLER = "app.handlers:handle"\n')
+        if run == "second":
+            # added to the bundle while the module referencing it is unchanged
+            (package_path / "handlers.py").write_text(
+                "def handle() -> None:\n    pass\n"
+            )
+        treeshake_package(
+            str(tmp_path / run),
+            preserve_symbols={"app.HANDLER"},
+            string_references=True,
+            cache_dir=cache_dir,
+        )
+
+    assert "def handle(" in (package_path / "handlers.py").read_text()
+
+
+def test_treeshake_package_forward_references(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "forward_references"
+    result_path = run_treeshake_package(source_path)
+
+    init_file_content = (result_path / "__init__.py").read_text()
+    assert "from __future__ import annotations" in init_file_content
+    assert "CastTarget" in init_file_content
+
+    models_file_content = (result_path / "models.py").read_text()
+    assert "from __future__ import annotations" in models_file_content
+    assert "class Item:" in models_file_content
+    assert "class CastTarget:" in models_file_content
+    assert "class Bound:" in models_file_content
+    assert "class Unused:" not in models_file_content
+
+
+def test_treeshake_package_lexical_scopes(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "lexical_scopes"
+    result_path = run_treeshake_package(source_path)
+    init_file_content = (result_path / "__init__.py").read_text()
+
+    assert "def used() -> int:" in init_file_content
+    # names bound by comprehensions, lambdas and except handlers are local
+    assert "def value() -> int:" not in init_file_content
+    assert "def helper() -> int:" not in init_file_content
+    assert "def error() -> None:" not in init_file_content
+
+
+def test_treeshake_package_global_nonlocal(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "global_nonlocal"
+    result_path = run_treeshake_package(source_path)
+    init_file_content = (result_path / "__init__.py").read_text()
+    init_file_lines = init_file_content.splitlines()
+
+    assert "counter = 0" in init_file_lines
+    assert "unused_counter = 0" not in init_file_lines
+    # `global value` skips the binding in the enclosing function
+    assert 'value = "module"' in init_file_lines
+    assert "nonlocal count" in init_file_content
+
+
+def test_treeshake_package_dunder_all(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "dunder_all"
+    result_path = run_treeshake_package(source_path)
+    api_file_content = (result_path / "api.py").read_text()
+
+    assert "def public() -> str:" in api_file_content
+    assert "def extra() -> str:" in api_file_content
+    # `helper` is not in `__all__`, so the star import does not bind it
+    assert "def helper() -> str:" not in api_file_content
+    assert "def shared() -> str:" in (result_path / "base.py").read_text()
+
+    private_file_content = (result_path / "private.py").read_text()
+
+    assert "def visible() -> str:" in private_file_content
+    # without `__all__` names starting with an underscore are not imported
+    assert "def _private() -> str:" not in private_file_content
+
+
+def test_treeshake_package_preserve_exports(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "preserve_exports"
+    result_path = run_treeshake_package(
+        source_path, preserve_exports=["preserve_exports"]
+    )
+    core_file_content = (result_path / "core.py").read_text()
+
+    assert "def exported() -> str:" in core_file_content
+    assert "def internal() -> str:" not in core_file_content
+
+
+def test_treeshake_package_library_mode(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "library_mode"
+    result_path = run_treeshake_package(
+        source_path,
+        library_include=["library_mode.**"],
+        library_exclude=["library_mode.testing.*"],
+    )
+    init_file_content = (result_path / "__init__.py").read_text()
+
+    assert "from .client import Client" in init_file_content
+    assert "_internal_helper()" in init_file_content
+
+    client_file_content = (result_path / "client.py").read_text()
+
+    assert "class Client:" in client_file_content
+    assert "def create_client() -> Client:" in client_file_content
+    assert "def _connect() -> str:" in client_file_content
+    assert "def _unused_private() -> str:" not in client_file_content
+
+    assert not (result_path / "testing.py").exists()
+
+    internal_file_content = (result_path / "_internal.py").read_text()
+
+    assert "def internal_helper() -> None:" in internal_file_content
+    assert "def internal_unused() -> None:" not in internal_file_content
+
+
+def test_treeshake_package_symbol_patterns(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "symbol_patterns"
+    result_path = run_treeshake_package(
+        source_path,
+        preserve_symbols={
+            "symbol_patterns.migrations.**",
+            "!symbol_patterns.migrations.initial.backwards",
+            r"re:symbol_patterns\.handlers\.\w+Handler",
+        },
+        safe_decorators={"**.route"},
+    )
+    init_file_content = (result_path / "__init__.py").read_text()
+
+    assert "def index() -> None:" not in init_file_content
+    assert "@register\ndef plugin() -> None:" in init_file_content
+
+    migration_file_content = (result_path / "migrations" / "initial.py").read_text()
+
+    assert "def forwards() -> None:" in migration_file_content
+    assert "def backwards() -> None:" not in migration_file_content
+
+    handlers_file_content = (result_path / "handlers.py").read_text()
+
+    assert "class UserHandler:" in handlers_file_content
+    assert "class OrderHandler:" in handlers_file_content
+    assert "def handler_factory() -> None:" not in handlers_file_content
+
+
+def test_treeshake_package_django_project(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "django_project"
+    result_path = run_treeshake_package(
+        source_path, django_settings="django_project.settings"
+    )
+    settings_file_content = (result_path / "settings.py").read_text()
+
+    assert "INSTALLED_APPS = " in settings_file_content
+    assert "ROOT_URLCONF = " in settings_file_content
+
+    base_settings_file_content = (result_path / "base_settings.py").read_text()
+
+    assert 'SECRET_KEY = "not-a-secret"' in base_settings_file_content
+    assert "def get_database_name() -> str:" not in base_settings_file_content
+
+    middleware_file_content = (result_path / "middleware.py").read_text()
+
+    assert "class TimingMiddleware:" in middleware_file_content
+    assert "class UnusedMiddleware:" not in middleware_file_content
+
+    assert "urlpatterns: list[object] = []" in (result_path / "urls.py").read_text()
+
+    blog_path = result_path / "blog"
+
+    assert "class BlogConfig:" in (blog_path / "apps.py").read_text()
+    assert "class Post:" in (blog_path / "models.py").read_text()
+    # the module is empty without the unused function
+    assert not (blog_path / "utils.py").exists()
+    assert "class Migration:" in (
+        blog_path / "migrations" / "0001_initial.py"
+    ).read_text()
+
+
+def test_treeshake_package_string_references(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "string_references"
+    result_path = run_treeshake_package(
+        source_path,
+        preserve_symbols={
+            "string_references.config.LOGGING",
+            "string_references.config.WORKER",
+        },
+        string_references=True,
+    )
+    handlers_file_content = (result_path / "handlers.py").read_text()
+
+    assert "class ConsoleHandler(logging.StreamHandler):" in handlers_file_content
+    assert "class FileHandler(logging.FileHandler):" not in handlers_file_content
+
+    workers_file_content = (result_path / "workers.py").read_text()
+
+    assert "def run_worker() -> None:" in workers_file_content
+    assert "def stop_worker() -> None:" not in workers_file_content
+
+
+def test_treeshake_package_dynamic_attributes(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "dynamic_attributes"
+    result_path = run_treeshake_package(
+        source_path,
+        preserve_symbols={
+            "dynamic_attributes.registry.dispatch",
+            "dynamic_attributes.registry.load_plugin",
+        },
+    )
+    handlers_file_content = (result_path / "handlers.py").read_text()
+
+    assert "def on_create() -> None:" in handlers_file_content
+    assert "def on_delete() -> None:" in handlers_file_content
+    assert "def on_update() -> None:" in handlers_file_content
+    assert "def on_rename() -> None:" in handlers_file_content
+    assert "def unused() -> None:" not in handlers_file_content
+
+    # plugins are looked up with a name only known at runtime
+    plugins_file_content = (result_path / "plugins.py").read_text()
+
+    assert "def alpha() -> None:" in plugins_file_content
+    assert "def beta() -> None:" in plugins_file_content
+
+
+def test_treeshake_package_registries(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "registries"
+    result_path = run_treeshake_package(
+        source_path,
+        preserve_symbols={
+            "registries.plugins.VERSION",
+            "registries.registry.PLUGINS",
+            "registries.registry.COMMANDS",
+        },
+        non_registering_bases={"registries.registry.Model"},
+    )
+    plugins_file_content = (result_path / "plugins.py").read_text()
+
+    assert "class CsvPlugin(Plugin):" in plugins_file_content
+    assert "class TsvPlugin(CsvPlugin):" in plugins_file_content
+    assert "class GreetCommand(Command):" in plugins_file_content
+    assert "class User(Model):" not in plugins_file_content
+
+
+def test_treeshake_package_pure_calls(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "pure_calls"
+    result_path = run_treeshake_package(
+        source_path, preserve_symbols={"pure_calls.main.main"}
+    )
+    main_file_content = (result_path / "main.py").read_text()
+    models_file_content = (result_path / "models.py").read_text()
+    helpers_file_content = (result_path / "helpers.py").read_text()
+
+    assert "logger = " not in main_file_content
+    assert "T = " not in main_file_content
+    assert "PATTERN = " not in main_file_content
+    assert "class Big:" not in models_file_content
+    assert "class Tiny:" in models_file_content
+    # functions only calling pure functions are pure as well
+    assert "def make_label(" not in helpers_file_content
+    assert "def make_name(" not in helpers_file_content
+    # methods of unknown objects might have side-effects
+    assert "def shout(" in helpers_file_content
+    assert "def register(" in helpers_file_content
+    assert "class Small:" in models_file_content
+    # raising or asserting on import is a side-effect
+    assert "_platform_checked = check_platform()" in main_file_content
+    assert "_version_checked = check_version()" in main_file_content
+    assert "def check_platform(" in helpers_file_content
+    assert "def check_version(" in helpers_file_content
+
+
+def test_treeshake_package_pure_callables_option(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "pure_calls"
+    result_path = run_treeshake_package(
+        source_path,
+        preserve_symbols={"pure_calls.main.main"},
+        pure_callables={"pure_calls.helpers.shout", "pure_calls.helpers.register"},
+    )
+    models_file_content = (result_path / "models.py").read_text()
+    helpers_file_content = (result_path / "helpers.py").read_text()
+
+    assert "def shout(" not in helpers_file_content
+    assert "def register(" not in helpers_file_content
+    assert "class Small:" not in models_file_content
+
+
+def test_treeshake_package_type_definitions(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "type_definitions"
+    result_path = run_treeshake_package(
+        source_path, preserve_symbols={"type_definitions.main.main"}
+    )
+    main_file_content = (result_path / "main.py").read_text()
+    models_file_content = (result_path / "models.py").read_text()
+
+    assert "type BigList" not in main_file_content
+    assert "type SmallList = list[Small]" in main_file_content
+    assert 'T = t.TypeVar("T"' not in main_file_content
+    assert 'P = t.ParamSpec("P")' not in main_file_content
+    assert 'S = t.TypeVar("S", bound=Small)' in main_file_content
+    assert "class Big:" not in models_file_content
+    assert "class Small:" in models_file_content
+    # unused targets of unpacking assignments are removed with their values
+    assert "ORIGIN = Point" in main_file_content
+    assert "WIDTH = 640" in main_file_content
+    assert "HEIGHT" not in main_file_content
+    assert "class Extra:" not in models_file_content
+    assert "class Point:" in models_file_content
+    assert 'Ts = t.TypeVarTuple("Ts")' not in main_file_content
+    # unused type parameters of functions are removed with their bounds
+    assert "def identity[V](item: V) -> V:" in main_file_content
+    assert "class Bound:" not in models_file_content
+    assert 'def forward[V](item: "V") -> None:' in main_file_content
+    # type parameters of classes define the arguments of `Pair[int, str]`
+    assert "class Pair[A, B]:" in main_file_content
+
+
+def test_treeshake_package_dead_code(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "dead_code"
+    result_path = run_treeshake_package(
+        source_path, preserve_symbols={"dead_code.main.main"}
+    )
+    main_file_content = (result_path / "main.py").read_text()
+    helpers_file_content = (result_path / "helpers.py").read_text()
+
+    assert "legacy" not in main_file_content
+    assert "shutil" not in main_file_content
+    assert "unused_branch" not in main_file_content
+    assert "def legacy(" not in helpers_file_content
+    assert "def unused_branch(" not in helpers_file_content
+    # a yield makes the function a generator, even if it is never reached
+    assert "yield kept_by_yield()" in main_file_content
+    # binding a name makes it local to the function, even if it is never reached
+    assert "total = kept_by_binding()" in main_file_content
+    assert "def kept_by_binding(" in helpers_file_content
+    assert "if __debug__:" in main_file_content
+    assert "def debug_only(" in helpers_file_content
+    assert "assert checked()" in main_file_content
+
+
+def test_treeshake_package_dead_code_strip_debug(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "dead_code"
+    result_path = run_treeshake_package(
+        source_path, preserve_symbols={"dead_code.main.main"}, strip_debug=True
+    )
+    main_file_content = (result_path / "main.py").read_text()
+    helpers_file_content = (result_path / "helpers.py").read_text()
+
+    assert "__debug__" not in main_file_content
+    assert "assert" not in main_file_content
+    assert "def debug_only(" not in helpers_file_content
+    assert "def checked(" not in helpers_file_content
+
+
+def test_treeshake_package_unused_defaults(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "unused_defaults"
+    preserve_symbols = {"unused_defaults.main.main", "unused_defaults.api.describe"}
+    result_path = run_treeshake_package(source_path, preserve_symbols=preserve_symbols)
+    helpers_file_content = (result_path / "helpers.py").read_text()
+
+    assert "class DefaultFormatter" in helpers_file_content
+    assert "def store(" in helpers_file_content
+
+
+def test_treeshake_package_unused_defaults_closed_world(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "unused_defaults"
+    preserve_symbols = {"unused_defaults.main.main", "unused_defaults.api.describe"}
+    result_path = run_treeshake_package(
+        source_path,
+        preserve_symbols=preserve_symbols,
+        closed_world_packages=["unused_defaults"],
+    )
+    helpers_file_content = (result_path / "helpers.py").read_text()
+    api_file_content = (result_path / "api.py").read_text()
+
+    # formatter is passed by every call
+    assert "class DefaultFormatter" not in helpers_file_content
+    assert "class UpperFormatter" in helpers_file_content
+    # verbose, cache and notify_done are never passed
+    assert "trace(text)" not in helpers_file_content
+    assert "def store(" not in helpers_file_content
+    assert "if not notify_done" not in helpers_file_content
+    assert "notify(text)" in helpers_file_content
+    # preserved functions might be called with any arguments
+    assert "if verbose:" in api_file_content
+    assert "def trace(" in helpers_file_content
+
+
+def test_treeshake_package_memory_cache(tmp_path: Path) -> None:
+    memory_cache = MemoryCache()
+    results: list[str] = []
+    entries_counts: list[int] = []
+    for run in ("first", "second"):
+        shutil.copytree(
+            str(TEST_PACKAGES_DIR / "remove_unused_import"),
+            str(tmp_path / run / "remove_unused_import"),
+            ignore=shutil.ignore_patterns("expected"),
+        )
+        treeshake_package(str(tmp_path / run), memory_cache=memory_cache)
+        results.append(
+            (tmp_path / run / "remove_unused_import" / "__init__.py").read_text()
+        )
+        entries_counts.append(len(memory_cache))
+
+    # the journals of the first run are replayed by the second one
+    assert entries_counts[0]
+    assert entries_counts[0] == entries_counts[1]
+    assert results[0] == results[1]
+
+
+def test_treeshake_package_verify(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "verification"
+    with pytest.raises(TreeshakeVerificationError) as exc_info:
+        run_treeshake_package(source_path, verify=True)
+
+    [failure] = exc_info.value.failures
+    assert failure.source == "verification.main"
+    assert failure.error == "NameError: name 'compute' is not defined"
+    assert failure.removed_symbol == "verification.main.compute"
+    assert failure.removed_statement == (
+        "verification.main",
+        1,
+        "def compute() -> int:",
+    )
+
+
+def test_treeshake_package_verify_smoke_command(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    source_path = TEST_PACKAGES_DIR / "verification"
+    with pytest.raises(TreeshakeVerificationError) as exc_info:
+        run_treeshake_package(
+            source_path, smoke_command='python -c "import verification.main"'
+        )
+
+    [failure] = exc_info.value.failures
+    # tracebacks might suggest similar names
+    assert failure.error.startswith("NameError: name 'compute' is not defined")
+    assert failure.removed_symbol == "verification.main.compute"
+
+
+def test_treeshake_package_verify_smoke_command_isolated(
+    run_treeshake_package: RunTreeshakePackageT,
+    monkeypatch: pytest.MonkeyPatch,
+) -> None:
+    # the untreeshaken package is importable from the working directory
+    monkeypatch.chdir(TEST_PACKAGES_DIR)
+    with pytest.raises(TreeshakeVerificationError) as exc_info:
+        run_treeshake_package(
+            TEST_PACKAGES_DIR / "remove_empty_modules",
+            smoke_command='python -c "import remove_empty_modules.unused"',
+        )
+
+    [failure] = exc_info.value.failures
+    assert failure.error == (
+        "ModuleNotFoundError: No module named 'remove_empty_modules.unused'"
+    )
+
+
+def test_treeshake_package_verify_preserved(
+    run_treeshake_package: RunTreeshakePackageT,
+) -> None:
+    result_path = run_treeshake_package(
+        TEST_PACKAGES_DIR / "verification",
+        preserve_symbols={"verification.main.compute"},
+        verify=True,
+        smoke_command='python -c "import verification.main"',
+    )
+
+    assert "def compute(" in (result_path / "main.py").read_text()
+
+
+def test_treeshake_package_trace_files(
+    run_treeshake_package: RunTreeshakePackageT,
+    tmp_path_factory: pytest.TempPathFactory,
+) -> None:
+    trace_file = tmp_path_factory.mktemp("traces") / "trace.json"
+    write_trace(Trace(symbols=["verification.main.compute"]), trace_file)
+    result_path = run_treeshake_package(
+        TEST_PACKAGES_DIR / "verification", trace_files=[trace_file], verify=True
+    )
+
+    assert "def compute(" in (result_path / "main.py").read_text()